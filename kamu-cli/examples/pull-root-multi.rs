@@ -14,7 +14,9 @@ fn main() {
         ["a"].iter(),
         false,
         false,
+        false,
         &OutputFormat::default(),
+        CancellationToken::new(),
     );
     cmd.run().unwrap();
 }
@@ -65,6 +67,7 @@ impl TestPullService {
         let hash = "a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a";
         let result = IngestResult::Updated {
             block_hash: hash.to_owned(),
+            stats: UpdateStats::default(),
         };
         listener.success(&result);
 
@@ -72,6 +75,7 @@ impl TestPullService {
             id,
             Ok(PullResult::Updated {
                 block_hash: hash.to_owned(),
+                stats: UpdateStats::default(),
             }),
         )
     }
@@ -102,4 +106,13 @@ impl PullService for TestPullService {
 
         handles.into_iter().map(|h| h.join().unwrap()).collect()
     }
+
+    fn plan_pull(
+        &mut self,
+        _dataset_ids: &mut dyn Iterator<Item = &DatasetID>,
+        _recursive: bool,
+        _all: bool,
+    ) -> Result<Vec<PullPlanStep>, PullError> {
+        Ok(Vec::new())
+    }
 }
@@ -14,7 +14,9 @@ fn main() {
         ["a"].iter(),
         false,
         false,
+        false,
         &OutputFormat::default(),
+        CancellationToken::new(),
     );
     cmd.run().unwrap();
 }
@@ -68,13 +70,24 @@ impl PullService for TestPullService {
         let hash = "a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a";
         let result = IngestResult::Updated {
             block_hash: hash.to_owned(),
+            stats: UpdateStats::default(),
         };
         listener.success(&result);
         vec![(
             id,
             Ok(PullResult::Updated {
                 block_hash: hash.to_owned(),
+                stats: UpdateStats::default(),
             }),
         )]
     }
+
+    fn plan_pull(
+        &mut self,
+        _dataset_ids: &mut dyn Iterator<Item = &DatasetID>,
+        _recursive: bool,
+        _all: bool,
+    ) -> Result<Vec<PullPlanStep>, PullError> {
+        Ok(Vec::new())
+    }
 }
@@ -11,6 +11,11 @@ pub fn cli(binary_name: &'static str, version: &'static str) -> App<'static, 'st
                 .multiple(true)
                 .help("Sets the level of verbosity (repeat for more)"),
         )
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .help("Print results as JSON instead of tables where supported"),
+        )
         .subcommands(vec![
             SubCommand::with_name("add")
                 .about("Add a new dataset or modify an existing one")
@@ -87,6 +92,35 @@ pub fn cli(binary_name: &'static str, version: &'static str) -> App<'static, 'st
                         .long("yes")
                         .help("Don't ask for confirmation"),
                 ),
+            SubCommand::with_name("ingest")
+                .about("Ingest data from a file or standard input into a root dataset")
+                .after_help(indoc::indoc!(
+                    r"
+                    Runs the prepare, read and merge steps of the dataset's source on the supplied
+                    data instead of fetching it. Use `-` to read the data from standard input:
+
+                        cat data.csv | kamu ingest my.dataset -
+                    "
+                ))
+                .arg(
+                    Arg::with_name("dataset")
+                        .required(true)
+                        .index(1)
+                        .help("ID of the dataset"),
+                )
+                .arg(
+                    Arg::with_name("file")
+                        .required(true)
+                        .index(2)
+                        .help("Path to the data file or `-` for standard input"),
+                )
+                .arg(
+                    Arg::with_name("event-time")
+                        .long("event-time")
+                        .takes_value(true)
+                        .value_name("T")
+                        .help("Event time (RFC3339) of the records that don't specify it"),
+                ),
             SubCommand::with_name("init")
                 .about("Initialize an empty workspace in the current directory")
                 .arg(
@@ -94,6 +128,19 @@ pub fn cli(binary_name: &'static str, version: &'static str) -> App<'static, 'st
                         .long("pull-images")
                         .help("Only pull docker images and exit"),
                 ),
+            SubCommand::with_name("inspect")
+                .about("Inspect the structure of a dataset")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("schema")
+                        .about("Show current schema of the dataset and how it changed over time")
+                        .arg(
+                            Arg::with_name("dataset")
+                                .required(true)
+                                .index(1)
+                                .help("ID of the dataset"),
+                        ),
+                ),
             SubCommand::with_name("list")
                 .about("List all datasets in the workspace")
                 .subcommand(
@@ -169,6 +216,82 @@ pub fn cli(binary_name: &'static str, version: &'static str) -> App<'static, 'st
                         .long("recursive")
                         .help("Also pull all transitive dependencies of specified datasets"),
                 )
+                .arg(
+                    Arg::with_name("keep-going")
+                        .long("keep-going")
+                        .help("Don't treat the pull as failed when some datasets had errors"),
+                )
+                .arg(
+                    Arg::with_name("dry-run")
+                        .long("dry-run")
+                        .conflicts_with_all(&["all", "recursive", "keep-going"])
+                        .help("Fetch and read the data into a temporary location and show a preview without committing anything"),
+                )
+                .arg(
+                    Arg::with_name("watch")
+                        .long("watch")
+                        .conflicts_with_all(&["all", "recursive", "keep-going", "dry-run", "dataset"])
+                        .help("Keep running and pull root datasets according to their poll schedules or when their local source files change, along with their dependents"),
+                )
+                .arg(
+                    Arg::with_name("plan")
+                        .long("plan")
+                        .conflicts_with_all(&["keep-going", "dry-run", "watch"])
+                        .help("Show which datasets would be pulled, in what order and with which inputs, without running anything"),
+                )
+                .arg(
+                    Arg::with_name("jobs")
+                        .short("j")
+                        .long("jobs")
+                        .takes_value(true)
+                        .value_name("N")
+                        .default_value("4")
+                        .help("Maximum number of datasets to process in parallel"),
+                )
+                .arg(
+                    Arg::with_name("engine-jobs")
+                        .long("engine-jobs")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .value_name("ENGINE=N")
+                        .validator(|v| match v.split('=').collect::<Vec<_>>()[..] {
+                            [engine, n] if !engine.is_empty() && n.parse::<usize>().is_ok() => Ok(()),
+                            _ => Err("Expected a value like sparkSQL=2".to_owned()),
+                        })
+                        .help("Maximum number of datasets to process in parallel by the specific engine"),
+                )
+                .arg(
+                    Arg::with_name("batch-blocks")
+                        .long("batch-blocks")
+                        .takes_value(true)
+                        .value_name("N")
+                        .validator(|v| match v.parse::<usize>() {
+                            Ok(n) if n > 0 => Ok(()),
+                            _ => Err("Expected a positive number".to_owned()),
+                        })
+                        .help("Maximum number of blocks of each input to process in one transformation batch"),
+                )
+                .arg(
+                    Arg::with_name("batch-interval")
+                        .long("batch-interval")
+                        .takes_value(true)
+                        .value_name("DURATION")
+                        .validator(|v| {
+                            kamu::infra::parse_interval(&v)
+                                .map(|_| ())
+                                .map_err(|e| e.to_string())
+                        })
+                        .help("Maximum system time span of input blocks to process in one transformation batch (e.g. 12h or 7d)"),
+                )
+                .arg(
+                    Arg::with_name("preview-rows")
+                        .long("preview-rows")
+                        .takes_value(true)
+                        .value_name("N")
+                        .default_value("10")
+                        .help("Number of rows to show in the dry run preview"),
+                )
                 .arg(
                     Arg::with_name("dataset")
                         .multiple(true)
@@ -235,5 +358,41 @@ pub fn cli(binary_name: &'static str, version: &'static str) -> App<'static, 'st
                         .value_name("FILE")
                         .help("SQL script file to execute"),
                 ),
+            SubCommand::with_name("watermark")
+                .about("Manage the event time watermarks of datasets")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .after_help(indoc::indoc!(
+                    r"
+                    Watermark is the event time up to which all data is believed to be present.
+                    Advancing it manually on a root dataset signals that no more late data will
+                    arrive, which lets windowed aggregations downstream produce their results.
+                    "
+                ))
+                .subcommand(
+                    SubCommand::with_name("set")
+                        .about("Advance the watermark of a root dataset")
+                        .arg(
+                            Arg::with_name("dataset")
+                                .required(true)
+                                .index(1)
+                                .help("ID of the dataset"),
+                        )
+                        .arg(
+                            Arg::with_name("watermark")
+                                .required(true)
+                                .index(2)
+                                .help("Event time (RFC3339) of the new watermark"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("show")
+                        .about("Show current watermarks of datasets")
+                        .arg(
+                            Arg::with_name("dataset")
+                                .multiple(true)
+                                .index(1)
+                                .help("IDs of the datasets, all datasets if not specified"),
+                        ),
+                ),
         ])
 }
@@ -3,9 +3,12 @@ use kamu::domain::*;
 use kamu::infra::serde::yaml::*;
 
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::io::Read;
 use std::rc::Rc;
 
+type LoadResult = Result<(DatasetSnapshot, DatasetConfig), DomainError>;
+
 pub struct AddCommand {
     resource_loader: Rc<RefCell<dyn ResourceLoader>>,
     metadata_repo: Rc<RefCell<dyn MetadataRepository>>,
@@ -33,7 +36,7 @@ impl AddCommand {
 }
 
 impl AddCommand {
-    fn load_specific(&self) -> Vec<(String, Result<DatasetSnapshot, DomainError>)> {
+    fn load_specific(&self) -> Vec<(String, LoadResult)> {
         self.snapshot_refs
             .iter()
             .map(|r| {
@@ -47,7 +50,7 @@ impl AddCommand {
             .collect()
     }
 
-    fn load_recursive(&self) -> Vec<(String, Result<DatasetSnapshot, DomainError>)> {
+    fn load_recursive(&self) -> Vec<(String, LoadResult)> {
         self.snapshot_refs
             .iter()
             .map(|r| std::path::Path::new(r).join("**").join("*.yaml"))
@@ -105,12 +108,17 @@ impl Command for AddCommand {
             return Err(Error::Aborted);
         }
 
+        let mut configs = HashMap::new();
+
         let mut add_results =
             self.metadata_repo
                 .borrow_mut()
                 .add_datasets(
                     &mut load_results.into_iter().filter_map(|(_, res)| match res {
-                        Ok(snapshot) => Some(snapshot),
+                        Ok((snapshot, config)) => {
+                            configs.insert(snapshot.id.clone(), config);
+                            Some(snapshot)
+                        }
                         _ => None,
                     }),
                 );
@@ -118,6 +126,13 @@ impl Command for AddCommand {
         add_results.sort_by(|(id_a, _), (id_b, _)| id_a.cmp(&id_b));
 
         for (id, res) in add_results {
+            // Existing datasets keep their configuration
+            let res = res.and_then(|_| {
+                self.metadata_repo
+                    .borrow_mut()
+                    .set_config(&id, configs.remove(&id).unwrap_or_default())
+            });
+
             match res {
                 Ok(_) => {
                     added += 1;
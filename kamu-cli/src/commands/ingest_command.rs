@@ -0,0 +1,85 @@
+use super::{Command, Error};
+use kamu::domain::*;
+use kamu::infra::serde::yaml::*;
+
+use chrono::{DateTime, Utc};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub struct IngestCommand {
+    ingest_svc: Rc<RefCell<dyn IngestService>>,
+    metadata_repo: Rc<RefCell<dyn MetadataRepository>>,
+    dataset_id: DatasetIDBuf,
+    input: String,
+    event_time: Option<String>,
+}
+
+impl IngestCommand {
+    pub fn new(
+        ingest_svc: Rc<RefCell<dyn IngestService>>,
+        metadata_repo: Rc<RefCell<dyn MetadataRepository>>,
+        dataset_id: DatasetIDBuf,
+        input: &str,
+        event_time: Option<&str>,
+    ) -> Self {
+        Self {
+            ingest_svc: ingest_svc,
+            metadata_repo: metadata_repo,
+            dataset_id: dataset_id,
+            input: input.to_owned(),
+            event_time: event_time.map(|s| s.to_owned()),
+        }
+    }
+
+    fn parse_event_time(&self) -> Result<Option<DateTime<Utc>>, Error> {
+        match self.event_time {
+            None => Ok(None),
+            Some(ref s) => DateTime::parse_from_rfc3339(s)
+                .map(|dt| Some(dt.into()))
+                .map_err(|_| Error::UsageError {
+                    msg: format!("Invalid event time {}, expected RFC3339 format", s),
+                }),
+        }
+    }
+}
+
+impl Command for IngestCommand {
+    fn run(&mut self) -> Result<(), Error> {
+        let event_time = self.parse_event_time()?;
+
+        let summary = self.metadata_repo.borrow().get_summary(&self.dataset_id)?;
+        if summary.kind != DatasetKind::Root {
+            return Err(Error::UsageError {
+                msg: "Data can only be ingested into root datasets".to_owned(),
+            });
+        }
+
+        let result = if self.input == "-" {
+            let stdin = std::io::stdin();
+            let mut data = stdin.lock();
+            self.ingest_svc
+                .borrow_mut()
+                .ingest_from(&self.dataset_id, &mut data, event_time, None)
+        } else {
+            let mut data = std::fs::File::open(&self.input)?;
+            self.ingest_svc
+                .borrow_mut()
+                .ingest_from(&self.dataset_id, &mut data, event_time, None)
+        }?;
+
+        match result {
+            IngestResult::UpToDate { .. } => eprintln!(
+                "{}",
+                console::style("Dataset is up-to-date").yellow().bold()
+            ),
+            IngestResult::Updated { block_hash, .. } => eprintln!(
+                "{}",
+                console::style(format!("Committed new block {}", block_hash))
+                    .green()
+                    .bold()
+            ),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,102 @@
+use super::{Command, Error};
+use kamu::domain::*;
+use kamu::infra::ingest::{diff_ddl_schemas, split_ddl_column};
+use kamu::infra::serde::yaml::*;
+
+use console::style;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub struct InspectSchemaCommand {
+    metadata_repo: Rc<RefCell<dyn MetadataRepository>>,
+    dataset_id: DatasetIDBuf,
+}
+
+impl InspectSchemaCommand {
+    pub fn new(
+        metadata_repo: Rc<RefCell<dyn MetadataRepository>>,
+        dataset_id: DatasetIDBuf,
+    ) -> Self {
+        Self {
+            metadata_repo: metadata_repo,
+            dataset_id: dataset_id,
+        }
+    }
+
+    fn render_schema(&self, schema: &[String]) {
+        use prettytable::*;
+
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_CLEAN);
+        table.set_titles(row![b->"Column", b->"Type"]);
+        for column in schema.iter() {
+            match split_ddl_column(column) {
+                Ok((name, data_type)) => table.add_row(row![name, data_type]),
+                Err(_) => table.add_row(row![column, ""]),
+            };
+        }
+        table.printstd();
+    }
+
+    fn render_history(&self, history: &[SchemaRecord]) -> Result<(), Error> {
+        let mut prev_schema: Option<&Vec<String>> = None;
+
+        for record in history.iter() {
+            let schema = &record.schema;
+
+            let changes = match prev_schema {
+                None => vec![format!("initial schema with {} column(s)", schema.len())],
+                Some(prev) => diff_ddl_schemas(prev, schema)
+                    .map_err(|e| DomainError::InfraError(e.into()))?
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect(),
+            };
+            prev_schema = Some(schema);
+
+            if changes.is_empty() {
+                continue;
+            }
+
+            println!(
+                "{} {} {}",
+                style("Block:").green(),
+                style(&record.block_hash).yellow(),
+                style(format!("({})", record.system_time)).dim()
+            );
+            for change in changes {
+                println!("  {}", change);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Command for InspectSchemaCommand {
+    fn run(&mut self) -> Result<(), Error> {
+        // Oldest first
+        let history = self
+            .metadata_repo
+            .borrow()
+            .get_schema_history(&self.dataset_id)?;
+
+        let current = match history.last() {
+            Some(record) => &record.schema,
+            None => {
+                eprintln!(
+                    "{}",
+                    style("Dataset has no data with a recorded schema yet").yellow()
+                );
+                return Ok(());
+            }
+        };
+
+        println!("{}", style("Current schema:").bold());
+        self.render_schema(current);
+        println!();
+
+        println!("{}", style("History:").bold());
+        self.render_history(&history)
+    }
+}
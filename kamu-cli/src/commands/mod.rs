@@ -21,9 +21,15 @@ pub use list_command::*;
 mod log_command;
 pub use log_command::*;
 
+mod ingest_command;
+pub use ingest_command::*;
+
 mod init_command;
 pub use init_command::*;
 
+mod inspect_schema_command;
+pub use inspect_schema_command::*;
+
 mod new_dataset_command;
 pub use new_dataset_command::*;
 
@@ -33,12 +39,27 @@ pub use notebook_command::*;
 mod pull_command;
 pub use pull_command::*;
 
+mod pull_dry_run_command;
+pub use pull_dry_run_command::*;
+
+mod pull_plan_command;
+pub use pull_plan_command::*;
+
+mod pull_watch_command;
+pub use pull_watch_command::*;
+
 mod sql_server_command;
 pub use sql_server_command::*;
 
 mod sql_shell_command;
 pub use sql_shell_command::*;
 
+mod watermark_set_command;
+pub use watermark_set_command::*;
+
+mod watermark_show_command;
+pub use watermark_show_command::*;
+
 pub trait Command {
     fn needs_workspace(&self) -> bool {
         true
@@ -2,6 +2,7 @@ use super::{Command, Error};
 use crate::output::OutputFormat;
 use kamu::domain::*;
 
+use itertools::Itertools;
 use std::backtrace::BacktraceStatus;
 use std::cell::RefCell;
 use std::error::Error as StdError;
@@ -18,7 +19,9 @@ pub struct PullCommand {
     ids: Vec<String>,
     all: bool,
     recursive: bool,
+    keep_going: bool,
     output_format: OutputFormat,
+    cancel: CancellationToken,
 }
 
 impl PullCommand {
@@ -27,7 +30,9 @@ impl PullCommand {
         ids: I,
         all: bool,
         recursive: bool,
+        keep_going: bool,
         output_format: &OutputFormat,
+        cancel: CancellationToken,
     ) -> Self
     where
         I: Iterator<Item = S>,
@@ -38,7 +43,9 @@ impl PullCommand {
             ids: ids.map(|s| s.as_ref().to_owned()).collect(),
             all: all,
             recursive: recursive,
+            keep_going: keep_going,
             output_format: output_format.clone(),
+            cancel: cancel,
         }
     }
 
@@ -79,6 +86,128 @@ impl PullCommand {
 
         results
     }
+
+    fn print_stats_table(&self, results: &[(DatasetIDBuf, Result<PullResult, PullError>)]) {
+        use prettytable::*;
+
+        let mut table = Table::new();
+        table.set_format(self.get_table_format());
+
+        table.set_titles(row![
+            bc->"Dataset", bc->"Records", bc->"Output interval", bc->"Watermark", bc->"Fetched", bc->"Time"
+        ]);
+
+        for (id, res) in results.iter() {
+            let stats = match res {
+                Ok(PullResult::Updated { stats, .. }) | Ok(PullResult::UpToDate { stats }) => stats,
+                _ => continue,
+            };
+
+            table.add_row(Row::new(vec![
+                Cell::new(id),
+                Cell::new(&self.humanize_num_records(stats.records_added)).style_spec("r"),
+                Cell::new(&stats.output_intervals.iter().join("\n")),
+                Cell::new(&self.describe_watermark(stats)),
+                Cell::new(&self.describe_fetch(stats)).style_spec("r"),
+                Cell::new(&self.describe_stages(stats)).style_spec("r"),
+            ]));
+        }
+
+        table.printstd();
+    }
+
+    fn print_json(&self, results: &[(DatasetIDBuf, Result<PullResult, PullError>)]) {
+        let values: Vec<_> = results
+            .iter()
+            .map(|(id, res)| match res {
+                Ok(PullResult::UpToDate { stats }) => serde_json::json!({
+                    "datasetId": id,
+                    "result": "upToDate",
+                    "stats": stats,
+                }),
+                Ok(PullResult::Updated { block_hash, stats }) => serde_json::json!({
+                    "datasetId": id,
+                    "result": "updated",
+                    "blockHash": block_hash,
+                    "stats": stats,
+                }),
+                Ok(PullResult::Skipped { reason }) => serde_json::json!({
+                    "datasetId": id,
+                    "result": "skipped",
+                    "reason": reason.to_string(),
+                }),
+                Err(PullError::Cancelled(_)) => serde_json::json!({
+                    "datasetId": id,
+                    "result": "cancelled",
+                }),
+                Err(err) => serde_json::json!({
+                    "datasetId": id,
+                    "result": "failed",
+                    "error": err.to_string(),
+                }),
+            })
+            .collect();
+
+        println!("{}", serde_json::to_string_pretty(&values).unwrap());
+    }
+
+    fn describe_watermark(&self, stats: &UpdateStats) -> String {
+        match (stats.prev_watermark, stats.new_watermark) {
+            (_, None) => "-".to_owned(),
+            (Some(prev), Some(new)) if prev == new => new.to_rfc3339(),
+            (None, Some(new)) => format!("-> {}", new.to_rfc3339()),
+            (Some(prev), Some(new)) => format!("{}\n-> {}", prev.to_rfc3339(), new.to_rfc3339()),
+        }
+    }
+
+    fn describe_fetch(&self, stats: &UpdateStats) -> String {
+        use humansize::{file_size_opts, FileSize};
+
+        match (stats.bytes_fetched, stats.fetched_from_cache) {
+            (_, Some(true)) => "cached".to_owned(),
+            (Some(bytes), _) => bytes.file_size(file_size_opts::BINARY).unwrap(),
+            _ => "-".to_owned(),
+        }
+    }
+
+    fn describe_stages(&self, stats: &UpdateStats) -> String {
+        let mut lines: Vec<_> = stats
+            .stages
+            .iter()
+            .map(|s| format!("{}: {:.1}s", s.stage, s.duration.as_secs_f64()))
+            .collect();
+        if stats.stages.len() > 1 {
+            lines.push(format!("total: {:.1}s", stats.total_time().as_secs_f64()));
+        }
+        lines.join("\n")
+    }
+
+    fn humanize_num_records(&self, num: u64) -> String {
+        use num_format::{Locale, ToFormattedString};
+        if num == 0 {
+            return "-".to_owned();
+        }
+        num.to_formatted_string(&Locale::en)
+    }
+
+    fn get_table_format(&self) -> prettytable::format::TableFormat {
+        use prettytable::format::*;
+
+        FormatBuilder::new()
+            .column_separator('│')
+            .borders('│')
+            .separators(&[LinePosition::Top], LineSeparator::new('─', '┬', '┌', '┐'))
+            .separators(
+                &[LinePosition::Title],
+                LineSeparator::new('─', '┼', '├', '┤'),
+            )
+            .separators(
+                &[LinePosition::Bottom],
+                LineSeparator::new('─', '┴', '└', '┘'),
+            )
+            .padding(1, 1)
+            .build()
+    }
 }
 
 impl Command for PullCommand {
@@ -98,22 +227,37 @@ impl Command for PullCommand {
             }
         };
 
+        // Lets running engines be stopped and partial outputs cleaned up.
+        // Handler is reset after the first signal so a second Ctrl+C exits immediately.
+        signal_hook::flag::register(signal_hook::SIGINT, self.cancel.flag())?;
+        signal_hook::cleanup::register(signal_hook::SIGINT, vec![signal_hook::SIGINT])?;
+
         let results = if self.output_format.verbosity_level == 0 {
             self.pull_with_progress(dataset_ids)
         } else {
             self.pull_quiet(dataset_ids)
         };
 
+        if self.output_format.json {
+            self.print_json(&results);
+        } else {
+            self.print_stats_table(&results);
+        }
+
         let mut updated = 0;
         let mut up_to_date = 0;
+        let mut skipped = 0;
+        let mut cancelled = 0;
         let mut errors = 0;
 
         for (_, res) in results.iter() {
             match res {
                 Ok(r) => match r {
-                    PullResult::UpToDate => up_to_date += 1,
+                    PullResult::UpToDate { .. } => up_to_date += 1,
                     PullResult::Updated { .. } => updated += 1,
+                    PullResult::Skipped { .. } => skipped += 1,
                 },
+                Err(PullError::Cancelled(_)) => cancelled += 1,
                 Err(_) => errors += 1,
             }
         }
@@ -134,6 +278,27 @@ impl Command for PullCommand {
                     .bold()
             );
         }
+        if skipped != 0 {
+            eprintln!(
+                "{}",
+                console::style(format!("{} dataset(s) skipped", skipped))
+                    .yellow()
+                    .bold()
+            );
+            for (id, res) in results.iter() {
+                if let Ok(PullResult::Skipped { reason }) = res {
+                    eprintln!("  {}", console::style(format!("{}: {}", id, reason)).dim());
+                }
+            }
+        }
+        if cancelled != 0 {
+            eprintln!(
+                "{}",
+                console::style(format!("{} dataset(s) cancelled", cancelled))
+                    .yellow()
+                    .bold()
+            );
+        }
         if errors != 0 {
             eprintln!(
                 "{}\n\n{}:",
@@ -145,6 +310,10 @@ impl Command for PullCommand {
             results
                 .into_iter()
                 .filter_map(|(id, res)| res.err().map(|e| (id, e)))
+                .filter(|(_, err)| match err {
+                    PullError::Cancelled(_) => false,
+                    _ => true,
+                })
                 .for_each(|(id, err)| {
                     eprintln!(
                         "\n{}: {}",
@@ -170,6 +339,14 @@ impl Command for PullCommand {
                 });
         }
 
+        if self.cancel.is_cancelled() {
+            return Err(Error::Aborted);
+        }
+
+        if errors != 0 && !self.keep_going {
+            return Err(Error::PartialFailure);
+        }
+
         Ok(())
     }
 }
@@ -242,6 +419,7 @@ struct PrettyIngestProgress {
     multi_progress: Arc<indicatif::MultiProgress>,
     curr_progress: indicatif::ProgressBar,
     curr_progress_style: ProgressStyle,
+    schema_changes: usize,
 }
 
 impl PrettyIngestProgress {
@@ -256,6 +434,7 @@ impl PrettyIngestProgress {
                 "Checking for updates",
             ))),
             multi_progress: multi_progress,
+            schema_changes: 0,
         }
     }
 
@@ -350,8 +529,17 @@ impl IngestListener for PrettyIngestProgress {
 
     fn success(&mut self, result: &IngestResult) {
         let msg = match result {
-            IngestResult::UpToDate => console::style("Dataset is up-to-date".to_owned()).yellow(),
-            IngestResult::Updated { ref block_hash } => {
+            IngestResult::UpToDate { .. } => {
+                console::style("Dataset is up-to-date".to_owned()).yellow()
+            }
+            IngestResult::Updated { ref block_hash, .. } if self.schema_changes != 0 => {
+                console::style(format!(
+                    "Committed new block {} with {} schema change(s)",
+                    block_hash, self.schema_changes
+                ))
+                .yellow()
+            }
+            IngestResult::Updated { ref block_hash, .. } => {
                 console::style(format!("Committed new block {}", block_hash)).green()
             }
         };
@@ -363,6 +551,32 @@ impl IngestListener for PrettyIngestProgress {
             ));
     }
 
+    fn on_schema_drift(&mut self, changes: &[SchemaChange]) {
+        self.schema_changes += changes.len();
+        for change in changes {
+            self.curr_progress.println(format!(
+                "{} {}: {}",
+                console::style("Schema changed").yellow(),
+                self.dataset_id,
+                change
+            ));
+        }
+    }
+
+    fn on_expectations_checked(&mut self, results: &[ExpectationResult]) {
+        for result in results
+            .iter()
+            .filter(|r| r.outcome == ExpectationOutcome::Quarantined)
+        {
+            self.curr_progress.println(format!(
+                "{} {}: {}",
+                console::style("Quarantined records").yellow(),
+                self.dataset_id,
+                result
+            ));
+        }
+    }
+
     fn uncacheable(&mut self) {
         self.curr_progress
             .finish_with_message(&Self::spinner_message(
@@ -372,12 +586,16 @@ impl IngestListener for PrettyIngestProgress {
             ));
     }
 
-    fn error(&mut self, _error: &IngestError) {
+    fn error(&mut self, error: &IngestError) {
+        let msg = match error {
+            IngestError::Cancelled(_) => console::style("Cancelled").yellow(),
+            _ => console::style("Failed to update root dataset").red(),
+        };
         self.curr_progress
             .finish_with_message(&Self::spinner_message(
                 &self.dataset_id,
                 self.stage as u32,
-                console::style("Failed to update root dataset").red(),
+                msg,
             ));
     }
 }
@@ -424,12 +642,26 @@ impl PrettyTransformProgress {
 }
 
 impl TransformListener for PrettyTransformProgress {
+    fn on_batch_progress(&mut self, n: u64, out_of: u64) {
+        if n < out_of {
+            self.curr_progress.set_message(&Self::spinner_message(
+                &self.dataset_id,
+                0,
+                format!(
+                    "Applying derivative transformations (batch {} of {})",
+                    n + 1,
+                    out_of
+                ),
+            ));
+        }
+    }
+
     fn success(&mut self, result: &TransformResult) {
         let msg = match result {
             TransformResult::UpToDate => {
                 console::style("Dataset is up-to-date".to_owned()).yellow()
             }
-            TransformResult::Updated { ref block_hash } => {
+            TransformResult::Updated { ref block_hash, .. } => {
                 console::style(format!("Committed new block {}", block_hash)).green()
             }
         };
@@ -437,12 +669,12 @@ impl TransformListener for PrettyTransformProgress {
             .finish_with_message(&Self::spinner_message(&self.dataset_id, 0, msg));
     }
 
-    fn error(&mut self, _error: &TransformError) {
+    fn error(&mut self, error: &TransformError) {
+        let msg = match error {
+            TransformError::Cancelled(_) => console::style("Cancelled").yellow(),
+            _ => console::style("Failed to update derivative dataset").red(),
+        };
         self.curr_progress
-            .finish_with_message(&Self::spinner_message(
-                &self.dataset_id,
-                0,
-                console::style("Failed to update derivative dataset").red(),
-            ));
+            .finish_with_message(&Self::spinner_message(&self.dataset_id, 0, msg));
     }
 }
@@ -0,0 +1,138 @@
+use super::{Command, Error};
+use crate::output::OutputFormat;
+use kamu::domain::*;
+use kamu::infra::serde::yaml::*;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub struct PullDryRunCommand {
+    ingest_svc: Rc<RefCell<dyn IngestService>>,
+    metadata_repo: Rc<RefCell<dyn MetadataRepository>>,
+    ids: Vec<String>,
+    num_rows: usize,
+    output_format: OutputFormat,
+}
+
+impl PullDryRunCommand {
+    pub fn new<I, S>(
+        ingest_svc: Rc<RefCell<dyn IngestService>>,
+        metadata_repo: Rc<RefCell<dyn MetadataRepository>>,
+        ids: I,
+        num_rows: usize,
+        output_format: &OutputFormat,
+    ) -> Self
+    where
+        I: Iterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self {
+            ingest_svc: ingest_svc,
+            metadata_repo: metadata_repo,
+            ids: ids.map(|s| s.as_ref().to_owned()).collect(),
+            num_rows: num_rows,
+            output_format: output_format.clone(),
+        }
+    }
+
+    fn print_preview(&self, dataset_id: &DatasetID, preview: &IngestPreview) {
+        use prettytable::*;
+
+        eprintln!(
+            "{} {}",
+            console::style("Preview of").bold(),
+            console::style(dataset_id).cyan().bold()
+        );
+
+        let mut schema = Table::new();
+        schema.set_format(*format::consts::FORMAT_CLEAN);
+        schema.set_titles(row![b->"Column", b->"Type"]);
+        for (name, data_type) in preview.columns.iter() {
+            schema.add_row(row![name, data_type]);
+        }
+        schema.printstd();
+        println!();
+
+        let mut data = Table::new();
+        data.set_format(*format::consts::FORMAT_BOX_CHARS);
+        data.set_titles(Row::new(
+            preview
+                .columns
+                .iter()
+                .map(|(name, _)| Cell::new(name).style_spec("bc"))
+                .collect(),
+        ));
+        for row in preview.rows.iter() {
+            data.add_row(Row::new(
+                row.iter()
+                    .map(|v| match v {
+                        Some(v) => Cell::new(v),
+                        None => Cell::new("null").style_spec("d"),
+                    })
+                    .collect(),
+            ));
+        }
+        data.printstd();
+
+        eprintln!(
+            "{}",
+            console::style(format!(
+                "Showing {} of {} record(s), nothing was committed",
+                preview.rows.len(),
+                preview.num_records
+            ))
+            .yellow()
+        );
+    }
+
+    fn print_machine_readable(&self, preview: &IngestPreview) {
+        let header: Vec<&str> = preview.columns.iter().map(|(n, _)| n.as_str()).collect();
+        println!("{}", header.join(","));
+        for row in preview.rows.iter() {
+            let values: Vec<&str> = row
+                .iter()
+                .map(|v| v.as_ref().map(|s| s.as_str()).unwrap_or(""))
+                .collect();
+            println!("{}", values.join(","));
+        }
+    }
+}
+
+impl Command for PullDryRunCommand {
+    fn run(&mut self) -> Result<(), Error> {
+        if self.ids.is_empty() {
+            return Err(Error::UsageError {
+                msg: "Specify the dataset(s) to preview".to_owned(),
+            });
+        }
+
+        let dataset_ids: Vec<DatasetIDBuf> = self.ids.iter().map(|s| s.parse().unwrap()).collect();
+
+        for dataset_id in dataset_ids.iter() {
+            let summary = self.metadata_repo.borrow().get_summary(dataset_id)?;
+            if summary.kind != DatasetKind::Root {
+                return Err(Error::UsageError {
+                    msg: format!(
+                        "Dry run is only supported for root datasets: {}",
+                        dataset_id
+                    ),
+                });
+            }
+        }
+
+        for dataset_id in dataset_ids.iter() {
+            let preview = self
+                .ingest_svc
+                .borrow_mut()
+                .preview(dataset_id, self.num_rows, None)?;
+
+            if self.output_format.is_tty {
+                self.print_preview(dataset_id, &preview);
+            } else {
+                self.print_machine_readable(&preview);
+            }
+        }
+
+        Ok(())
+    }
+}
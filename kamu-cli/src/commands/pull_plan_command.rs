@@ -0,0 +1,146 @@
+use super::{Command, Error};
+use crate::output::OutputFormat;
+use kamu::domain::*;
+
+use itertools::Itertools;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub struct PullPlanCommand {
+    pull_svc: Rc<RefCell<dyn PullService>>,
+    ids: Vec<String>,
+    all: bool,
+    recursive: bool,
+    output_format: OutputFormat,
+}
+
+impl PullPlanCommand {
+    pub fn new<I, S>(
+        pull_svc: Rc<RefCell<dyn PullService>>,
+        ids: I,
+        all: bool,
+        recursive: bool,
+        output_format: &OutputFormat,
+    ) -> Self
+    where
+        I: Iterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self {
+            pull_svc: pull_svc,
+            ids: ids.map(|s| s.as_ref().to_owned()).collect(),
+            all: all,
+            recursive: recursive,
+            output_format: output_format.clone(),
+        }
+    }
+
+    fn print_table(&self, plan: &[PullPlanStep]) {
+        use prettytable::*;
+
+        let mut table = Table::new();
+        table.set_format(self.get_table_format());
+
+        table.set_titles(
+            row![bc->"#", bc->"Dataset", bc->"Action", bc->"Details", bc->"Depends on"],
+        );
+
+        for (i, step) in plan.iter().enumerate() {
+            let (action, details) = match &step.action {
+                PullAction::Ingest(ingest) => ("Ingest", Self::describe_ingest(ingest)),
+                PullAction::Transform(transform) => {
+                    ("Transform", Self::describe_transform(transform))
+                }
+            };
+
+            table.add_row(Row::new(vec![
+                Cell::new(&(i + 1).to_string()).style_spec("r"),
+                Cell::new(&step.dataset_id),
+                Cell::new(action).style_spec("c"),
+                Cell::new(&details),
+                Cell::new(&step.depends_on.iter().join("\n")),
+            ]));
+        }
+
+        table.printstd();
+    }
+
+    fn describe_ingest(plan: &IngestPlan) -> String {
+        let mut lines = vec![
+            format!("source: {}", plan.source),
+            format!("cache: {}", plan.cache),
+        ];
+        if let Some(engine) = &plan.engine {
+            lines.push(format!("engine: {}", engine));
+        }
+        lines.join("\n")
+    }
+
+    fn describe_transform(plan: &TransformPlan) -> String {
+        let mut lines = vec![format!("engine: {}", plan.engine)];
+        if plan.batches.is_empty() {
+            lines.push("up to date".to_owned());
+        }
+        for (i, slices) in plan.batches.iter().enumerate() {
+            if plan.batches.len() > 1 {
+                lines.push(format!("batch {}:", i + 1));
+            }
+            for (input_id, slice) in slices.iter() {
+                lines.push(format!("{}: {}", input_id, slice.interval));
+            }
+        }
+        lines.join("\n")
+    }
+
+    fn get_table_format(&self) -> prettytable::format::TableFormat {
+        use prettytable::format::*;
+
+        FormatBuilder::new()
+            .column_separator('│')
+            .borders('│')
+            .separators(&[LinePosition::Top], LineSeparator::new('─', '┬', '┌', '┐'))
+            .separators(
+                &[LinePosition::Title],
+                LineSeparator::new('─', '┼', '├', '┤'),
+            )
+            .separators(
+                &[LinePosition::Bottom],
+                LineSeparator::new('─', '┴', '└', '┘'),
+            )
+            .padding(1, 1)
+            .build()
+    }
+}
+
+impl Command for PullPlanCommand {
+    fn run(&mut self) -> Result<(), Error> {
+        let dataset_ids: Vec<DatasetIDBuf> = match (&self.ids[..], self.recursive, self.all) {
+            ([], false, false) => {
+                return Err(Error::UsageError {
+                    msg: "Specify a dataset or pass --all".to_owned(),
+                })
+            }
+            ([], false, true) => vec![],
+            (ref ids, _, false) => ids.iter().map(|s| s.parse().unwrap()).collect(),
+            _ => {
+                return Err(Error::UsageError {
+                    msg: "Invalid combination of arguments".to_owned(),
+                })
+            }
+        };
+
+        let plan = self.pull_svc.borrow_mut().plan_pull(
+            &mut dataset_ids.iter().map(|id| id.as_ref()),
+            self.recursive,
+            self.all,
+        )?;
+
+        if self.output_format.json {
+            println!("{}", serde_json::to_string_pretty(&plan).unwrap());
+        } else {
+            self.print_table(&plan);
+        }
+
+        Ok(())
+    }
+}
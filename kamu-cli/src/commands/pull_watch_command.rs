@@ -0,0 +1,143 @@
+use super::{Command, Error};
+use kamu::domain::*;
+use kamu::infra::*;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use chrono_humanize::HumanTime;
+use slog::Logger;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+///////////////////////////////////////////////////////////////////////////////
+// Command
+///////////////////////////////////////////////////////////////////////////////
+
+pub struct PullWatchCommand {
+    scheduler: PullScheduler,
+}
+
+impl PullWatchCommand {
+    pub fn new(
+        metadata_repo: Rc<RefCell<dyn MetadataRepository>>,
+        pull_svc: Rc<RefCell<dyn PullService>>,
+        volume_layout: &VolumeLayout,
+        logger: Logger,
+    ) -> Self {
+        Self {
+            scheduler: PullScheduler::new(metadata_repo, pull_svc, volume_layout, logger),
+        }
+    }
+}
+
+impl Command for PullWatchCommand {
+    fn run(&mut self) -> Result<(), Error> {
+        let scheduled = self.scheduler.get_scheduled_datasets(Utc::now(), None)?;
+        eprintln!(
+            "{}",
+            console::style(format!(
+                "Watching {} scheduled dataset(s) and local source files, press Ctrl+C to stop",
+                scheduled.len()
+            ))
+            .bold()
+        );
+
+        let listener = Arc::new(Mutex::new(WatchListener {
+            last_idle: None,
+            reported_invalid: HashSet::new(),
+            reported_watch_errors: HashSet::new(),
+        }));
+        self.scheduler.run(Some(listener))?;
+
+        eprintln!("{}", console::style("Stopped watching").bold());
+        Ok(())
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Listener
+///////////////////////////////////////////////////////////////////////////////
+
+struct WatchListener {
+    last_idle: Option<DateTime<Utc>>,
+    reported_invalid: HashSet<DatasetIDBuf>,
+    reported_watch_errors: HashSet<PathBuf>,
+}
+
+impl WatchListener {
+    fn now() -> String {
+        Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+    }
+}
+
+impl PullSchedulerListener for WatchListener {
+    fn on_pull_start(&mut self, due: &[DatasetIDBuf], dependents: &[DatasetIDBuf]) {
+        self.last_idle = None;
+        let due: Vec<&str> = due.iter().map(|id| id.as_str()).collect();
+        if dependents.is_empty() {
+            eprintln!("[{}] Pulling {}", Self::now(), due.join(", "));
+        } else {
+            let dependents: Vec<&str> = dependents.iter().map(|id| id.as_str()).collect();
+            eprintln!(
+                "[{}] Pulling {} and dependent(s) {}",
+                Self::now(),
+                due.join(", "),
+                dependents.join(", ")
+            );
+        }
+    }
+
+    fn on_pull_end(&mut self, results: &[(DatasetIDBuf, Result<PullResult, PullError>)]) {
+        for (id, res) in results {
+            let msg = match res {
+                Ok(PullResult::UpToDate { .. }) => console::style("up-to-date".to_owned()).yellow(),
+                Ok(PullResult::Updated { .. }) => console::style("updated".to_owned()).green(),
+                Ok(PullResult::Skipped { reason }) => {
+                    console::style(format!("skipped: {}", reason)).yellow()
+                }
+                Err(e) => console::style(format!("failed: {}", e)).red(),
+            };
+            eprintln!("[{}]   {}: {}", Self::now(), id, msg);
+        }
+    }
+
+    fn on_idle(&mut self, next_run: Option<DateTime<Utc>>) {
+        // Don't repeat the same message on every periodic re-check
+        if next_run.is_none() || next_run == self.last_idle {
+            return;
+        }
+        self.last_idle = next_run;
+
+        let next_run = next_run.unwrap();
+        eprintln!(
+            "[{}] Next pull {}",
+            Self::now(),
+            console::style(HumanTime::from(next_run - Utc::now())).dim()
+        );
+    }
+
+    fn on_invalid_schedule(&mut self, dataset_id: &DatasetID, error: &ScheduleError) {
+        if !self.reported_invalid.insert(dataset_id.to_owned()) {
+            return;
+        }
+        eprintln!(
+            "[{}] {}",
+            Self::now(),
+            console::style(format!("Skipping {}: {}", dataset_id, error)).red()
+        );
+    }
+
+    fn on_files_changed(&mut self, dataset_ids: &[DatasetIDBuf]) {
+        let ids: Vec<&str> = dataset_ids.iter().map(|id| id.as_str()).collect();
+        eprintln!("[{}] Source files changed: {}", Self::now(), ids.join(", "));
+    }
+
+    fn on_watch_error(&mut self, error: &WatchError) {
+        if !self.reported_watch_errors.insert(error.dir.clone()) {
+            return;
+        }
+        eprintln!("[{}] {}", Self::now(), console::style(error).red());
+    }
+}
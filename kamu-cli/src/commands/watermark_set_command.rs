@@ -0,0 +1,65 @@
+use super::{Command, Error};
+use kamu::domain::*;
+
+use chrono::{DateTime, Utc};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub struct WatermarkSetCommand {
+    watermark_svc: Rc<RefCell<dyn WatermarkService>>,
+    dataset_id: DatasetIDBuf,
+    watermark: String,
+}
+
+impl WatermarkSetCommand {
+    pub fn new(
+        watermark_svc: Rc<RefCell<dyn WatermarkService>>,
+        dataset_id: DatasetIDBuf,
+        watermark: &str,
+    ) -> Self {
+        Self {
+            watermark_svc: watermark_svc,
+            dataset_id: dataset_id,
+            watermark: watermark.to_owned(),
+        }
+    }
+
+    fn parse_watermark(&self) -> Result<DateTime<Utc>, Error> {
+        DateTime::parse_from_rfc3339(&self.watermark)
+            .map(|dt| dt.into())
+            .map_err(|_| Error::UsageError {
+                msg: format!(
+                    "Invalid watermark {}, expected RFC3339 format",
+                    self.watermark
+                ),
+            })
+    }
+}
+
+impl Command for WatermarkSetCommand {
+    fn run(&mut self) -> Result<(), Error> {
+        let watermark = self.parse_watermark()?;
+
+        let result = self
+            .watermark_svc
+            .borrow_mut()
+            .set_watermark(&self.dataset_id, watermark)?;
+
+        match result {
+            WatermarkResult::UpToDate => eprintln!(
+                "{}",
+                console::style("Watermark is already set to this time")
+                    .yellow()
+                    .bold()
+            ),
+            WatermarkResult::Updated { block_hash } => eprintln!(
+                "{}",
+                console::style(format!("Committed new block {}", block_hash))
+                    .green()
+                    .bold()
+            ),
+        }
+
+        Ok(())
+    }
+}
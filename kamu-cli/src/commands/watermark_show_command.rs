@@ -0,0 +1,78 @@
+use super::{Command, Error};
+use crate::output::OutputFormat;
+use kamu::domain::*;
+
+use chrono::{DateTime, Utc};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub struct WatermarkShowCommand {
+    metadata_repo: Rc<RefCell<dyn MetadataRepository>>,
+    ids: Vec<String>,
+    output_format: OutputFormat,
+}
+
+impl WatermarkShowCommand {
+    pub fn new<I, S>(
+        metadata_repo: Rc<RefCell<dyn MetadataRepository>>,
+        ids: I,
+        output_format: &OutputFormat,
+    ) -> Self
+    where
+        I: Iterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self {
+            metadata_repo: metadata_repo,
+            ids: ids.map(|s| s.as_ref().to_owned()).collect(),
+            output_format: output_format.clone(),
+        }
+    }
+
+    fn get_watermark(&self, dataset_id: &DatasetID) -> Result<Option<DateTime<Utc>>, Error> {
+        let chain = self.metadata_repo.borrow().get_metadata_chain(dataset_id)?;
+        Ok(chain
+            .iter_blocks()
+            .filter_map(|b| b.output_watermark)
+            .next())
+    }
+}
+
+impl Command for WatermarkShowCommand {
+    fn run(&mut self) -> Result<(), Error> {
+        let mut dataset_ids: Vec<DatasetIDBuf> = if self.ids.is_empty() {
+            self.metadata_repo.borrow().get_all_datasets().collect()
+        } else {
+            self.ids.iter().map(|s| s.parse().unwrap()).collect()
+        };
+        dataset_ids.sort();
+
+        if self.output_format.is_tty {
+            use prettytable::*;
+
+            let mut table = Table::new();
+            table.set_format(*format::consts::FORMAT_CLEAN);
+            table.set_titles(row![b->"ID", b->"Watermark"]);
+            for id in dataset_ids.iter() {
+                match self.get_watermark(id)? {
+                    Some(wm) => table.add_row(row![id, wm.to_rfc3339()]),
+                    None => table.add_row(row![id, "-"]),
+                };
+            }
+            table.printstd();
+        } else {
+            println!("ID,Watermark");
+            for id in dataset_ids.iter() {
+                println!(
+                    "{},{}",
+                    id,
+                    self.get_watermark(id)?
+                        .map(|wm| wm.to_rfc3339())
+                        .unwrap_or_default()
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -7,6 +7,14 @@ pub enum Error {
     UsageError { msg: String },
     #[error("{0}")]
     DomainError(#[from] kamu::domain::DomainError),
+    #[error("{0}")]
+    IngestError(#[from] kamu::domain::IngestError),
+    #[error("{0}")]
+    PullError(#[from] kamu::domain::PullError),
+    #[error("{0}")]
+    SchedulerError(#[from] kamu::infra::SchedulerError),
+    #[error("{0}")]
+    WatermarkError(#[from] kamu::domain::WatermarkError),
     #[error("Directory is already a kamu workspace")]
     AlreadyInWorkspace,
     #[error("Directory is not a kamu workspace")]
@@ -1,6 +1,7 @@
 #![feature(backtrace)]
 
 use kamu::domain::*;
+use kamu::infra::utils::worker_pool::ConcurrencyOptions;
 use kamu::infra::*;
 use kamu_cli::cli_parser;
 use kamu_cli::commands::*;
@@ -14,7 +15,7 @@ use std::cell::RefCell;
 use std::error::Error as StdError;
 use std::path::Path;
 use std::rc::Rc;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 
 const BINARY_NAME: &str = "kamu";
 const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -34,25 +35,47 @@ fn main() {
 
     let logger = configure_logging(&output_format, &workspace_layout);
 
+    let concurrency = configure_concurrency(&matches);
+    let batching = configure_batching(&matches);
+    let cancel = CancellationToken::new();
+
     let metadata_repo = Rc::new(RefCell::new(MetadataRepositoryImpl::new(&workspace_layout)));
     let resource_loader = Rc::new(RefCell::new(ResourceLoaderImpl::new()));
-    let engine_factory = Arc::new(Mutex::new(EngineFactory::new(&workspace_layout)));
-    let ingest_svc = Rc::new(RefCell::new(IngestServiceImpl::new(
-        metadata_repo.clone(),
-        engine_factory.clone(),
-        &local_volume_layout,
-        logger.new(o!()),
-    )));
-    let transform_svc = Rc::new(RefCell::new(TransformServiceImpl::new(
-        metadata_repo.clone(),
-        engine_factory.clone(),
-        &local_volume_layout,
-        logger.new(o!()),
-    )));
-    let pull_svc = Rc::new(RefCell::new(PullServiceImpl::new(
+    let engine_factory = Arc::new(EngineFactory::new(&workspace_layout));
+    let ingest_svc = Rc::new(RefCell::new(
+        IngestServiceImpl::new(
+            metadata_repo.clone(),
+            engine_factory.clone(),
+            &workspace_layout,
+            &local_volume_layout,
+            logger.new(o!()),
+        )
+        .with_concurrency(concurrency.clone())
+        .with_cancellation(cancel.clone()),
+    ));
+    let transform_svc = Rc::new(RefCell::new(
+        TransformServiceImpl::new(
+            metadata_repo.clone(),
+            engine_factory.clone(),
+            &local_volume_layout,
+            logger.new(o!()),
+        )
+        .with_concurrency(concurrency.clone())
+        .with_batching(batching)
+        .with_cancellation(cancel.clone()),
+    ));
+    let pull_svc = Rc::new(RefCell::new(
+        PullServiceImpl::new(
+            metadata_repo.clone(),
+            ingest_svc.clone(),
+            transform_svc.clone(),
+            logger.new(o!()),
+        )
+        .with_concurrency(concurrency)
+        .with_cancellation(cancel.clone()),
+    ));
+    let watermark_svc = Rc::new(RefCell::new(WatermarkServiceImpl::new(
         metadata_repo.clone(),
-        ingest_svc.clone(),
-        transform_svc.clone(),
         logger.new(o!()),
     )));
 
@@ -80,7 +103,21 @@ fn main() {
             submatches.is_present("recursive"),
             submatches.is_present("yes"),
         )),
+        ("ingest", Some(submatches)) => Box::new(IngestCommand::new(
+            ingest_svc.clone(),
+            metadata_repo.clone(),
+            value_t_or_exit!(submatches.value_of("dataset"), DatasetIDBuf),
+            submatches.value_of("file").unwrap(),
+            submatches.value_of("event-time"),
+        )),
         ("init", Some(_)) => Box::new(InitCommand::new(&workspace_layout)),
+        ("inspect", Some(submatches)) => match submatches.subcommand() {
+            ("schema", Some(schema_matches)) => Box::new(InspectSchemaCommand::new(
+                metadata_repo.clone(),
+                value_t_or_exit!(schema_matches.value_of("dataset"), DatasetIDBuf),
+            )),
+            _ => unimplemented!(),
+        },
         ("list", Some(submatches)) => match submatches.subcommand() {
             ("", None) => Box::new(ListCommand::new(metadata_repo.clone(), &output_format)),
             ("depgraph", _) => Box::new(DepgraphCommand::new(metadata_repo.clone())),
@@ -102,13 +139,42 @@ fn main() {
             &output_format,
             submatches.values_of("env").unwrap_or_default(),
         )),
-        ("pull", Some(submatches)) => Box::new(PullCommand::new(
-            pull_svc.clone(),
-            submatches.values_of("dataset").unwrap_or_default(),
-            submatches.is_present("all"),
-            submatches.is_present("recursive"),
-            &output_format,
-        )),
+        ("pull", Some(submatches)) => {
+            if submatches.is_present("dry-run") {
+                Box::new(PullDryRunCommand::new(
+                    ingest_svc.clone(),
+                    metadata_repo.clone(),
+                    submatches.values_of("dataset").unwrap_or_default(),
+                    value_t_or_exit!(submatches.value_of("preview-rows"), usize),
+                    &output_format,
+                ))
+            } else if submatches.is_present("plan") {
+                Box::new(PullPlanCommand::new(
+                    pull_svc.clone(),
+                    submatches.values_of("dataset").unwrap_or_default(),
+                    submatches.is_present("all"),
+                    submatches.is_present("recursive"),
+                    &output_format,
+                ))
+            } else if submatches.is_present("watch") {
+                Box::new(PullWatchCommand::new(
+                    metadata_repo.clone(),
+                    pull_svc.clone(),
+                    &local_volume_layout,
+                    logger.new(o!()),
+                ))
+            } else {
+                Box::new(PullCommand::new(
+                    pull_svc.clone(),
+                    submatches.values_of("dataset").unwrap_or_default(),
+                    submatches.is_present("all"),
+                    submatches.is_present("recursive"),
+                    submatches.is_present("keep-going"),
+                    &output_format,
+                    cancel.clone(),
+                ))
+            }
+        }
         ("sql", Some(submatches)) => match submatches.subcommand() {
             ("", None) => Box::new(SqlShellCommand::new(
                 &workspace_layout,
@@ -122,6 +188,19 @@ fn main() {
             )),
             _ => unimplemented!(),
         },
+        ("watermark", Some(submatches)) => match submatches.subcommand() {
+            ("set", Some(set_matches)) => Box::new(WatermarkSetCommand::new(
+                watermark_svc.clone(),
+                value_t_or_exit!(set_matches.value_of("dataset"), DatasetIDBuf),
+                set_matches.value_of("watermark").unwrap(),
+            )),
+            ("show", Some(show_matches)) => Box::new(WatermarkShowCommand::new(
+                metadata_repo.clone(),
+                show_matches.values_of("dataset").unwrap_or_default(),
+                &output_format,
+            )),
+            _ => unimplemented!(),
+        },
         _ => unimplemented!(),
     };
 
@@ -199,6 +278,42 @@ fn configure_logging(output_format: &OutputFormat, workspace_layout: &WorkspaceL
     logger
 }
 
+fn configure_concurrency(matches: &clap::ArgMatches<'_>) -> ConcurrencyOptions {
+    let submatches = match matches.subcommand() {
+        ("pull", Some(submatches)) => submatches,
+        _ => return ConcurrencyOptions::default(),
+    };
+
+    ConcurrencyOptions {
+        jobs: value_t_or_exit!(submatches.value_of("jobs"), usize),
+        engine_jobs: submatches
+            .values_of("engine-jobs")
+            .unwrap_or_default()
+            .map(|v| {
+                let mut parts = v.splitn(2, '=');
+                let engine = parts.next().unwrap().to_owned();
+                (engine, parts.next().unwrap().parse().unwrap())
+            })
+            .collect(),
+    }
+}
+
+fn configure_batching(matches: &clap::ArgMatches<'_>) -> TransformBatchOptions {
+    let submatches = match matches.subcommand() {
+        ("pull", Some(submatches)) => submatches,
+        _ => return TransformBatchOptions::default(),
+    };
+
+    TransformBatchOptions {
+        max_input_blocks: submatches
+            .value_of("batch-blocks")
+            .map(|v| v.parse().unwrap()),
+        max_interval: submatches
+            .value_of("batch-interval")
+            .map(|v| parse_interval(v).unwrap()),
+    }
+}
+
 fn configure_output_format(matches: &clap::ArgMatches<'_>) -> OutputFormat {
     let verbosity_level = matches.occurrences_of("v") as u8;
 
@@ -209,6 +324,7 @@ fn configure_output_format(matches: &clap::ArgMatches<'_>) -> OutputFormat {
     OutputFormat {
         verbosity_level: verbosity_level,
         is_tty: console::Term::stdout().is_term(),
+        json: matches.is_present("json"),
     }
 }
 
@@ -2,6 +2,8 @@
 pub struct OutputFormat {
     pub verbosity_level: u8,
     pub is_tty: bool,
+    /// Whether commands should print their results as JSON instead of tables
+    pub json: bool,
 }
 
 impl Default for OutputFormat {
@@ -9,6 +11,7 @@ impl Default for OutputFormat {
         Self {
             verbosity_level: 0,
             is_tty: false,
+            json: false,
         }
     }
 }
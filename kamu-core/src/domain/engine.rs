@@ -10,9 +10,19 @@ use std::collections::BTreeMap;
 use std::path::PathBuf;
 use thiserror::Error;
 
-pub trait Engine {
-    fn ingest(&self, request: IngestRequest) -> Result<IngestResponse, EngineError>;
-    fn transform(&self, request: ExecuteQueryRequest) -> Result<ExecuteQueryResponse, EngineError>;
+/// Engines must support processing multiple requests concurrently
+pub trait Engine: Send + Sync {
+    fn ingest(
+        &self,
+        request: IngestRequest,
+        cancel: &CancellationToken,
+    ) -> Result<IngestResponse, EngineError>;
+
+    fn transform(
+        &self,
+        request: ExecuteQueryRequest,
+        cancel: &CancellationToken,
+    ) -> Result<ExecuteQueryResponse, EngineError>;
 }
 
 ///////////////////////////////////////////////////////////////////////////////
@@ -97,6 +107,8 @@ pub enum EngineError {
     ProcessError(#[from] ProcessError),
     #[error("Contract error: {0}")]
     ContractError(#[from] ContractError),
+    #[error("{0}")]
+    Cancelled(#[from] CancelledError),
     #[error("Internal error: {source}")]
     InternalError {
         #[from]
@@ -1,7 +1,10 @@
-use super::EngineError;
+use super::{CancelledError, EngineError, UpdateStats};
 use crate::domain::{DatasetID, DatasetIDBuf};
 
+use ::serde::Serialize;
+use chrono::{DateTime, Utc};
 use std::backtrace::Backtrace;
+use std::io::Read;
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 use thiserror::Error;
@@ -22,12 +25,192 @@ pub trait IngestService {
         dataset_ids: &mut dyn Iterator<Item = &DatasetID>,
         listener: Option<Arc<Mutex<dyn IngestMultiListener>>>,
     ) -> Vec<(DatasetIDBuf, Result<IngestResult, IngestError>)>;
+
+    /// Prepares ingestion of a dataset as a job that the caller can run on any thread.
+    /// The result of the job has to be passed to `complete_ingest`.
+    fn prepare_ingest(
+        &mut self,
+        dataset_id: &DatasetID,
+        listener: Option<Arc<Mutex<dyn IngestMultiListener>>>,
+    ) -> Result<IngestJob, IngestError>;
+
+    /// Finishes ingestion of a dataset using the result of its prepared job
+    fn complete_ingest(
+        &mut self,
+        dataset_id: &DatasetID,
+        result: Result<IngestResult, IngestError>,
+    ) -> Result<IngestResult, IngestError>;
+
+    /// Describes what ingestion would do based on the cached state of the source,
+    /// without fetching anything
+    fn plan_ingest(&mut self, dataset_id: &DatasetID) -> Result<IngestPlan, IngestError>;
+
+    /// Runs fetch, prepare and read steps in a temporary location and returns
+    /// a sample of the resulting data without committing anything
+    fn preview(
+        &mut self,
+        dataset_id: &DatasetID,
+        num_rows: usize,
+        listener: Option<Arc<Mutex<dyn IngestListener>>>,
+    ) -> Result<IngestPreview, IngestError>;
+
+    /// Ingests data supplied by the caller into a root dataset, skipping the fetch step.
+    /// The optional event time is used for records that don't carry one.
+    fn ingest_from(
+        &mut self,
+        dataset_id: &DatasetID,
+        data: &mut dyn Read,
+        event_time: Option<DateTime<Utc>>,
+        listener: Option<Arc<Mutex<dyn IngestListener>>>,
+    ) -> Result<IngestResult, IngestError>;
+}
+
+/// Ingestion of a single dataset that can be executed on any thread
+pub struct IngestJob {
+    /// Engine used to preprocess the data, if any
+    pub engine: Option<String>,
+    pub run: Box<dyn FnOnce() -> Result<IngestResult, IngestError> + Send>,
 }
 
 #[derive(Debug)]
 pub enum IngestResult {
-    UpToDate,
-    Updated { block_hash: String },
+    /// Stats describe the stages that found no new data
+    UpToDate { stats: UpdateStats },
+    Updated {
+        block_hash: String,
+        stats: UpdateStats,
+    },
+}
+
+/// What ingestion of a dataset would do
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IngestPlan {
+    /// Location the data is fetched from
+    pub source: String,
+    pub cache: FetchCacheState,
+    /// Engine used to preprocess the data, if any
+    pub engine: Option<String>,
+}
+
+/// What the fetch cache knows about the source of a root dataset
+#[serde(tag = "state")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum FetchCacheState {
+    /// Source was never fetched
+    Empty,
+    /// Source will not be fetched again, either because its local file didn't
+    /// change or because it can't be checked for updates
+    #[serde(rename_all = "camelCase")]
+    UpToDate { last_fetched: DateTime<Utc> },
+    /// Local source file changed since it was last fetched
+    #[serde(rename_all = "camelCase")]
+    Modified { last_fetched: DateTime<Utc> },
+    /// Remote source will be asked whether it has new data
+    #[serde(rename_all = "camelCase")]
+    NeedsCheck { last_fetched: DateTime<Utc> },
+}
+
+impl std::fmt::Display for FetchCacheState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FetchCacheState::Empty => write!(f, "never fetched"),
+            FetchCacheState::UpToDate { last_fetched } => {
+                write!(f, "up to date, fetched at {}", last_fetched.to_rfc3339())
+            }
+            FetchCacheState::Modified { last_fetched } => {
+                write!(f, "modified since {}", last_fetched.to_rfc3339())
+            }
+            FetchCacheState::NeedsCheck { last_fetched } => write!(
+                f,
+                "will check for updates since {}",
+                last_fetched.to_rfc3339()
+            ),
+        }
+    }
+}
+
+/// Sample of the data that ingestion would produce
+#[derive(Debug, Clone)]
+pub struct IngestPreview {
+    /// Names and types of the output columns
+    pub columns: Vec<(String, String)>,
+    /// First rows of the output, `None` represents nulls
+    pub rows: Vec<Vec<Option<String>>>,
+    pub num_records: u64,
+}
+
+/// Difference between the schema of newly ingested data and the previous slices
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaChange {
+    ColumnAdded {
+        name: String,
+        data_type: String,
+    },
+    ColumnRemoved {
+        name: String,
+        data_type: String,
+    },
+    TypeChanged {
+        name: String,
+        old_type: String,
+        new_type: String,
+    },
+}
+
+impl std::fmt::Display for SchemaChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchemaChange::ColumnAdded { name, data_type } => {
+                write!(f, "column {} {} was added", name, data_type)
+            }
+            SchemaChange::ColumnRemoved { name, data_type } => {
+                write!(f, "column {} {} was removed", name, data_type)
+            }
+            SchemaChange::TypeChanged {
+                name,
+                old_type,
+                new_type,
+            } => write!(
+                f,
+                "column {} changed type from {} to {}",
+                name, old_type, new_type
+            ),
+        }
+    }
+}
+
+/// Outcome of a data quality expectation evaluated on the new data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpectationResult {
+    /// Human-readable description of the expectation
+    pub expectation: String,
+    pub outcome: ExpectationOutcome,
+    pub num_violations: u64,
+    /// Sample of the violations to include in reports
+    pub examples: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpectationOutcome {
+    Passed,
+    /// Offending records were excluded from the data and put aside
+    Quarantined,
+    Failed,
+}
+
+impl std::fmt::Display for ExpectationResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} violation(s)",
+            self.expectation, self.num_violations
+        )?;
+        if !self.examples.is_empty() {
+            write!(f, " ({})", self.examples.join("; "))?;
+        }
+        Ok(())
+    }
 }
 
 ///////////////////////////////////////////////////////////////////////////////
@@ -48,6 +231,10 @@ pub enum IngestStage {
 pub trait IngestListener: Send {
     fn begin(&mut self) {}
     fn on_stage_progress(&mut self, _stage: IngestStage, _n: u64, _out_of: u64) {}
+    /// Called with schema changes that the source's policy allows but warns about
+    fn on_schema_drift(&mut self, _changes: &[SchemaChange]) {}
+    /// Called with results of all data quality expectations once they passed
+    fn on_expectations_checked(&mut self, _results: &[ExpectationResult]) {}
 
     fn success(&mut self, _result: &IngestResult) {}
     fn uncacheable(&mut self) {}
@@ -74,6 +261,8 @@ type BoxedError = Box<dyn std::error::Error + Send + Sync>;
 
 #[derive(Debug, Error)]
 pub enum IngestError {
+    #[error("Dataset {dataset_id} is not a root dataset")]
+    NotARootDataset { dataset_id: DatasetIDBuf },
     #[error("Source is unreachable at {path}")]
     Unreachable {
         path: String,
@@ -86,8 +275,32 @@ pub enum IngestError {
         #[source]
         source: Option<BoxedError>,
     },
+    #[error("Transient failure when fetching {path}")]
+    TransientFailure {
+        path: String,
+        #[source]
+        source: Option<BoxedError>,
+    },
+    #[error("Checksum mismatch for {path}: expected {expected} but got {actual}")]
+    ChecksumMismatch {
+        path: String,
+        expected: String,
+        actual: String,
+    },
+    #[error("Schema of the new data is incompatible: {}", format_changes(.changes))]
+    SchemaDrift { changes: Vec<SchemaChange> },
+    #[error("Data quality expectations failed: {}", format_results(.results))]
+    ExpectationsFailed { results: Vec<ExpectationResult> },
+    #[error("{stage:?} stage failed: {source}")]
+    StageFailed {
+        stage: IngestStage,
+        source: BoxedError,
+        backtrace: Backtrace,
+    },
     #[error("Engine error: {0}")]
     EngineError(#[from] EngineError),
+    #[error("{0}")]
+    Cancelled(#[from] CancelledError),
     #[error("Internal error: {source}")]
     InternalError {
         #[from]
@@ -97,6 +310,12 @@ pub enum IngestError {
 }
 
 impl IngestError {
+    pub fn not_a_root_dataset(dataset_id: &DatasetID) -> Self {
+        IngestError::NotARootDataset {
+            dataset_id: dataset_id.to_owned(),
+        }
+    }
+
     pub fn unreachable<S: AsRef<Path>>(path: S, source: Option<BoxedError>) -> Self {
         IngestError::Unreachable {
             path: path.as_ref().to_str().unwrap().to_owned(),
@@ -111,6 +330,32 @@ impl IngestError {
         }
     }
 
+    pub fn transient_failure<S: AsRef<Path>>(path: S, source: Option<BoxedError>) -> Self {
+        IngestError::TransientFailure {
+            path: path.as_ref().to_str().unwrap().to_owned(),
+            source: source,
+        }
+    }
+
+    pub fn checksum_mismatch<S: AsRef<Path>>(path: S, expected: String, actual: String) -> Self {
+        IngestError::ChecksumMismatch {
+            path: path.as_ref().to_str().unwrap().to_owned(),
+            expected: expected,
+            actual: actual,
+        }
+    }
+
+    pub fn failed_stage(
+        stage: IngestStage,
+        e: impl std::error::Error + Send + Sync + 'static,
+    ) -> Self {
+        IngestError::StageFailed {
+            stage: stage,
+            source: e.into(),
+            backtrace: Backtrace::capture(),
+        }
+    }
+
     pub fn internal(e: impl std::error::Error + Send + Sync + 'static) -> Self {
         IngestError::InternalError {
             source: e.into(),
@@ -118,3 +363,20 @@ impl IngestError {
         }
     }
 }
+
+fn format_changes(changes: &[SchemaChange]) -> String {
+    changes
+        .iter()
+        .map(|c| c.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn format_results(results: &[ExpectationResult]) -> String {
+    results
+        .iter()
+        .filter(|r| r.outcome == ExpectationOutcome::Failed)
+        .map(|r| r.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
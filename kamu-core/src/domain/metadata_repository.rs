@@ -28,6 +28,25 @@ pub trait MetadataRepository {
         dataset_id: &DatasetID,
         summary: DatasetSummary,
     ) -> Result<(), DomainError>;
+
+    /// Returns the default configuration if none was set for the dataset
+    fn get_config(&self, dataset_id: &DatasetID) -> Result<DatasetConfig, DomainError>;
+
+    fn set_config(
+        &mut self,
+        dataset_id: &DatasetID,
+        config: DatasetConfig,
+    ) -> Result<(), DomainError>;
+
+    /// Returns schemas of the dataset's data, oldest first
+    fn get_schema_history(&self, dataset_id: &DatasetID) -> Result<Vec<SchemaRecord>, DomainError>;
+
+    /// Appends the schema to the history unless it matches the latest one
+    fn record_schema(
+        &mut self,
+        dataset_id: &DatasetID,
+        record: SchemaRecord,
+    ) -> Result<(), DomainError>;
 }
 
 pub trait DatasetDependencyVisitor {
@@ -1,5 +1,8 @@
 // Data structures
 
+mod cancellation;
+pub use cancellation::*;
+
 mod error;
 pub use error::*;
 
@@ -11,6 +14,9 @@ pub use dataset_id::*;
 mod time_interval;
 pub use time_interval::*;
 
+mod update_stats;
+pub use update_stats::*;
+
 // Services
 
 mod engine;
@@ -33,3 +39,6 @@ pub use resource_loader::*;
 
 mod transform_service;
 pub use transform_service::*;
+
+mod watermark_service;
+pub use watermark_service::*;
@@ -1,7 +1,8 @@
 use super::ingest_service::*;
 use super::transform_service::*;
-use crate::domain::{DatasetID, DatasetIDBuf};
+use crate::domain::{CancelledError, DatasetID, DatasetIDBuf, DomainError, UpdateStats};
 
+use ::serde::Serialize;
 use std::sync::{Arc, Mutex};
 use thiserror::Error;
 
@@ -10,6 +11,10 @@ use thiserror::Error;
 ///////////////////////////////////////////////////////////////////////////////
 
 pub trait PullService {
+    /// Pulls datasets in the order of their dependencies and returns a result for every one of them.
+    ///
+    /// Datasets that depend on a failed dataset are skipped, while the ones that don't
+    /// are pulled as usual.
     fn pull_multi(
         &mut self,
         dataset_ids: &mut dyn Iterator<Item = &DatasetID>,
@@ -18,12 +23,65 @@ pub trait PullService {
         ingest_listener: Option<Arc<Mutex<dyn IngestMultiListener>>>,
         transform_listener: Option<Arc<Mutex<dyn TransformMultiListener>>>,
     ) -> Vec<(DatasetIDBuf, Result<PullResult, PullError>)>;
+
+    /// Describes what `pull_multi` would do with the same arguments, without running any engine.
+    ///
+    /// Steps are listed in the order of execution. Transformations are planned based
+    /// on the current state of their inputs, so data that the same pull would add
+    /// to the inputs is not accounted for.
+    fn plan_pull(
+        &mut self,
+        dataset_ids: &mut dyn Iterator<Item = &DatasetID>,
+        recursive: bool,
+        all: bool,
+    ) -> Result<Vec<PullPlanStep>, PullError>;
 }
 
 #[derive(Debug)]
 pub enum PullResult {
-    UpToDate,
-    Updated { block_hash: String },
+    /// Stats describe the stages that found no new data
+    UpToDate {
+        stats: UpdateStats,
+    },
+    Updated {
+        block_hash: String,
+        stats: UpdateStats,
+    },
+    Skipped {
+        reason: SkipReason,
+    },
+}
+
+/// Single dataset in a pull plan
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PullPlanStep {
+    pub dataset_id: DatasetIDBuf,
+    /// Datasets of the plan that have to be pulled first
+    pub depends_on: Vec<DatasetIDBuf>,
+    pub action: PullAction,
+}
+
+#[serde(tag = "kind")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum PullAction {
+    Ingest(IngestPlan),
+    Transform(TransformPlan),
+}
+
+/// Why a dataset was not pulled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkipReason {
+    /// One of the dataset's inputs, direct or transitive, failed to pull
+    InputFailed { dataset_id: DatasetIDBuf },
+}
+
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SkipReason::InputFailed { dataset_id } => write!(f, "input {} failed", dataset_id),
+        }
+    }
 }
 
 ///////////////////////////////////////////////////////////////////////////////
@@ -36,4 +94,8 @@ pub enum PullError {
     IngestError(#[from] IngestError),
     #[error("Transform error: {0}")]
     TransformError(#[from] TransformError),
+    #[error("{0}")]
+    DomainError(#[from] DomainError),
+    #[error("{0}")]
+    Cancelled(#[from] CancelledError),
 }
@@ -1,11 +1,16 @@
 use super::DomainError;
-use crate::infra::serde::yaml::DatasetSnapshot;
+use crate::infra::serde::yaml::{DatasetConfig, DatasetSnapshot};
 
+/// Snapshots are loaded along with the dataset configuration they carry,
+/// which is the default one if the snapshot file has none
 pub trait ResourceLoader {
     fn load_dataset_snapshot_from_path(
         &self,
         path: &std::path::Path,
-    ) -> Result<DatasetSnapshot, DomainError>;
+    ) -> Result<(DatasetSnapshot, DatasetConfig), DomainError>;
 
-    fn load_dataset_snapshot_from_ref(&self, sref: &str) -> Result<DatasetSnapshot, DomainError>;
+    fn load_dataset_snapshot_from_ref(
+        &self,
+        sref: &str,
+    ) -> Result<(DatasetSnapshot, DatasetConfig), DomainError>;
 }
@@ -1,7 +1,9 @@
-use super::EngineError;
+use super::{CancelledError, EngineError, InputDataSlice, UpdateStats};
 use crate::domain::{DatasetID, DatasetIDBuf};
 
+use ::serde::Serialize;
 use std::backtrace::Backtrace;
+use std::collections::BTreeMap;
 use std::sync::{Arc, Mutex};
 use thiserror::Error;
 
@@ -21,12 +23,51 @@ pub trait TransformService {
         dataset_ids: &mut dyn Iterator<Item = &DatasetID>,
         listener: Option<Arc<Mutex<dyn TransformMultiListener>>>,
     ) -> Vec<(DatasetIDBuf, Result<TransformResult, TransformError>)>;
+
+    /// Describes what transformation would do given the current state of the inputs
+    fn plan_transform(&mut self, dataset_id: &DatasetID) -> Result<TransformPlan, TransformError>;
+
+    /// Prepares transformation of a dataset as a job that the caller can run on any thread.
+    /// Inputs are read at the time of the call, so all of them should already be up to date.
+    /// The result of the job has to be passed to `complete_transform`.
+    fn prepare_transform(
+        &mut self,
+        dataset_id: &DatasetID,
+        listener: Option<Arc<Mutex<dyn TransformMultiListener>>>,
+    ) -> Result<TransformJob, TransformError>;
+
+    /// Finishes transformation of a dataset using the result of its prepared job
+    fn complete_transform(
+        &mut self,
+        dataset_id: &DatasetID,
+        result: Result<TransformResult, TransformError>,
+    ) -> Result<TransformResult, TransformError>;
+}
+
+/// What transformation of a dataset would do
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TransformPlan {
+    pub engine: String,
+    /// Slices of the inputs that would be processed by every batch, empty when none
+    /// of the inputs has new data
+    pub batches: Vec<BTreeMap<DatasetIDBuf, InputDataSlice>>,
+}
+
+/// Transformation of a single dataset that can be executed on any thread
+pub struct TransformJob {
+    /// Engine that executes the transformation, `None` if there is nothing to do
+    pub engine: Option<String>,
+    pub run: Box<dyn FnOnce() -> Result<TransformResult, TransformError> + Send>,
 }
 
 #[derive(Debug)]
 pub enum TransformResult {
     UpToDate,
-    Updated { block_hash: String },
+    Updated {
+        block_hash: String,
+        stats: UpdateStats,
+    },
 }
 
 ///////////////////////////////////////////////////////////////////////////////
@@ -35,6 +76,8 @@ pub enum TransformResult {
 
 pub trait TransformListener: Send {
     fn begin(&mut self) {}
+    /// Called after each batch of input data was processed and committed
+    fn on_batch_progress(&mut self, _n: u64, _out_of: u64) {}
     fn success(&mut self, _result: &TransformResult) {}
     fn error(&mut self, _error: &TransformError) {}
 }
@@ -64,6 +107,8 @@ type BoxedError = Box<dyn std::error::Error + Send + Sync>;
 pub enum TransformError {
     #[error("Engine error: {0}")]
     EngineError(#[from] EngineError),
+    #[error("{0}")]
+    Cancelled(#[from] CancelledError),
     #[error("Internal error: {source}")]
     InternalError {
         #[from]
@@ -0,0 +1,52 @@
+use super::TimeInterval;
+
+use ::serde::{Serialize, Serializer};
+use chrono::{DateTime, Utc};
+use std::time::{Duration, Instant};
+
+/// What pulling a dataset changed and how long it took
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct UpdateStats {
+    pub records_added: u64,
+    /// Output intervals of the committed blocks, several if the dataset was
+    /// transformed in multiple batches
+    pub output_intervals: Vec<TimeInterval>,
+    pub prev_watermark: Option<DateTime<Utc>>,
+    pub new_watermark: Option<DateTime<Utc>>,
+    /// Schema of the added data in DDL form, `None` if no data was added
+    pub output_schema: Option<Vec<String>>,
+    /// Size of the data fetched from the source, `None` for derivative datasets
+    pub bytes_fetched: Option<u64>,
+    /// Whether data fetched by an earlier pull was used instead of fetching,
+    /// `None` for derivative datasets
+    pub fetched_from_cache: Option<bool>,
+    /// Wall-clock time of every stage in the order of execution
+    pub stages: Vec<StageTime>,
+}
+
+impl UpdateStats {
+    pub fn total_time(&self) -> Duration {
+        self.stages.iter().map(|s| s.duration).sum()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StageTime {
+    pub stage: String,
+    #[serde(rename = "seconds", serialize_with = "serialize_seconds")]
+    pub duration: Duration,
+}
+
+impl StageTime {
+    pub fn since(stage: &str, started: Instant) -> Self {
+        Self {
+            stage: stage.to_owned(),
+            duration: started.elapsed(),
+        }
+    }
+}
+
+fn serialize_seconds<S: Serializer>(duration: &Duration, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_f64(duration.as_secs_f64())
+}
@@ -0,0 +1,44 @@
+use crate::domain::{DatasetID, DatasetIDBuf, DomainError};
+
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+
+///////////////////////////////////////////////////////////////////////////////
+// Service
+///////////////////////////////////////////////////////////////////////////////
+
+pub trait WatermarkService {
+    /// Manually advances the watermark of a root dataset, signaling that
+    /// no data with an older event time is expected to arrive
+    fn set_watermark(
+        &mut self,
+        dataset_id: &DatasetID,
+        watermark: DateTime<Utc>,
+    ) -> Result<WatermarkResult, WatermarkError>;
+}
+
+#[derive(Debug)]
+pub enum WatermarkResult {
+    /// Watermark is already set to the specified time
+    UpToDate,
+    Updated {
+        block_hash: String,
+    },
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Errors
+///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Error)]
+pub enum WatermarkError {
+    #[error("Watermark can only be set on root datasets, {dataset_id} is derivative")]
+    NotARootDataset { dataset_id: DatasetIDBuf },
+    #[error("Watermark {watermark} is older than the current watermark {current}")]
+    NotMonotonic {
+        current: DateTime<Utc>,
+        watermark: DateTime<Utc>,
+    },
+    #[error("{0}")]
+    DomainError(#[from] DomainError),
+}
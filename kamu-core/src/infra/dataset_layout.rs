@@ -11,6 +11,8 @@ pub struct DatasetLayout {
     pub checkpoints_dir: PathBuf,
     /// Stores data that is not essential but can improve performance of operations like data polling
     pub cache_dir: PathBuf,
+    /// Path to the directory containing records rejected by data quality expectations
+    pub quarantine_dir: PathBuf,
 }
 
 impl DatasetLayout {
@@ -19,6 +21,7 @@ impl DatasetLayout {
             data_dir: volume_layout.data_dir.join(dataset_id),
             checkpoints_dir: volume_layout.checkpoints_dir.join(dataset_id),
             cache_dir: volume_layout.cache_dir.join(dataset_id),
+            quarantine_dir: volume_layout.quarantine_dir.join(dataset_id),
         }
     }
 
@@ -44,12 +44,17 @@ impl FlinkEngine {
         run_id: &str,
         in_out_dir: &Path,
         checkpoints_dir: &Path,
+        cancel: &CancellationToken,
     ) -> Result<(), EngineError> {
         let docker = DockerClient::new();
 
+        // Names are unique per run so that multiple jobs can execute concurrently
         let network_name = format!("kamu-flink-{}", run_id);
         let _network = docker.create_network(&network_name);
 
+        let job_manager_name = format!("kamu-flink-jobmanager-{}", run_id);
+        let task_manager_name = format!("kamu-flink-taskmanager-{}", run_id);
+
         let job_manager_stdout_path = self
             .workspace_layout
             .run_info_dir
@@ -81,12 +86,12 @@ impl FlinkEngine {
             DockerRunArgs {
                 image: self.image.clone(),
                 network: Some(network_name.clone()),
-                container_name: Some("jobmanager".to_owned()),
+                container_name: Some(job_manager_name.clone()),
                 hostname: Some("jobmanager".to_owned()),
                 args: vec!["jobmanager".to_owned()],
                 environment_vars: vec![(
                     "JOB_MANAGER_RPC_ADDRESS".to_owned(),
-                    "jobmanager".to_owned(),
+                    job_manager_name.clone(),
                 )],
                 expose_ports: vec![6123, 8081],
                 volume_map: vec![
@@ -106,12 +111,12 @@ impl FlinkEngine {
             DockerRunArgs {
                 image: self.image.clone(),
                 network: Some(network_name.clone()),
-                container_name: Some("taskmanager".to_owned()),
+                container_name: Some(task_manager_name.clone()),
                 hostname: Some("taskmanager".to_owned()),
                 args: vec!["taskmanager".to_owned()],
                 environment_vars: vec![(
                     "JOB_MANAGER_RPC_ADDRESS".to_owned(),
-                    "jobmanager".to_owned(),
+                    job_manager_name.clone(),
                 )],
                 expose_ports: vec![6121, 6122],
                 volume_map: vec![(
@@ -135,7 +140,7 @@ impl FlinkEngine {
             .map(|p| format!("-s {}", p.display()))
             .unwrap_or_default();
 
-        let run_status = docker
+        let mut run_process = docker
             .exec_shell_cmd(
                 ExecArgs::default(),
                 job_manager.name(),
@@ -154,7 +159,16 @@ impl FlinkEngine {
             )
             .stdout(Stdio::from(File::create(&submit_stdout_path)?))
             .stderr(Stdio::from(File::create(&submit_stderr_path)?))
-            .status()?;
+            .spawn()?;
+
+        let run_status =
+            match docker.wait_cancellable(&mut run_process, job_manager.name(), cancel)? {
+                Some(status) => status,
+                None => {
+                    self.discard_savepoints(checkpoints_dir, prev_savepoint)?;
+                    return Err(CancelledError.into());
+                }
+            };
 
         if run_status.success() {
             self.commit_savepoint(prev_savepoint)?;
@@ -209,6 +223,25 @@ impl FlinkEngine {
         Ok(())
     }
 
+    // Removes savepoints that a cancelled job might have started writing
+    fn discard_savepoints(
+        &self,
+        checkpoints_dir: &Path,
+        keep_savepoint: Option<PathBuf>,
+    ) -> Result<(), std::io::Error> {
+        if !checkpoints_dir.exists() {
+            return Ok(());
+        }
+
+        for entry in std::fs::read_dir(checkpoints_dir)? {
+            let path = entry?.path();
+            if path.is_dir() && Some(&path) != keep_savepoint.as_ref() {
+                std::fs::remove_dir_all(path)?;
+            }
+        }
+        Ok(())
+    }
+
     fn write_request<T>(
         &self,
         in_out_dir: &Path,
@@ -258,11 +291,19 @@ impl FlinkEngine {
 }
 
 impl Engine for FlinkEngine {
-    fn ingest(&self, _request: IngestRequest) -> Result<IngestResponse, EngineError> {
+    fn ingest(
+        &self,
+        _request: IngestRequest,
+        _cancel: &CancellationToken,
+    ) -> Result<IngestResponse, EngineError> {
         unimplemented!();
     }
 
-    fn transform(&self, request: ExecuteQueryRequest) -> Result<ExecuteQueryResponse, EngineError> {
+    fn transform(
+        &self,
+        request: ExecuteQueryRequest,
+        cancel: &CancellationToken,
+    ) -> Result<ExecuteQueryResponse, EngineError> {
         let in_out_dir = tempfile::Builder::new()
             .prefix("kamu-transform-")
             .tempdir()?;
@@ -291,7 +332,7 @@ impl Engine for FlinkEngine {
 
         self.write_request(in_out_dir.path(), request_adj, "ExecuteQueryRequest")?;
 
-        self.submit(&run_id, in_out_dir.path(), &checkpoints_dir)?;
+        self.submit(&run_id, in_out_dir.path(), &checkpoints_dir, cancel)?;
 
         self.read_response(in_out_dir.path(), "ExecuteQueryResult")
     }
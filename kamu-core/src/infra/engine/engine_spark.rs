@@ -13,6 +13,7 @@ pub struct SparkEngine {
 }
 
 struct RunInfo {
+    container_name: String,
     in_out_dir: PathBuf,
     stdout_path: PathBuf,
     stderr_path: PathBuf,
@@ -28,6 +29,7 @@ impl RunInfo {
         );
 
         Self {
+            container_name: format!("kamu-spark-{}", run_id),
             in_out_dir: in_out_dir.to_owned(),
             stdout_path: workspace_layout
                 .run_info_dir
@@ -64,7 +66,12 @@ impl SparkEngine {
         self.volume_dir_in_container().join(rel)
     }
 
-    fn submit(&self, app_class: &str, run_info: &RunInfo) -> Result<(), EngineError> {
+    fn submit(
+        &self,
+        app_class: &str,
+        run_info: &RunInfo,
+        cancel: &CancellationToken,
+    ) -> Result<(), EngineError> {
         let docker = DockerClient::new();
 
         let volume_map = vec![
@@ -78,10 +85,11 @@ impl SparkEngine {
         let stdout_file = std::fs::File::create(&run_info.stdout_path)?;
         let stderr_file = std::fs::File::create(&run_info.stderr_path)?;
 
-        let status = docker
+        let mut process = docker
             .run_shell_cmd(
                 DockerRunArgs {
                     image: self.image.clone(),
+                    container_name: Some(run_info.container_name.clone()),
                     volume_map: volume_map,
                     ..DockerRunArgs::default()
                 },
@@ -105,9 +113,14 @@ impl SparkEngine {
             )
             .stdout(std::process::Stdio::from(stdout_file))
             .stderr(std::process::Stdio::from(stderr_file))
-            .status()
+            .spawn()
             .map_err(|e| EngineError::internal(e))?;
 
+        let status = docker
+            .wait_cancellable(&mut process, &run_info.container_name, cancel)
+            .map_err(|e| EngineError::internal(e))?
+            .ok_or(CancelledError)?;
+
         match status.code() {
             Some(code) if code == 0 => Ok(()),
             _ => Err(ProcessError::new(
@@ -171,7 +184,11 @@ impl SparkEngine {
 }
 
 impl Engine for SparkEngine {
-    fn ingest(&self, request: IngestRequest) -> Result<IngestResponse, EngineError> {
+    fn ingest(
+        &self,
+        request: IngestRequest,
+        cancel: &CancellationToken,
+    ) -> Result<IngestResponse, EngineError> {
         let in_out_dir = tempfile::Builder::new().prefix("kamu-ingest-").tempdir()?;
         let run_info = RunInfo::new(in_out_dir.path(), &self.workspace_layout);
 
@@ -186,12 +203,16 @@ impl Engine for SparkEngine {
 
         self.write_request(&run_info, request_adj, "IngestRequest")?;
 
-        self.submit("dev.kamu.engine.spark.ingest.IngestApp", &run_info)?;
+        self.submit("dev.kamu.engine.spark.ingest.IngestApp", &run_info, cancel)?;
 
         self.read_response(&run_info, "IngestResult")
     }
 
-    fn transform(&self, request: ExecuteQueryRequest) -> Result<ExecuteQueryResponse, EngineError> {
+    fn transform(
+        &self,
+        request: ExecuteQueryRequest,
+        cancel: &CancellationToken,
+    ) -> Result<ExecuteQueryResponse, EngineError> {
         let in_out_dir = tempfile::Builder::new()
             .prefix("kamu-transform-")
             .tempdir()?;
@@ -214,7 +235,11 @@ impl Engine for SparkEngine {
 
         self.write_request(&run_info, request_adj, "ExecuteQueryRequest")?;
 
-        self.submit("dev.kamu.engine.spark.transform.TransformApp", &run_info)?;
+        self.submit(
+            "dev.kamu.engine.spark.transform.TransformApp",
+            &run_info,
+            cancel,
+        )?;
 
         self.read_response(&run_info, "ExecuteQueryResult")
     }
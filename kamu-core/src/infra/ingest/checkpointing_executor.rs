@@ -23,16 +23,18 @@ impl CheckpointingExecutor {
         fun: F,
     ) -> Result<Result<ExecutionResult<C>, E>, CheckpointingError>
     where
-        C: Clone + serde::de::DeserializeOwned + serde::ser::Serialize,
+        C: Clone + PartialEq + serde::de::DeserializeOwned + serde::ser::Serialize,
         F: FnOnce(Option<C>) -> Result<ExecutionResult<C>, E>,
         E: std::error::Error,
     {
         let checkpoint_kind = std::any::type_name::<C>();
         let old_checkpoint = self.read_checkpoint(checkpoint_path, checkpoint_kind)?;
 
-        match fun(old_checkpoint) {
+        match fun(old_checkpoint.clone()) {
             Ok(exec_res) => {
-                if !exec_res.was_up_to_date {
+                // Up-to-date steps can still refresh their checkpoint (e.g. new cache validators)
+                if !exec_res.was_up_to_date || old_checkpoint.as_ref() != Some(&exec_res.checkpoint)
+                {
                     self.write_checkpoint(
                         checkpoint_path,
                         exec_res.checkpoint.clone(),
@@ -45,6 +47,14 @@ impl CheckpointingExecutor {
         }
     }
 
+    /// Reads the last checkpoint without executing anything
+    pub fn read<C: serde::de::DeserializeOwned>(
+        &self,
+        checkpoint_path: &Path,
+    ) -> Result<Option<C>, CheckpointingError> {
+        self.read_checkpoint(checkpoint_path, std::any::type_name::<C>())
+    }
+
     fn read_checkpoint<C: serde::de::DeserializeOwned>(
         &self,
         path: &Path,
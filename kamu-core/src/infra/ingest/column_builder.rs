@@ -0,0 +1,450 @@
+use arrow::array::*;
+use arrow::datatypes::{DataType, DateUnit, TimeUnit};
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Controls how textual values are converted into typed ones
+#[derive(Debug, Clone)]
+pub struct ValueFormat {
+    pub null_value: Option<String>,
+    pub nan_value: String,
+    pub positive_inf: String,
+    pub negative_inf: String,
+    /// Date format in `chrono` notation
+    pub date_format: Option<String>,
+    /// Timestamp format in `chrono` notation
+    pub timestamp_format: Option<String>,
+}
+
+impl Default for ValueFormat {
+    // Mirrors the defaults of Spark's CSV reader
+    fn default() -> Self {
+        Self {
+            null_value: Some("".to_owned()),
+            nan_value: "NaN".to_owned(),
+            positive_inf: "Inf".to_owned(),
+            negative_inf: "-Inf".to_owned(),
+            date_format: None,
+            timestamp_format: None,
+        }
+    }
+}
+
+impl ValueFormat {
+    pub fn parse_date(&self, s: &str) -> Option<NaiveDate> {
+        match self.date_format {
+            Some(ref fmt) => NaiveDate::parse_from_str(s, fmt).ok(),
+            None => NaiveDate::parse_from_str(s, "%Y-%m-%d").ok(),
+        }
+    }
+
+    pub fn parse_timestamp(&self, s: &str) -> Option<DateTime<Utc>> {
+        match self.timestamp_format {
+            Some(ref fmt) if fmt.contains("%z") || fmt.contains("%:z") => {
+                DateTime::parse_from_str(s, fmt).ok().map(|dt| dt.into())
+            }
+            Some(ref fmt) => NaiveDateTime::parse_from_str(s, fmt)
+                .ok()
+                .map(|dt| DateTime::from_utc(dt, Utc)),
+            None => DateTime::parse_from_rfc3339(s)
+                .map(|dt| dt.into())
+                .ok()
+                .or_else(|| {
+                    ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"]
+                        .iter()
+                        .filter_map(|fmt| NaiveDateTime::parse_from_str(s, fmt).ok())
+                        .next()
+                        .or_else(|| self.parse_date(s).map(|d| d.and_hms(0, 0, 0)))
+                        .map(|dt| DateTime::from_utc(dt, Utc))
+                }),
+        }
+    }
+
+    fn parse_float(&self, s: &str) -> Option<f64> {
+        if s == self.nan_value {
+            Some(std::f64::NAN)
+        } else if s == self.positive_inf {
+            Some(std::f64::INFINITY)
+        } else if s == self.negative_inf {
+            Some(std::f64::NEG_INFINITY)
+        } else {
+            s.parse().ok()
+        }
+    }
+}
+
+/// Converts a Java `SimpleDateFormat` pattern (as used in source definitions)
+/// into `chrono` format string. Returns `None` if pattern uses unsupported symbols.
+pub fn java_to_chrono_format(pattern: &str) -> Option<String> {
+    let mut out = String::with_capacity(pattern.len() * 2);
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c == '\'' {
+            // Quoted literal, '' is an escaped quote
+            let mut j = i + 1;
+            if j < chars.len() && chars[j] == '\'' {
+                out.push('\'');
+                i = j + 1;
+                continue;
+            }
+            while j < chars.len() && chars[j] != '\'' {
+                push_literal(&mut out, chars[j]);
+                j += 1;
+            }
+            i = j + 1;
+            continue;
+        }
+
+        if !c.is_ascii_alphabetic() {
+            push_literal(&mut out, c);
+            i += 1;
+            continue;
+        }
+
+        let mut n = 1;
+        while i + n < chars.len() && chars[i + n] == c {
+            n += 1;
+        }
+
+        let spec = match (c, n) {
+            ('y', 2) => "%y",
+            ('y', _) => "%Y",
+            ('M', 1) => "%-m",
+            ('M', 2) => "%m",
+            ('M', 3) => "%b",
+            ('M', _) => "%B",
+            ('d', 1) => "%-d",
+            ('d', _) => "%d",
+            ('H', 1) => "%-H",
+            ('H', _) => "%H",
+            ('h', _) => "%I",
+            ('a', _) => "%p",
+            ('m', _) => "%M",
+            ('s', _) => "%S",
+            ('S', _) => "%f",
+            ('E', 1..=3) => "%a",
+            ('E', _) => "%A",
+            ('X', 1) | ('Z', _) => "%z",
+            ('X', _) | ('x', _) => "%:z",
+            _ => return None,
+        };
+
+        // Fractional seconds are always preceded by a dot in practice
+        if spec == "%f" && out.ends_with('.') {
+            out.pop();
+            out.push_str("%.f");
+        } else {
+            out.push_str(spec);
+        }
+
+        i += n;
+    }
+
+    Some(out)
+}
+
+fn push_literal(out: &mut String, c: char) {
+    if c == '%' {
+        out.push_str("%%");
+    } else {
+        out.push(c);
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// ColumnBuilder
+///////////////////////////////////////////////////////////////////////////////
+
+/// Accumulates values of a single column into an Arrow array
+pub enum ColumnBuilder {
+    Utf8(StringBuilder),
+    Binary(BinaryBuilder),
+    Boolean(BooleanBuilder),
+    Int8(Int8Builder),
+    Int16(Int16Builder),
+    Int32(Int32Builder),
+    Int64(Int64Builder),
+    Float32(Float32Builder),
+    Float64(Float64Builder),
+    Date32(Date32Builder),
+    Timestamp(TimestampMillisecondBuilder),
+}
+
+impl ColumnBuilder {
+    pub fn new(data_type: &DataType, capacity: usize) -> Option<Self> {
+        match data_type {
+            DataType::Utf8 => Some(Self::Utf8(StringBuilder::new(capacity))),
+            DataType::Binary => Some(Self::Binary(BinaryBuilder::new(capacity))),
+            DataType::Boolean => Some(Self::Boolean(BooleanBuilder::new(capacity))),
+            DataType::Int8 => Some(Self::Int8(Int8Builder::new(capacity))),
+            DataType::Int16 => Some(Self::Int16(Int16Builder::new(capacity))),
+            DataType::Int32 => Some(Self::Int32(Int32Builder::new(capacity))),
+            DataType::Int64 => Some(Self::Int64(Int64Builder::new(capacity))),
+            DataType::Float32 => Some(Self::Float32(Float32Builder::new(capacity))),
+            DataType::Float64 => Some(Self::Float64(Float64Builder::new(capacity))),
+            DataType::Date32(DateUnit::Day) => Some(Self::Date32(Date32Builder::new(capacity))),
+            DataType::Timestamp(TimeUnit::Millisecond, None) => {
+                Some(Self::Timestamp(TimestampMillisecondBuilder::new(capacity)))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn data_type(&self) -> DataType {
+        match self {
+            Self::Utf8(_) => DataType::Utf8,
+            Self::Binary(_) => DataType::Binary,
+            Self::Boolean(_) => DataType::Boolean,
+            Self::Int8(_) => DataType::Int8,
+            Self::Int16(_) => DataType::Int16,
+            Self::Int32(_) => DataType::Int32,
+            Self::Int64(_) => DataType::Int64,
+            Self::Float32(_) => DataType::Float32,
+            Self::Float64(_) => DataType::Float64,
+            Self::Date32(_) => DataType::Date32(DateUnit::Day),
+            Self::Timestamp(_) => DataType::Timestamp(TimeUnit::Millisecond, None),
+        }
+    }
+
+    pub fn append_null(&mut self) {
+        let res = match self {
+            Self::Utf8(b) => b.append_null(),
+            Self::Binary(b) => b.append_null(),
+            Self::Boolean(b) => b.append_null(),
+            Self::Int8(b) => b.append_null(),
+            Self::Int16(b) => b.append_null(),
+            Self::Int32(b) => b.append_null(),
+            Self::Int64(b) => b.append_null(),
+            Self::Float32(b) => b.append_null(),
+            Self::Float64(b) => b.append_null(),
+            Self::Date32(b) => b.append_null(),
+            Self::Timestamp(b) => b.append_null(),
+        };
+        // Appending to in-memory builders can only fail on allocation
+        res.unwrap();
+    }
+
+    /// Parses the textual representation of the value according to the column type
+    pub fn append_str(
+        &mut self,
+        value: Option<&str>,
+        format: &ValueFormat,
+    ) -> Result<(), ValueParseError> {
+        let s = match value {
+            None => {
+                self.append_null();
+                return Ok(());
+            }
+            Some(s) if format.null_value.as_deref() == Some(s) => {
+                self.append_null();
+                return Ok(());
+            }
+            Some(s) => s,
+        };
+
+        let data_type = self.data_type();
+        let err = || ValueParseError::new(s, data_type.clone());
+
+        let res = match self {
+            Self::Utf8(b) => b.append_value(s),
+            Self::Binary(b) => b.append_value(s.as_bytes()),
+            Self::Boolean(b) => match &s.to_lowercase()[..] {
+                "true" => b.append_value(true),
+                "false" => b.append_value(false),
+                _ => return Err(err()),
+            },
+            Self::Int8(b) => b.append_value(s.trim().parse().map_err(|_| err())?),
+            Self::Int16(b) => b.append_value(s.trim().parse().map_err(|_| err())?),
+            Self::Int32(b) => b.append_value(s.trim().parse().map_err(|_| err())?),
+            Self::Int64(b) => b.append_value(s.trim().parse().map_err(|_| err())?),
+            Self::Float32(b) => {
+                b.append_value(format.parse_float(s.trim()).ok_or_else(err)? as f32)
+            }
+            Self::Float64(b) => b.append_value(format.parse_float(s.trim()).ok_or_else(err)?),
+            Self::Date32(b) => {
+                let d = format.parse_date(s).ok_or_else(err)?;
+                b.append_value(days_since_epoch(d))
+            }
+            Self::Timestamp(b) => {
+                let t = format.parse_timestamp(s).ok_or_else(err)?;
+                b.append_value(t.timestamp_millis())
+            }
+        };
+        res.unwrap();
+        Ok(())
+    }
+
+    /// Appends a value extracted from another array, converting between
+    /// compatible numeric types
+    pub fn append_cell(&mut self, cell: &Cell) -> Result<(), ValueParseError> {
+        let data_type = self.data_type();
+        let err = || ValueParseError::new(&format!("{:?}", cell), data_type.clone());
+
+        let res = match (self, cell) {
+            (b, Cell::Null) => {
+                b.append_null();
+                return Ok(());
+            }
+            (Self::Utf8(b), Cell::Utf8(v)) => b.append_value(v),
+            (Self::Binary(b), Cell::Binary(v)) => b.append_value(v),
+            (Self::Boolean(b), Cell::Boolean(v)) => b.append_value(*v),
+            (Self::Int8(b), Cell::Int(v)) => b.append_value(*v as i8),
+            (Self::Int16(b), Cell::Int(v)) => b.append_value(*v as i16),
+            (Self::Int32(b), Cell::Int(v)) => b.append_value(*v as i32),
+            (Self::Int64(b), Cell::Int(v)) => b.append_value(*v),
+            (Self::Float32(b), Cell::Float(v)) => b.append_value(f64::from_bits(*v) as f32),
+            (Self::Float64(b), Cell::Float(v)) => b.append_value(f64::from_bits(*v)),
+            (Self::Date32(b), Cell::Date32(v)) => b.append_value(*v),
+            (Self::Timestamp(b), Cell::Timestamp(v)) => b.append_value(*v),
+            _ => return Err(err()),
+        };
+        res.unwrap();
+        Ok(())
+    }
+
+    /// Appends raw bytes to a binary column
+    pub fn append_bytes(&mut self, value: &[u8]) -> Result<(), ValueParseError> {
+        match self {
+            Self::Binary(b) => {
+                b.append_value(value).unwrap();
+                Ok(())
+            }
+            _ => Err(ValueParseError::new(
+                &format!("<{} bytes>", value.len()),
+                self.data_type(),
+            )),
+        }
+    }
+
+    pub fn finish(&mut self) -> ArrayRef {
+        match self {
+            Self::Utf8(b) => Arc::new(b.finish()),
+            Self::Binary(b) => Arc::new(b.finish()),
+            Self::Boolean(b) => Arc::new(b.finish()),
+            Self::Int8(b) => Arc::new(b.finish()),
+            Self::Int16(b) => Arc::new(b.finish()),
+            Self::Int32(b) => Arc::new(b.finish()),
+            Self::Int64(b) => Arc::new(b.finish()),
+            Self::Float32(b) => Arc::new(b.finish()),
+            Self::Float64(b) => Arc::new(b.finish()),
+            Self::Date32(b) => Arc::new(b.finish()),
+            Self::Timestamp(b) => Arc::new(b.finish()),
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Cell
+///////////////////////////////////////////////////////////////////////////////
+
+/// Single value of an Arrow array that can be compared and hashed.
+///
+/// Floats are stored as bits and timestamps are normalized to milliseconds.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Cell {
+    Null,
+    Boolean(bool),
+    Int(i64),
+    Float(u64),
+    Utf8(String),
+    Binary(Vec<u8>),
+    Date32(i32),
+    Timestamp(i64),
+}
+
+impl Cell {
+    /// Returns `None` for unsupported array types
+    pub fn from_array(array: &dyn Array, row: usize) -> Option<Self> {
+        if array.is_null(row) {
+            return Some(Cell::Null);
+        }
+
+        macro_rules! value {
+            ($t:ty) => {
+                array.as_any().downcast_ref::<$t>().unwrap().value(row)
+            };
+        }
+
+        let cell = match array.data_type() {
+            DataType::Boolean => Cell::Boolean(value!(BooleanArray)),
+            DataType::Int8 => Cell::Int(value!(Int8Array) as i64),
+            DataType::Int16 => Cell::Int(value!(Int16Array) as i64),
+            DataType::Int32 => Cell::Int(value!(Int32Array) as i64),
+            DataType::Int64 => Cell::Int(value!(Int64Array)),
+            DataType::Float32 => Cell::Float((value!(Float32Array) as f64).to_bits()),
+            DataType::Float64 => Cell::Float(value!(Float64Array).to_bits()),
+            DataType::Utf8 => Cell::Utf8(value!(StringArray).to_owned()),
+            DataType::Binary => Cell::Binary(value!(BinaryArray).to_owned()),
+            DataType::Date32(DateUnit::Day) => Cell::Date32(value!(Date32Array)),
+            DataType::Timestamp(TimeUnit::Second, _) => {
+                Cell::Timestamp(value!(TimestampSecondArray) * 1000)
+            }
+            DataType::Timestamp(TimeUnit::Millisecond, _) => {
+                Cell::Timestamp(value!(TimestampMillisecondArray))
+            }
+            DataType::Timestamp(TimeUnit::Microsecond, _) => {
+                Cell::Timestamp(value!(TimestampMicrosecondArray) / 1000)
+            }
+            DataType::Timestamp(TimeUnit::Nanosecond, _) => {
+                Cell::Timestamp(value!(TimestampNanosecondArray) / 1_000_000)
+            }
+            _ => return None,
+        };
+
+        Some(cell)
+    }
+}
+
+impl std::fmt::Display for Cell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Cell::Null => write!(f, "null"),
+            Cell::Boolean(v) => write!(f, "{}", v),
+            Cell::Int(v) => write!(f, "{}", v),
+            Cell::Float(bits) => write!(f, "{}", f64::from_bits(*bits)),
+            Cell::Utf8(v) => write!(f, "{}", v),
+            Cell::Binary(v) => {
+                for b in v {
+                    write!(f, "{:02x}", b)?;
+                }
+                Ok(())
+            }
+            Cell::Date32(days) => write!(
+                f,
+                "{}",
+                NaiveDate::from_ymd(1970, 1, 1) + Duration::days(*days as i64)
+            ),
+            Cell::Timestamp(ms) => write!(
+                f,
+                "{}",
+                Utc.timestamp_millis(*ms).format("%Y-%m-%d %H:%M:%S%.3f")
+            ),
+        }
+    }
+}
+
+pub fn days_since_epoch(d: NaiveDate) -> i32 {
+    d.signed_duration_since(NaiveDate::from_ymd(1970, 1, 1))
+        .num_days() as i32
+}
+
+#[derive(Error, Debug)]
+#[error("Failed to parse '{value}' as {data_type:?}")]
+pub struct ValueParseError {
+    pub value: String,
+    pub data_type: DataType,
+}
+
+impl ValueParseError {
+    pub fn new(value: &str, data_type: DataType) -> Self {
+        Self {
+            value: value.to_owned(),
+            data_type: data_type,
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use super::*;
+use crate::domain::*;
+use crate::infra::serde::yaml::*;
+use crate::infra::utils::hashing::file_sha3;
+
+use arrow::array::{Array, ArrayRef, Date32Array, TimestampMillisecondArray};
+use arrow::datatypes::{DataType, DateUnit, Field, Schema, SchemaRef, TimeUnit};
+use arrow::record_batch::RecordBatch;
+use chrono::{DateTime, TimeZone, Utc};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const DEFAULT_SYSTEM_TIME_COLUMN: &str = "system_time";
+const DEFAULT_EVENT_TIME_COLUMN: &str = "event_time";
+
+/// Writes records produced by a [NativeReader] into a new Parquet part file,
+/// adding the system and event time columns
+pub struct DataWriter {
+    data_dir: PathBuf,
+    system_time_column: String,
+    event_time_column: String,
+    system_time: DateTime<Utc>,
+    // Used when data doesn't have its own event time column
+    default_event_time: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DataWriteResult {
+    pub output_slice: Option<DataSlice>,
+    pub output_watermark: Option<DateTime<Utc>>,
+}
+
+impl DataWriter {
+    pub fn new(
+        data_dir: &Path,
+        vocab: &DatasetVocabulary,
+        system_time: DateTime<Utc>,
+        source_event_time: Option<DateTime<Utc>>,
+    ) -> Self {
+        Self {
+            data_dir: data_dir.to_owned(),
+            system_time_column: vocab
+                .system_time_column
+                .clone()
+                .unwrap_or(DEFAULT_SYSTEM_TIME_COLUMN.to_owned()),
+            event_time_column: vocab
+                .event_time_column
+                .clone()
+                .unwrap_or(DEFAULT_EVENT_TIME_COLUMN.to_owned()),
+            system_time: system_time,
+            default_event_time: source_event_time.unwrap_or(system_time),
+        }
+    }
+
+    pub fn write(&self, reader: &mut dyn NativeReader) -> Result<DataWriteResult, ReadError> {
+        let in_schema = reader.schema();
+        let (out_schema, has_event_time) = self.output_schema(&in_schema)?;
+
+        std::fs::create_dir_all(&self.data_dir)?;
+
+        let file_name = format!(
+            "{}.snappy.parquet",
+            self.system_time.format("%Y%m%dT%H%M%S%.3fZ")
+        );
+        let out_path = self.data_dir.join(file_name);
+        let tmp_path = out_path.with_extension("tmp");
+
+        let res = self.write_impl(reader, out_schema, has_event_time, &tmp_path);
+
+        match res {
+            Ok((num_records, _)) if num_records == 0 => {
+                std::fs::remove_file(&tmp_path)?;
+                Ok(DataWriteResult {
+                    output_slice: None,
+                    output_watermark: None,
+                })
+            }
+            Ok((num_records, max_event_time)) => {
+                std::fs::rename(&tmp_path, &out_path)?;
+                Ok(DataWriteResult {
+                    output_slice: Some(DataSlice {
+                        hash: file_sha3(&out_path)?,
+                        interval: TimeInterval::singleton(self.system_time),
+                        num_records: num_records as i64,
+                    }),
+                    output_watermark: max_event_time,
+                })
+            }
+            Err(e) => {
+                std::fs::remove_file(&tmp_path).ok();
+                Err(e)
+            }
+        }
+    }
+
+    fn write_impl(
+        &self,
+        reader: &mut dyn NativeReader,
+        out_schema: SchemaRef,
+        has_event_time: bool,
+        path: &Path,
+    ) -> Result<(usize, Option<DateTime<Utc>>), ReadError> {
+        let props = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+
+        let file = File::create(path)?;
+        let mut writer = ArrowWriter::try_new(file, out_schema.clone(), Some(props))?;
+
+        let mut num_records = 0;
+        let mut max_event_time: Option<i64> = None;
+
+        while let Some(batch) = reader.next_batch()? {
+            let num_rows = batch.num_rows();
+
+            let mut columns: Vec<ArrayRef> = Vec::with_capacity(out_schema.fields().len());
+            columns.push(Self::const_timestamp_array(self.system_time, num_rows));
+
+            if has_event_time {
+                let index = batch.schema().index_of(&self.event_time_column)?;
+                let batch_max = Self::max_event_time(batch.column(index).as_ref());
+                max_event_time = max_event_time.max(batch_max);
+            } else {
+                columns.push(Self::const_timestamp_array(
+                    self.default_event_time,
+                    num_rows,
+                ));
+                max_event_time = Some(self.default_event_time.timestamp_millis());
+            }
+
+            columns.extend(batch.columns().iter().cloned());
+
+            writer.write(&RecordBatch::try_new(out_schema.clone(), columns)?)?;
+            num_records += num_rows;
+        }
+
+        writer.close()?;
+
+        Ok((
+            num_records,
+            max_event_time.map(|ms| Utc.timestamp_millis(ms)),
+        ))
+    }
+
+    fn output_schema(&self, in_schema: &Schema) -> Result<(SchemaRef, bool), ReadError> {
+        if in_schema.field_with_name(&self.system_time_column).is_ok() {
+            return Err(ReadError::ReservedColumn {
+                column: self.system_time_column.clone(),
+            });
+        }
+
+        let timestamp_type = DataType::Timestamp(TimeUnit::Millisecond, None);
+
+        let mut fields = vec![Field::new(
+            &self.system_time_column,
+            timestamp_type.clone(),
+            false,
+        )];
+
+        let has_event_time = match in_schema.field_with_name(&self.event_time_column) {
+            Ok(f) => match f.data_type() {
+                DataType::Timestamp(TimeUnit::Millisecond, None)
+                | DataType::Date32(DateUnit::Day) => true,
+                t => {
+                    return Err(ReadError::BadColumnType {
+                        column: self.event_time_column.clone(),
+                        data_type: t.clone(),
+                        role: "event time",
+                    })
+                }
+            },
+            Err(_) => {
+                fields.push(Field::new(&self.event_time_column, timestamp_type, false));
+                false
+            }
+        };
+
+        fields.extend(in_schema.fields().iter().cloned());
+        Ok((Arc::new(Schema::new(fields)), has_event_time))
+    }
+
+    fn const_timestamp_array(t: DateTime<Utc>, len: usize) -> ArrayRef {
+        Arc::new(TimestampMillisecondArray::from(vec![
+            t.timestamp_millis();
+            len
+        ]))
+    }
+
+    fn max_event_time(array: &dyn Array) -> Option<i64> {
+        if let Some(a) = array.as_any().downcast_ref::<TimestampMillisecondArray>() {
+            (0..a.len())
+                .filter(|i| !a.is_null(*i))
+                .map(|i| a.value(i))
+                .max()
+        } else if let Some(a) = array.as_any().downcast_ref::<Date32Array>() {
+            (0..a.len())
+                .filter(|i| !a.is_null(*i))
+                .map(|i| a.value(i) as i64 * 86_400_000)
+                .max()
+        } else {
+            unreachable!()
+        }
+    }
+}
+
+/// Writes batches into a Parquet file as is, replacing the existing file
+pub fn write_parquet_file(
+    path: &Path,
+    schema: SchemaRef,
+    batches: &[RecordBatch],
+) -> Result<(), ReadError> {
+    let props = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+
+    let tmp_path = path.with_extension("tmp");
+    let file = File::create(&tmp_path)?;
+    let mut writer = ArrowWriter::try_new(file, schema, Some(props))?;
+    for batch in batches {
+        writer.write(batch)?;
+    }
+    writer.close()?;
+
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
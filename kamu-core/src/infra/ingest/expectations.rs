@@ -0,0 +1,337 @@
+use super::*;
+use crate::domain::{ExpectationOutcome, ExpectationResult};
+use crate::infra::serde::yaml::*;
+
+use arrow::array::{Array, BooleanArray};
+use arrow::compute::kernels::filter::filter;
+use arrow::datatypes::DataType;
+use arrow::error::ArrowError;
+use arrow::record_batch::RecordBatch;
+use regex::Regex;
+use std::collections::HashSet;
+use thiserror::Error;
+
+const MAX_EXAMPLES: usize = 5;
+
+/// Results of evaluating data quality expectations on a set of batches
+pub struct ExpectationsReport {
+    pub results: Vec<ExpectationResult>,
+    /// Masks of records to keep for every input batch,
+    /// `None` when nothing has to be quarantined
+    pub keep: Option<Vec<BooleanArray>>,
+}
+
+impl ExpectationsReport {
+    pub fn is_failed(&self) -> bool {
+        self.results
+            .iter()
+            .any(|r| r.outcome == ExpectationOutcome::Failed)
+    }
+}
+
+/// Evaluates expectations on the records of all batches together,
+/// so uniqueness and row counts apply to the whole ingested slice
+pub fn check_expectations(
+    expectations: &[Expectation],
+    batches: &[RecordBatch],
+) -> Result<ExpectationsReport, ExpectationError> {
+    let num_rows: usize = batches.iter().map(|b| b.num_rows()).sum();
+
+    let mut results = Vec::new();
+    let mut quarantined = vec![false; num_rows];
+    let mut any_quarantined = false;
+
+    for expectation in expectations {
+        let (description, action, violations) = match expectation {
+            Expectation::NotNull(e) => (
+                format!("not null ({})", e.columns.join(", ")),
+                e.on_violation,
+                check_not_null(&e.columns, batches)?,
+            ),
+            Expectation::Unique(e) => (
+                format!("unique ({})", e.columns.join(", ")),
+                e.on_violation,
+                check_unique(&e.columns, batches)?,
+            ),
+            Expectation::Range(e) => (
+                format!(
+                    "range ({} in [{}, {}])",
+                    e.column,
+                    e.min.as_ref().map(|v| v.to_string()).unwrap_or_default(),
+                    e.max.as_ref().map(|v| v.to_string()).unwrap_or_default()
+                ),
+                e.on_violation,
+                check_range(e, batches)?,
+            ),
+            Expectation::Pattern(e) => (
+                format!("pattern ({} matches {})", e.column, e.regex),
+                e.on_violation,
+                check_pattern(e, batches)?,
+            ),
+            // Row count concerns the whole slice and can't be quarantined
+            Expectation::RowCount(e) => {
+                results.push(check_row_count(e, num_rows));
+                continue;
+            }
+        };
+
+        let outcome = match (violations.is_empty(), action) {
+            (true, _) => ExpectationOutcome::Passed,
+            (false, Some(ExpectationAction::Quarantine)) => ExpectationOutcome::Quarantined,
+            (false, _) => ExpectationOutcome::Failed,
+        };
+
+        if outcome == ExpectationOutcome::Quarantined {
+            any_quarantined = true;
+            for (row, _) in violations.iter() {
+                quarantined[*row] = true;
+            }
+        }
+
+        results.push(ExpectationResult {
+            expectation: description,
+            outcome: outcome,
+            num_violations: violations.len() as u64,
+            examples: violations
+                .into_iter()
+                .take(MAX_EXAMPLES)
+                .map(|(row, reason)| format!("row {}: {}", row, reason))
+                .collect(),
+        });
+    }
+
+    let keep = if any_quarantined {
+        let mut offset = 0;
+        let masks = batches
+            .iter()
+            .map(|b| {
+                let mask = (offset..offset + b.num_rows())
+                    .map(|r| !quarantined[r])
+                    .collect::<Vec<_>>();
+                offset += b.num_rows();
+                BooleanArray::from(mask)
+            })
+            .collect();
+        Some(masks)
+    } else {
+        None
+    };
+
+    Ok(ExpectationsReport {
+        results: results,
+        keep: keep,
+    })
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Checks
+///////////////////////////////////////////////////////////////////////////////
+
+// Each check returns indices of offending records and the reasons
+type Violations = Vec<(usize, String)>;
+
+fn check_not_null(
+    columns: &[String],
+    batches: &[RecordBatch],
+) -> Result<Violations, ExpectationError> {
+    let mut violations = Vec::new();
+
+    let cells: Vec<Vec<Cell>> = columns
+        .iter()
+        .map(|c| column_cells(c, batches))
+        .collect::<Result<_, _>>()?;
+
+    for row in 0..cells.first().map(|c| c.len()).unwrap_or(0) {
+        let null_columns: Vec<&str> = (0..cells.len())
+            .filter(|i| cells[*i][row] == Cell::Null)
+            .map(|i| columns[i].as_str())
+            .collect();
+        if !null_columns.is_empty() {
+            violations.push((row, format!("{} is null", null_columns.join(", "))));
+        }
+    }
+
+    Ok(violations)
+}
+
+fn check_unique(
+    columns: &[String],
+    batches: &[RecordBatch],
+) -> Result<Violations, ExpectationError> {
+    let mut violations = Vec::new();
+
+    let columns: Vec<Vec<Cell>> = columns
+        .iter()
+        .map(|c| column_cells(c, batches))
+        .collect::<Result<_, _>>()?;
+
+    let mut seen = HashSet::new();
+    for row in 0..columns.first().map(|c| c.len()).unwrap_or(0) {
+        let key: Vec<&Cell> = columns.iter().map(|c| &c[row]).collect();
+        if !seen.insert(key.clone()) {
+            let key_str: Vec<String> = key.iter().map(|c| c.to_string()).collect();
+            violations.push((row, format!("duplicate key ({})", key_str.join(", "))));
+        }
+    }
+
+    Ok(violations)
+}
+
+fn check_range(
+    expectation: &ExpectationRange,
+    batches: &[RecordBatch],
+) -> Result<Violations, ExpectationError> {
+    let min = expectation.min.as_ref().and_then(|v| v.as_f64());
+    let max = expectation.max.as_ref().and_then(|v| v.as_f64());
+
+    let mut violations = Vec::new();
+
+    for (row, cell) in column_cells(&expectation.column, batches)?
+        .into_iter()
+        .enumerate()
+    {
+        let value = match cell {
+            Cell::Null => continue,
+            Cell::Int(v) => v as f64,
+            Cell::Float(bits) => f64::from_bits(bits),
+            _ => {
+                return Err(ExpectationError::BadColumnType {
+                    column: expectation.column.clone(),
+                    data_type: column_type(&expectation.column, batches),
+                })
+            }
+        };
+
+        if min.map(|m| value < m).unwrap_or(false) || max.map(|m| value > m).unwrap_or(false) {
+            violations.push((row, format!("value {} is out of range", value)));
+        }
+    }
+
+    Ok(violations)
+}
+
+fn check_pattern(
+    expectation: &ExpectationPattern,
+    batches: &[RecordBatch],
+) -> Result<Violations, ExpectationError> {
+    // Pattern has to match the whole value
+    let re = Regex::new(&format!("^(?:{})$", expectation.regex)).map_err(|e| {
+        ExpectationError::BadPattern {
+            pattern: expectation.regex.clone(),
+            source: e,
+        }
+    })?;
+
+    let mut violations = Vec::new();
+
+    for (row, cell) in column_cells(&expectation.column, batches)?
+        .into_iter()
+        .enumerate()
+    {
+        if cell == Cell::Null {
+            continue;
+        }
+        let value = cell.to_string();
+        if !re.is_match(&value) {
+            violations.push((row, format!("value '{}' does not match", value)));
+        }
+    }
+
+    Ok(violations)
+}
+
+fn check_row_count(expectation: &ExpectationRowCount, num_rows: usize) -> ExpectationResult {
+    let num_rows = num_rows as u64;
+
+    let ok = expectation.min.map(|m| num_rows >= m).unwrap_or(true)
+        && expectation.max.map(|m| num_rows <= m).unwrap_or(true);
+
+    ExpectationResult {
+        expectation: format!(
+            "row count in [{}, {}]",
+            expectation.min.map(|v| v.to_string()).unwrap_or_default(),
+            expectation.max.map(|v| v.to_string()).unwrap_or_default()
+        ),
+        outcome: if ok {
+            ExpectationOutcome::Passed
+        } else {
+            ExpectationOutcome::Failed
+        },
+        num_violations: if ok { 0 } else { 1 },
+        examples: if ok {
+            Vec::new()
+        } else {
+            vec![format!("got {} record(s)", num_rows)]
+        },
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Helpers
+///////////////////////////////////////////////////////////////////////////////
+
+fn column_cells(column: &str, batches: &[RecordBatch]) -> Result<Vec<Cell>, ExpectationError> {
+    let mut cells = Vec::new();
+
+    for batch in batches {
+        let index =
+            batch
+                .schema()
+                .index_of(column)
+                .map_err(|_| ExpectationError::MissingColumn {
+                    column: column.to_owned(),
+                })?;
+        let array = batch.column(index);
+
+        for row in 0..batch.num_rows() {
+            let cell = Cell::from_array(array.as_ref(), row).ok_or_else(|| {
+                ExpectationError::BadColumnType {
+                    column: column.to_owned(),
+                    data_type: array.data_type().clone(),
+                }
+            })?;
+            cells.push(cell);
+        }
+    }
+
+    Ok(cells)
+}
+
+/// Keeps only the records selected by the mask
+pub fn filter_record_batch(
+    batch: &RecordBatch,
+    mask: &BooleanArray,
+) -> Result<RecordBatch, ArrowError> {
+    let columns = batch
+        .columns()
+        .iter()
+        .map(|c| filter(c.as_ref(), mask))
+        .collect::<Result<Vec<_>, _>>()?;
+    RecordBatch::try_new(batch.schema(), columns)
+}
+
+fn column_type(column: &str, batches: &[RecordBatch]) -> DataType {
+    batches
+        .first()
+        .and_then(|b| b.schema().field_with_name(column).ok().cloned())
+        .map(|f| f.data_type().clone())
+        .unwrap_or(DataType::Null)
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Errors
+///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Error, Debug)]
+pub enum ExpectationError {
+    #[error("Column {column} not found")]
+    MissingColumn { column: String },
+    #[error("Column {column} has type {data_type:?} which is not supported by the expectation")]
+    BadColumnType { column: String, data_type: DataType },
+    #[error("Invalid pattern {pattern}: {source}")]
+    BadPattern {
+        pattern: String,
+        #[source]
+        source: regex::Error,
+    },
+}
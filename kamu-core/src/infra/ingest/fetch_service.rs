@@ -2,20 +2,76 @@ use super::*;
 use crate::domain::*;
 use crate::infra::serde::yaml::formats::{datetime_rfc3339, datetime_rfc3339_opt};
 use crate::infra::serde::yaml::*;
+use crate::infra::utils::hashing::{file_sha256, file_sha3};
 
-use chrono::{DateTime, SubsecRound, Utc};
+use chrono::{DateTime, SubsecRound, TimeZone, Utc};
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
+use std::cell::Cell;
 use std::io::prelude::*;
-use std::path::Path;
-use std::time::Duration;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use url::Url;
 
-pub struct FetchService {}
+pub struct FetchService {
+    options: FetchOptions,
+    config: FetchConfig,
+    secret_resolver: SecretResolver,
+    cancel: CancellationToken,
+}
+
+#[derive(Debug, Clone)]
+pub struct FetchOptions {
+    pub connect_timeout: Duration,
+    /// Transfers that receive no data for this long are aborted and retried
+    pub stall_timeout: Duration,
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled on every subsequent one
+    pub retry_backoff: Duration,
+    /// Directory that `${{ secrets.NAME }}` references are resolved against
+    pub secrets_dir: Option<PathBuf>,
+}
+
+impl Default for FetchOptions {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(30),
+            stall_timeout: Duration::from_secs(60),
+            max_retries: 3,
+            retry_backoff: Duration::from_secs(1),
+            secrets_dir: None,
+        }
+    }
+}
 
 impl FetchService {
     pub fn new() -> Self {
-        Self {}
+        Self::with_options(FetchOptions::default())
+    }
+
+    pub fn with_options(options: FetchOptions) -> Self {
+        Self {
+            secret_resolver: SecretResolver::new(options.secrets_dir.as_deref()),
+            options: options,
+            config: FetchConfig::default(),
+            cancel: CancellationToken::new(),
+        }
+    }
+
+    /// Sets request headers, authentication and the expected checksum of the dataset's URL source
+    pub fn with_config(self, config: FetchConfig) -> Self {
+        Self {
+            config: config,
+            ..self
+        }
+    }
+
+    /// Aborts transfers in progress once the token gets cancelled
+    pub fn with_cancellation(self, cancel: CancellationToken) -> Self {
+        Self {
+            cancel: cancel,
+            ..self
+        }
     }
 
     pub fn fetch(
@@ -28,10 +84,20 @@ impl FetchService {
         let mut null_listener = NullFetchProgressListener {};
         let listener = maybe_listener.unwrap_or(&mut null_listener);
 
+        self.cancel.check()?;
+
         match fetch_step {
             FetchStep::Url(ref furl) => {
                 let url = Url::parse(&furl.url).map_err(|e| IngestError::internal(e))?;
-                match url.scheme() {
+                let checksum = match self.config.checksum {
+                    Some(ref c) => Some(
+                        Checksum::parse(c)
+                            .map_err(|e| IngestError::failed_stage(IngestStage::Fetch, e))?,
+                    ),
+                    None => None,
+                };
+
+                let res = match url.scheme() {
                     "file" => self.fetch_file(
                         &url.to_file_path()
                             .map_err(|_| BadUrlError::new(&furl.url))?,
@@ -42,6 +108,7 @@ impl FetchService {
                     ),
                     "http" | "https" => self.fetch_http(
                         &furl.url,
+                        &self.http_request_options()?,
                         furl.event_time.as_ref(),
                         old_checkpoint,
                         target,
@@ -54,13 +121,122 @@ impl FetchService {
                         target,
                         listener,
                     ),
-                    _ => unimplemented!(),
+                    _ => Err(IngestError::failed_stage(
+                        IngestStage::Fetch,
+                        UnsupportedSourceError::new(&furl.url),
+                    )),
+                }?;
+
+                self.verify_fetched(&furl.url, checksum.as_ref(), res, target)
+            }
+            FetchStep::FilesGlob(ref fglob) => Err(IngestError::failed_stage(
+                IngestStage::Fetch,
+                UnsupportedSourceError::new(&fglob.path),
+            )),
+        }
+    }
+
+    /// Tells whether the source changed since the checkpoint was made without transferring
+    /// any data. Only local files can be checked this way, remote sources need a request.
+    pub fn check_cache(
+        &self,
+        fetch_step: &FetchStep,
+        checkpoint: &FetchCheckpoint,
+    ) -> Result<FetchCacheState, IngestError> {
+        let local_path = match fetch_step {
+            FetchStep::Url(ref furl) => {
+                let url = Url::parse(&furl.url).map_err(|e| IngestError::internal(e))?;
+                match url.scheme() {
+                    "file" => Some(
+                        url.to_file_path()
+                            .map_err(|_| BadUrlError::new(&furl.url))?,
+                    ),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+
+        let last_fetched = checkpoint.last_fetched;
+
+        match local_path {
+            None => Ok(FetchCacheState::NeedsCheck {
+                last_fetched: last_fetched,
+            }),
+            Some(path) => {
+                let (_, mod_time) = Self::file_metadata(&path)?;
+                if checkpoint.last_modified == Some(mod_time) {
+                    Ok(FetchCacheState::UpToDate {
+                        last_fetched: last_fetched,
+                    })
+                } else {
+                    Ok(FetchCacheState::Modified {
+                        last_fetched: last_fetched,
+                    })
                 }
             }
-            _ => unimplemented!(),
         }
     }
 
+    fn file_metadata(path: &Path) -> Result<(std::fs::Metadata, DateTime<Utc>), IngestError> {
+        let meta = std::fs::metadata(path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => IngestError::not_found(path, Some(e.into())),
+            _ => IngestError::internal(Box::new(e)),
+        })?;
+
+        let mod_time: DateTime<Utc> = meta
+            .modified()
+            .map(|t| -> DateTime<Utc> { t.into() })
+            .expect("File modification time is not available on this platform")
+            .round_subsecs(3);
+
+        Ok((meta, mod_time))
+    }
+
+    // Only freshly fetched data is verified, cached data was verified when it was fetched
+    fn verify_fetched(
+        &self,
+        url: &str,
+        checksum: Option<&Checksum>,
+        mut res: ExecutionResult<FetchCheckpoint>,
+        target: &Path,
+    ) -> Result<ExecutionResult<FetchCheckpoint>, IngestError> {
+        if res.was_up_to_date {
+            return Ok(res);
+        }
+
+        // Digest is always recorded for provenance
+        if res.checkpoint.content_hash.is_none() {
+            res.checkpoint.content_hash =
+                Some(file_sha3(target).map_err(|e| IngestError::internal(e))?);
+        }
+
+        if let Some(checksum) = checksum {
+            let actual = match checksum.algorithm {
+                ChecksumAlgorithm::Sha256 => {
+                    file_sha256(target).map_err(|e| IngestError::internal(e))?
+                }
+                ChecksumAlgorithm::Sha3_256 => res.checkpoint.content_hash.clone().unwrap(),
+            };
+
+            if actual != checksum.digest {
+                // Don't let unexpected data reach the later stages
+                std::fs::remove_file(target).map_err(|e| IngestError::internal(e))?;
+                return Err(IngestError::checksum_mismatch(
+                    url,
+                    checksum.to_string(),
+                    Checksum {
+                        algorithm: checksum.algorithm,
+                        digest: actual,
+                    }
+                    .to_string(),
+                ));
+            }
+        }
+
+        Ok(res)
+    }
+
     // TODO: Validate event_time_source
     // TODO: Support event time from ctime/modtime
     fn fetch_file(
@@ -73,16 +249,7 @@ impl FetchService {
     ) -> Result<ExecutionResult<FetchCheckpoint>, IngestError> {
         use fs_extra::file::*;
 
-        let meta = std::fs::metadata(path).map_err(|e| match e.kind() {
-            std::io::ErrorKind::NotFound => IngestError::not_found(path, Some(e.into())),
-            _ => IngestError::internal(Box::new(e)),
-        })?;
-
-        let mod_time: DateTime<Utc> = meta
-            .modified()
-            .map(|t| -> DateTime<Utc> { t.into() })
-            .expect("File modification time is not available on this platform")
-            .round_subsecs(3);
+        let (meta, mod_time) = Self::file_metadata(path)?;
 
         if let Some(cp) = old_checkpoint {
             if cp.last_modified == Some(mod_time) {
@@ -98,6 +265,8 @@ impl FetchService {
             last_modified: Some(mod_time),
             etag: None,
             source_event_time: Some(mod_time),
+            size: Some(meta.len()),
+            content_hash: None,
         };
 
         let mut options = CopyOptions::new();
@@ -123,24 +292,181 @@ impl FetchService {
         })
     }
 
+    // Secrets are resolved right before the request so that their values
+    // never end up in the metadata or in checkpoints
+    fn http_request_options(&self) -> Result<HttpRequestOptions, IngestError> {
+        let resolve = |value: &str| {
+            self.secret_resolver
+                .resolve(value)
+                .map_err(|e| IngestError::failed_stage(IngestStage::Fetch, e))
+        };
+
+        let mut options = HttpRequestOptions {
+            headers: Vec::new(),
+            basic_auth: None,
+        };
+
+        for header in self.config.headers.iter().flatten() {
+            options
+                .headers
+                .push(format!("{}: {}", header.name, resolve(&header.value)?));
+        }
+
+        match self.config.auth {
+            None => (),
+            Some(FetchAuth::Basic(ref auth)) => {
+                options.basic_auth = Some((resolve(&auth.username)?, resolve(&auth.password)?));
+            }
+            Some(FetchAuth::Bearer(ref auth)) => {
+                options
+                    .headers
+                    .push(format!("Authorization: Bearer {}", resolve(&auth.token)?));
+            }
+        }
+
+        Ok(options)
+    }
+
     fn fetch_http(
         &self,
         url: &str,
+        request_options: &HttpRequestOptions,
         _event_time_source: Option<&EventTimeSource>,
         old_checkpoint: Option<FetchCheckpoint>,
         target_path: &Path,
         listener: &mut dyn FetchProgressListener,
     ) -> Result<ExecutionResult<FetchCheckpoint>, IngestError> {
         let target_path_tmp = target_path.with_extension("tmp");
+        // Stores ETag or Last-Modified of the partially downloaded file
+        // so download can be resumed by a later attempt or a later run
+        let validator_path = target_path.with_extension("tmp.validator");
+
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+
+            // Partial data is not kept for resuming as cancelled pulls should leave nothing behind
+            if self.cancel.is_cancelled() {
+                std::fs::remove_file(&target_path_tmp).ok();
+                std::fs::remove_file(&validator_path).ok();
+                return Err(CancelledError.into());
+            }
+
+            let res = self.fetch_http_attempt(
+                url,
+                request_options,
+                old_checkpoint.as_ref(),
+                &target_path_tmp,
+                &validator_path,
+                listener,
+            );
+
+            match res {
+                Ok(HttpFetchResult::Fetched(checkpoint)) => {
+                    std::fs::remove_file(&validator_path).ok();
+
+                    // Servers that send no validators can only be checked by content
+                    let content_hash =
+                        file_sha3(&target_path_tmp).map_err(|e| IngestError::internal(e))?;
+
+                    let was_up_to_date = old_checkpoint
+                        .as_ref()
+                        .map(|cp| cp.content_hash.as_ref() == Some(&content_hash))
+                        .unwrap_or(false);
+
+                    // New validators are kept even when content is the same
+                    if was_up_to_date {
+                        std::fs::remove_file(&target_path_tmp)
+                            .map_err(|e| IngestError::internal(e))?;
+                    } else {
+                        std::fs::rename(&target_path_tmp, target_path)
+                            .map_err(|e| IngestError::internal(e))?;
+                    }
+
+                    return Ok(ExecutionResult {
+                        was_up_to_date: was_up_to_date,
+                        checkpoint: FetchCheckpoint {
+                            content_hash: Some(content_hash),
+                            ..checkpoint
+                        },
+                    });
+                }
+                Ok(HttpFetchResult::NotModified) => {
+                    // Only returned when a previous checkpoint exists
+                    return Ok(ExecutionResult {
+                        was_up_to_date: true,
+                        checkpoint: old_checkpoint.unwrap(),
+                    });
+                }
+                Err(HttpAttemptError::Retryable(_)) if attempt <= self.options.max_retries => {
+                    let backoff = self.options.retry_backoff * 2u32.pow(attempt - 1);
+                    self.sleep_cancellable(backoff);
+                }
+                Err(HttpAttemptError::Retryable(e)) | Err(HttpAttemptError::Fatal(e)) => {
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    // Wakes up early if cancelled, the caller is expected to check the token
+    fn sleep_cancellable(&self, duration: Duration) {
+        let deadline = Instant::now() + duration;
+        while !self.cancel.is_cancelled() {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            std::thread::sleep(std::cmp::min(deadline - now, Duration::from_millis(100)));
+        }
+    }
+
+    fn fetch_http_attempt(
+        &self,
+        url: &str,
+        request_options: &HttpRequestOptions,
+        old_checkpoint: Option<&FetchCheckpoint>,
+        target_path_tmp: &Path,
+        validator_path: &Path,
+        listener: &mut dyn FetchProgressListener,
+    ) -> Result<HttpFetchResult, HttpAttemptError> {
+        let cleanup = || {
+            std::fs::remove_file(target_path_tmp).ok();
+            std::fs::remove_file(validator_path).ok();
+        };
+
+        // Resume only when we know which version of the file we have partially
+        let old_validator = std::fs::read_to_string(validator_path).ok();
+        let resume_from = match (&old_validator, std::fs::metadata(target_path_tmp)) {
+            (Some(_), Ok(meta)) => meta.len(),
+            _ => 0,
+        };
+        if resume_from == 0 {
+            cleanup();
+        }
 
         let mut h = curl::easy::Easy::new();
         h.url(url)?;
         h.get(true)?;
-        h.connect_timeout(Duration::from_secs(30))?;
+        h.connect_timeout(self.options.connect_timeout)?;
+        // Abort stalled transfers so they can be retried
+        h.low_speed_limit(1)?;
+        h.low_speed_time(self.options.stall_timeout)?;
         h.progress(true)?;
 
-        if let Some(ref cp) = old_checkpoint {
-            let mut list = curl::easy::List::new();
+        if let Some((ref username, ref password)) = request_options.basic_auth {
+            h.username(username)?;
+            h.password(password)?;
+        }
+
+        let mut list = curl::easy::List::new();
+        for header in request_options.headers.iter() {
+            list.append(header)?;
+        }
+        if resume_from > 0 {
+            list.append(&format!("Range: bytes={}-", resume_from))?;
+            list.append(&format!("If-Range: {}", old_validator.as_ref().unwrap()))?;
+        } else if let Some(cp) = old_checkpoint {
             if let Some(ref etag) = cp.etag {
                 list.append(&format!("If-None-Match: {}", etag))?;
             } else if let Some(ref last_modified) = cp.last_modified {
@@ -149,27 +475,51 @@ impl FetchService {
                     last_modified.to_rfc2822()
                 ))?;
             }
-            h.http_headers(list)?;
         }
+        h.http_headers(list)?;
 
+        let status: Cell<Option<u32>> = Cell::new(None);
         let mut last_modified: Option<DateTime<Utc>> = None;
+        let mut last_modified_raw: Option<String> = None;
         let mut etag: Option<String> = None;
-        {
-            let mut target_file =
-                std::fs::File::create(&target_path_tmp).map_err(|e| IngestError::internal(e))?;
+        let mut content_length: Option<u64> = None;
+        let mut truncated = false;
+        let mut write_error: Option<std::io::Error> = None;
+
+        let perform_res = {
+            let mut target_file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(target_path_tmp)
+                .map_err(|e| HttpAttemptError::Fatal(IngestError::internal(e)))?;
 
             let mut transfer = h.transfer();
 
             transfer.header_function(|header| {
-                let s = std::str::from_utf8(header).unwrap();
-                if let Some((name, val)) = self.split_header(s) {
+                let s = match std::str::from_utf8(header) {
+                    Ok(s) => s,
+                    Err(_) => return true,
+                };
+                if s.starts_with("HTTP/") {
+                    // Headers of every response (e.g. after redirect) start with a status line
+                    status.set(s.split_whitespace().nth(1).and_then(|c| c.parse().ok()));
+                    last_modified = None;
+                    last_modified_raw = None;
+                    etag = None;
+                    content_length = None;
+                } else if let Some((name, val)) = self.split_header(s) {
                     match &name.to_lowercase()[..] {
                         "last-modified" => {
-                            last_modified = Some(self.parse_http_date_time(val));
+                            // Malformed dates are treated as if the header was absent
+                            last_modified = self.parse_http_date_time(val);
+                            last_modified_raw = last_modified.map(|_| val.to_owned());
                         }
                         "etag" => {
                             etag = Some(val.to_owned());
                         }
+                        "content-length" => {
+                            content_length = val.parse().ok();
+                        }
                         _ => (),
                     }
                 }
@@ -177,96 +527,205 @@ impl FetchService {
             })?;
 
             transfer.write_function(|data| {
-                let written = target_file.write(data).unwrap();
-                Ok(written)
+                match status.get() {
+                    Some(200) | Some(206) => (),
+                    // Don't mix error pages into the data
+                    _ => return Ok(data.len()),
+                }
+                // Server ignored the range request and is sending the whole file
+                if !truncated && resume_from > 0 && status.get() != Some(206) {
+                    if let Err(e) = target_file.set_len(0) {
+                        write_error = Some(e);
+                        // Aborts the transfer
+                        return Ok(0);
+                    }
+                    truncated = true;
+                }
+                if let Err(e) = target_file.write_all(data) {
+                    write_error = Some(e);
+                    return Ok(0);
+                }
+                Ok(data.len())
             })?;
 
             transfer.progress_function(|f_total, f_downloaded, _, _| {
-                let total = f_total as u64;
-                let downloaded = f_downloaded as u64;
+                let offset = if status.get() == Some(206) {
+                    resume_from
+                } else {
+                    0
+                };
+                let total = f_total as u64 + offset;
+                let downloaded = f_downloaded as u64 + offset;
                 if downloaded > 0 {
                     listener.on_progress(&FetchProgress {
                         total_bytes: std::cmp::max(total, downloaded),
                         fetched_bytes: downloaded,
                     });
                 }
-                true
+                // Aborts the transfer
+                !self.cancel.is_cancelled()
             })?;
 
-            transfer.perform().map_err(|e| {
-                std::fs::remove_file(&target_path_tmp).unwrap();
-                match e.code() {
-                    curl_sys::CURLE_COULDNT_RESOLVE_HOST => {
-                        IngestError::unreachable(url, Some(e.into()))
-                    }
-                    curl_sys::CURLE_COULDNT_CONNECT => {
-                        IngestError::unreachable(url, Some(e.into()))
-                    }
-                    _ => IngestError::internal(e),
+            transfer.perform()
+        };
+
+        let validator = match status.get() {
+            Some(206) => etag.clone().or(last_modified_raw).or(old_validator),
+            _ => etag.clone().or(last_modified_raw),
+        };
+
+        if let Err(e) = perform_res {
+            if let Some(write_error) = write_error {
+                cleanup();
+                return Err(HttpAttemptError::Fatal(IngestError::internal(write_error)));
+            }
+            if e.is_aborted_by_callback() {
+                cleanup();
+                return Err(HttpAttemptError::Fatal(CancelledError.into()));
+            }
+
+            // Keep partial data if it can be resumed later
+            match (&validator, status.get()) {
+                (Some(v), Some(200)) | (Some(v), Some(206)) => {
+                    std::fs::write(validator_path, v).ok();
                 }
-            })?;
+                _ => cleanup(),
+            }
+            return Err(match e.code() {
+                curl_sys::CURLE_COULDNT_RESOLVE_HOST => {
+                    HttpAttemptError::Fatal(IngestError::unreachable(url, Some(e.into())))
+                }
+                curl_sys::CURLE_COULDNT_CONNECT => {
+                    HttpAttemptError::Retryable(IngestError::unreachable(url, Some(e.into())))
+                }
+                curl_sys::CURLE_OPERATION_TIMEDOUT
+                | curl_sys::CURLE_PARTIAL_FILE
+                | curl_sys::CURLE_RECV_ERROR
+                | curl_sys::CURLE_SEND_ERROR
+                | curl_sys::CURLE_GOT_NOTHING => {
+                    HttpAttemptError::Retryable(IngestError::transient_failure(url, Some(e.into())))
+                }
+                _ => HttpAttemptError::Fatal(IngestError::internal(e)),
+            });
         }
 
         match h.response_code()? {
-            200 => {
-                std::fs::rename(target_path_tmp, target_path).unwrap();
-                Ok(ExecutionResult {
-                    was_up_to_date: false,
-                    checkpoint: FetchCheckpoint {
-                        last_fetched: Utc::now(),
-                        last_modified: last_modified,
-                        etag: etag,
-                        source_event_time: last_modified,
-                    },
-                })
+            code @ 200 | code @ 206 => {
+                let offset = if code == 206 { resume_from } else { 0 };
+                let actual_len = std::fs::metadata(target_path_tmp)
+                    .map_err(|e| HttpAttemptError::Fatal(IngestError::internal(e)))?
+                    .len();
+
+                if let Some(expected_len) = content_length.map(|l| l + offset) {
+                    if actual_len != expected_len {
+                        if actual_len < expected_len && validator.is_some() {
+                            std::fs::write(validator_path, validator.unwrap()).ok();
+                        } else {
+                            cleanup();
+                        }
+                        return Err(HttpAttemptError::Retryable(IngestError::transient_failure(
+                            url,
+                            Some(ContentLengthError::new(expected_len, actual_len).into()),
+                        )));
+                    }
+                }
+
+                Ok(HttpFetchResult::Fetched(FetchCheckpoint {
+                    last_fetched: Utc::now(),
+                    last_modified: last_modified,
+                    etag: etag,
+                    source_event_time: last_modified,
+                    size: Some(actual_len),
+                    content_hash: None,
+                }))
+            }
+            // Without a checkpoint the 304 can only come from user-supplied or resume
+            // validators, so we start over with a clean request
+            304 if old_checkpoint.is_none() => {
+                cleanup();
+                Err(HttpAttemptError::Retryable(IngestError::transient_failure(
+                    url,
+                    Some(HttpStatusError::new(304).into()),
+                )))
             }
             304 => {
-                std::fs::remove_file(&target_path_tmp).unwrap();
-                Ok(ExecutionResult {
-                    was_up_to_date: true,
-                    checkpoint: old_checkpoint.unwrap(),
-                })
+                cleanup();
+                Ok(HttpFetchResult::NotModified)
             }
             404 => {
-                std::fs::remove_file(&target_path_tmp).unwrap();
-                Err(IngestError::not_found(url, None))
+                cleanup();
+                Err(HttpAttemptError::Fatal(IngestError::not_found(url, None)))
+            }
+            // Partial data is no longer valid
+            416 => {
+                cleanup();
+                Err(HttpAttemptError::Retryable(IngestError::transient_failure(
+                    url,
+                    Some(HttpStatusError::new(416).into()),
+                )))
             }
+            // Partial data from previous attempts is kept for resuming
+            code @ 408 | code @ 429 | code @ 500..=599 => Err(HttpAttemptError::Retryable(
+                IngestError::transient_failure(url, Some(HttpStatusError::new(code).into())),
+            )),
             code => {
-                std::fs::remove_file(&target_path_tmp).unwrap();
-                Err(IngestError::unreachable(
+                cleanup();
+                Err(HttpAttemptError::Fatal(IngestError::unreachable(
                     url,
                     Some(HttpStatusError::new(code).into()),
-                ))
+                )))
             }
         }
     }
 
-    // TODO: not implementing caching as some FTP servers throw errors at us
-    // when we request filetime :(
+    // Caching is best-effort: some FTP servers throw errors at us when we
+    // request file time or size, so when those are not available we download
+    // the file anyway and compare content hashes
     fn fetch_ftp(
         &self,
         url: &str,
         _event_time_source: Option<&EventTimeSource>,
-        _old_checkpoint: Option<FetchCheckpoint>,
+        old_checkpoint: Option<FetchCheckpoint>,
         target_path: &Path,
         listener: &mut dyn FetchProgressListener,
     ) -> Result<ExecutionResult<FetchCheckpoint>, IngestError> {
         let target_path_tmp = target_path.with_extension("tmp");
 
+        let (last_modified, size) = self.probe_ftp(url);
+
+        if let Some(cp) = old_checkpoint.as_ref() {
+            if last_modified.is_some()
+                && size.is_some()
+                && cp.last_modified == last_modified
+                && cp.size == size
+            {
+                return Ok(ExecutionResult {
+                    was_up_to_date: true,
+                    checkpoint: old_checkpoint.unwrap(),
+                });
+            }
+        }
+
         let mut h = curl::easy::Easy::new();
-        h.connect_timeout(Duration::from_secs(30))?;
+        h.connect_timeout(self.options.connect_timeout)?;
         h.url(url)?;
         h.progress(true)?;
 
-        {
+        let mut write_error: Option<std::io::Error> = None;
+
+        let perform_res = {
             let mut target_file =
                 std::fs::File::create(&target_path_tmp).map_err(|e| IngestError::internal(e))?;
 
             let mut transfer = h.transfer();
 
-            transfer.write_function(|data| {
-                let written = target_file.write(data).unwrap();
-                Ok(written)
+            transfer.write_function(|data| match target_file.write_all(data) {
+                Ok(()) => Ok(data.len()),
+                Err(e) => {
+                    write_error = Some(e);
+                    // Aborts the transfer
+                    Ok(0)
+                }
             })?;
 
             transfer.progress_function(|f_total, f_downloaded, _, _| {
@@ -278,35 +737,89 @@ impl FetchService {
                         fetched_bytes: downloaded,
                     });
                 }
-                true
+                // Aborts the transfer
+                !self.cancel.is_cancelled()
             })?;
 
-            transfer.perform().map_err(|e| {
-                std::fs::remove_file(&target_path_tmp).unwrap();
-                match e.code() {
-                    curl_sys::CURLE_COULDNT_RESOLVE_HOST => {
-                        IngestError::unreachable(url, Some(e.into()))
-                    }
-                    curl_sys::CURLE_COULDNT_CONNECT => {
-                        IngestError::unreachable(url, Some(e.into()))
-                    }
-                    _ => IngestError::internal(e),
+            transfer.perform()
+        };
+
+        if let Err(e) = perform_res {
+            std::fs::remove_file(&target_path_tmp).map_err(|e| IngestError::internal(e))?;
+            if let Some(write_error) = write_error {
+                return Err(IngestError::internal(write_error));
+            }
+            if e.is_aborted_by_callback() {
+                return Err(IngestError::Cancelled(CancelledError));
+            }
+            return Err(match e.code() {
+                curl_sys::CURLE_COULDNT_RESOLVE_HOST => {
+                    IngestError::unreachable(url, Some(e.into()))
                 }
-            })?;
+                curl_sys::CURLE_COULDNT_CONNECT => IngestError::unreachable(url, Some(e.into())),
+                _ => IngestError::internal(e),
+            });
+        }
+
+        let content_hash = file_sha3(&target_path_tmp).map_err(|e| IngestError::internal(e))?;
+
+        let was_up_to_date = old_checkpoint
+            .as_ref()
+            .map(|cp| cp.content_hash.as_ref() == Some(&content_hash))
+            .unwrap_or(false);
+
+        // New file time and size are kept even when content is the same
+        if was_up_to_date {
+            std::fs::remove_file(&target_path_tmp).map_err(|e| IngestError::internal(e))?;
+        } else {
+            std::fs::rename(target_path_tmp, target_path).map_err(|e| IngestError::internal(e))?;
         }
 
-        std::fs::rename(target_path_tmp, target_path).unwrap();
         Ok(ExecutionResult {
-            was_up_to_date: false,
+            was_up_to_date: was_up_to_date,
             checkpoint: FetchCheckpoint {
                 last_fetched: Utc::now(),
-                last_modified: None,
+                last_modified: last_modified,
                 etag: None,
-                source_event_time: None,
+                source_event_time: last_modified,
+                size: size,
+                content_hash: Some(content_hash),
             },
         })
     }
 
+    // Requests file time (MDTM) and size (SIZE) without downloading the file.
+    // Any failures are ignored as the subsequent download will report them.
+    fn probe_ftp(&self, url: &str) -> (Option<DateTime<Utc>>, Option<u64>) {
+        let mut h = curl::easy::Easy::new();
+
+        let mut probe = || -> Result<(), curl::Error> {
+            h.connect_timeout(self.options.connect_timeout)?;
+            h.url(url)?;
+            h.nobody(true)?;
+            h.fetch_filetime(true)?;
+            h.perform()
+        };
+
+        if probe().is_err() {
+            return (None, None);
+        }
+
+        let last_modified = h
+            .filetime()
+            .ok()
+            .and_then(|t| t)
+            .map(|t| Utc.timestamp(t, 0));
+
+        let size = h
+            .content_length_download()
+            .ok()
+            .filter(|s| *s >= 0.0)
+            .map(|s| s as u64);
+
+        (last_modified, size)
+    }
+
     fn split_header<'a>(&self, h: &'a str) -> Option<(&'a str, &'a str)> {
         if let Some(sep) = h.find(':') {
             let (name, tail) = h.split_at(sep);
@@ -317,10 +830,8 @@ impl FetchService {
         }
     }
 
-    fn parse_http_date_time(&self, val: &str) -> DateTime<Utc> {
-        DateTime::parse_from_rfc2822(val)
-            .unwrap_or_else(|e| panic!("Failed to parse Last-Modified header {}: {}", val, e))
-            .into()
+    fn parse_http_date_time(&self, val: &str) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc2822(val).ok().map(|dt| dt.into())
     }
 }
 
@@ -335,11 +846,60 @@ pub struct FetchCheckpoint {
     pub etag: Option<String>,
     #[serde(default, with = "datetime_rfc3339_opt")]
     pub source_event_time: Option<DateTime<Utc>>,
+    pub size: Option<u64>,
+    pub content_hash: Option<String>,
 }
 
 impl FetchCheckpoint {
     pub fn is_cacheable(&self) -> bool {
-        self.last_modified.is_some() || self.etag.is_some()
+        self.last_modified.is_some() || self.etag.is_some() || self.content_hash.is_some()
+    }
+}
+
+/// Expected digest of the fetched data in `<algorithm>:<hex digest>` form
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Checksum {
+    algorithm: ChecksumAlgorithm,
+    digest: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChecksumAlgorithm {
+    Sha256,
+    Sha3_256,
+}
+
+impl Checksum {
+    fn parse(s: &str) -> Result<Self, ChecksumFormatError> {
+        let err = || ChecksumFormatError::new(s);
+
+        let sep = s.find(':').ok_or_else(err)?;
+        let (algorithm, digest) = (&s[..sep], &s[sep + 1..]);
+
+        let algorithm = match &algorithm.to_lowercase()[..] {
+            "sha256" => ChecksumAlgorithm::Sha256,
+            "sha3-256" => ChecksumAlgorithm::Sha3_256,
+            _ => return Err(err()),
+        };
+
+        if digest.len() != 64 || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(err());
+        }
+
+        Ok(Self {
+            algorithm: algorithm,
+            digest: digest.to_lowercase(),
+        })
+    }
+}
+
+impl std::fmt::Display for Checksum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let algorithm = match self.algorithm {
+            ChecksumAlgorithm::Sha256 => "sha256",
+            ChecksumAlgorithm::Sha3_256 => "sha3-256",
+        };
+        write!(f, "{}:{}", algorithm, self.digest)
     }
 }
 
@@ -382,6 +942,63 @@ impl std::convert::From<curl::Error> for IngestError {
 
 ///////////////////////////////////////////////////////////////////////////////
 
+#[derive(Error, Debug)]
+#[error("Expected {expected} bytes but received {actual}")]
+struct ContentLengthError {
+    pub expected: u64,
+    pub actual: u64,
+}
+
+impl ContentLengthError {
+    fn new(expected: u64, actual: u64) -> Self {
+        Self {
+            expected: expected,
+            actual: actual,
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+
+struct HttpRequestOptions {
+    headers: Vec<String>,
+    basic_auth: Option<(String, String)>,
+}
+
+enum HttpFetchResult {
+    Fetched(FetchCheckpoint),
+    NotModified,
+}
+
+enum HttpAttemptError {
+    Retryable(IngestError),
+    Fatal(IngestError),
+}
+
+impl std::convert::From<curl::Error> for HttpAttemptError {
+    fn from(e: curl::Error) -> Self {
+        Self::Fatal(IngestError::internal(e))
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Error, Debug)]
+#[error("Invalid checksum {checksum}, expected sha256:<hex> or sha3-256:<hex>")]
+struct ChecksumFormatError {
+    pub checksum: String,
+}
+
+impl ChecksumFormatError {
+    fn new(checksum: &str) -> Self {
+        Self {
+            checksum: checksum.to_owned(),
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+
 #[derive(Error, Debug)]
 #[error("Bad URL {url}")]
 struct BadUrlError {
@@ -403,3 +1020,19 @@ impl std::convert::From<BadUrlError> for IngestError {
 }
 
 ///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Error, Debug)]
+#[error("Fetching from {source_path} is not supported")]
+struct UnsupportedSourceError {
+    pub source_path: String,
+}
+
+impl UnsupportedSourceError {
+    fn new(source_path: &str) -> Self {
+        Self {
+            source_path: source_path.to_owned(),
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
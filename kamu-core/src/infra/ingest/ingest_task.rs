@@ -1,11 +1,17 @@
 use super::*;
 use crate::domain::*;
 use crate::infra::serde::yaml::*;
+use crate::infra::utils::hashing::files_sha3;
 use crate::infra::*;
 
+use arrow::array::{Array, BooleanArray};
+use arrow::record_batch::RecordBatch;
 use chrono::{DateTime, Utc};
-use slog::{info, Logger};
+use slog::{info, warn, Logger};
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 pub struct IngestTask {
     dataset_id: DatasetIDBuf,
@@ -13,11 +19,15 @@ pub struct IngestTask {
     meta_chain: Box<dyn MetadataChain>,
     source: DatasetSourceRoot,
     vocab: DatasetVocabulary,
+    schema_policy: Option<SchemaPolicy>,
+    expectations: Option<Vec<Expectation>>,
+    prev_schema: Option<Vec<String>>,
     listener: Arc<Mutex<dyn IngestListener>>,
     checkpointing_executor: CheckpointingExecutor,
     fetch_service: FetchService,
     prep_service: PrepService,
     read_service: ReadService,
+    cancel: CancellationToken,
     logger: Logger,
 }
 
@@ -27,29 +37,44 @@ impl IngestTask {
         layout: DatasetLayout,
         meta_chain: Box<dyn MetadataChain>,
         vocab: DatasetVocabulary,
+        config: DatasetConfig,
+        prev_schema: Option<Vec<String>>,
         listener: Arc<Mutex<dyn IngestListener>>,
-        engine_factory: Arc<Mutex<EngineFactory>>,
+        engine_factory: Arc<EngineFactory>,
+        fetch_options: FetchOptions,
+        cancel: CancellationToken,
         logger: Logger,
-    ) -> Self {
+    ) -> Result<Self, IngestError> {
         // TODO: this is expensive
         let source = match meta_chain.iter_blocks().filter_map(|b| b.source).next() {
             Some(DatasetSource::Root(src)) => src,
-            _ => panic!("Failed to find source definition"),
+            _ => return Err(IngestError::not_a_root_dataset(dataset_id)),
         };
 
-        Self {
+        let mut prep_service = PrepService::new().with_cancellation(cancel.clone());
+        if let Some(ref decompress) = config.decompress {
+            prep_service = prep_service.with_archive_format(decompress.format);
+        }
+
+        Ok(Self {
             dataset_id: dataset_id.to_owned(),
             layout: layout,
             meta_chain: meta_chain,
             source: source,
             vocab: vocab,
+            schema_policy: config.schema_policy,
+            expectations: config.expectations,
+            prev_schema: prev_schema,
             listener: listener,
             checkpointing_executor: CheckpointingExecutor::new(),
-            fetch_service: FetchService::new(),
-            prep_service: PrepService::new(),
-            read_service: ReadService::new(engine_factory),
+            fetch_service: FetchService::with_options(fetch_options)
+                .with_config(config.fetch.unwrap_or_default())
+                .with_cancellation(cancel.clone()),
+            prep_service: prep_service,
+            read_service: ReadService::new(engine_factory, cancel.clone()),
+            cancel: cancel,
             logger: logger,
-        }
+        })
     }
 
     pub fn ingest(&mut self) -> Result<IngestResult, IngestError> {
@@ -71,8 +96,73 @@ impl IngestTask {
         }
     }
 
+    /// Describes what ingestion would do based on the cached state of the source
+    pub fn plan(&self) -> Result<IngestPlan, IngestError> {
+        let checkpoint: Option<FetchCheckpoint> = self
+            .checkpointing_executor
+            .read(&self.layout.cache_dir.join("fetch.yaml"))
+            .map_err(|e| IngestError::internal(e))?;
+
+        // Uncacheable sources are only fetched once, see `maybe_fetch`
+        let cache = match checkpoint {
+            None => FetchCacheState::Empty,
+            Some(cp) if !cp.is_cacheable() => FetchCacheState::UpToDate {
+                last_fetched: cp.last_fetched,
+            },
+            Some(cp) => self.fetch_service.check_cache(&self.source.fetch, &cp)?,
+        };
+
+        let source = match self.source.fetch {
+            FetchStep::Url(ref furl) => furl.url.clone(),
+            FetchStep::FilesGlob(ref fglob) => fglob.path.clone(),
+        };
+
+        Ok(IngestPlan {
+            source: source,
+            cache: cache,
+            engine: self.source.preprocess.as_ref().map(|p| p.engine.clone()),
+        })
+    }
+
+    /// Expects the task to be created with a temporary dataset layout as
+    /// the data written by the read step is discarded afterwards
+    pub fn preview(&mut self, num_rows: usize) -> Result<IngestPreview, IngestError> {
+        self.listener.lock().unwrap().begin();
+
+        let res = self.preview_inner(num_rows);
+        if let Err(ref err) = res {
+            self.listener.lock().unwrap().error(err);
+        }
+        res
+    }
+
+    pub fn ingest_from(
+        &mut self,
+        data: &mut dyn Read,
+        event_time: Option<DateTime<Utc>>,
+    ) -> Result<IngestResult, IngestError> {
+        self.listener.lock().unwrap().begin();
+
+        match self.ingest_from_inner(data, event_time) {
+            Ok(res) => {
+                self.listener.lock().unwrap().success(&res);
+                Ok(res)
+            }
+            Err(err) => {
+                self.listener.lock().unwrap().error(&err);
+                Err(err)
+            }
+        }
+    }
+
     // Note: Can be called from multiple threads
+    //
+    // Cancellation is only checked before the stages that don't leave partial
+    // results behind. Once data was read it has to be committed, as the read
+    // checkpoint would otherwise consider it processed.
     pub fn ingest_inner(&mut self) -> Result<(IngestResult, bool), IngestError> {
+        self.cancel.check()?;
+
         self.listener
             .lock()
             .unwrap()
@@ -80,23 +170,44 @@ impl IngestTask {
 
         let prev_hash = self.meta_chain.read_ref(&BlockRef::Head).unwrap();
 
+        let mut stages = Vec::new();
+
+        let started = Instant::now();
         let fetch_result = self.maybe_fetch()?;
+        stages.push(StageTime::since("fetch", started));
+
         let cacheable = fetch_result.checkpoint.is_cacheable();
         let source_event_time = fetch_result.checkpoint.source_event_time.clone();
+        let fetched_from_cache = fetch_result.was_up_to_date;
+        let bytes_fetched = if fetched_from_cache {
+            0
+        } else {
+            std::fs::metadata(self.layout.cache_dir.join("fetched.bin"))
+                .map(|m| m.len())
+                .unwrap_or(0)
+        };
+
+        self.cancel.check()?;
 
         self.listener
             .lock()
             .unwrap()
             .on_stage_progress(IngestStage::Prepare, 0, 1);
 
+        let started = Instant::now();
         let prepare_result = self.maybe_prepare(fetch_result)?;
+        stages.push(StageTime::since("prepare", started));
+
+        self.cancel.check()?;
 
         self.listener
             .lock()
             .unwrap()
             .on_stage_progress(IngestStage::Read, 0, 1);
 
+        let started = Instant::now();
         let read_result = self.maybe_read(prepare_result, source_event_time)?;
+        stages.push(StageTime::since("read", started));
 
         self.listener
             .lock()
@@ -113,16 +224,234 @@ impl IngestTask {
             .unwrap()
             .on_stage_progress(IngestStage::Commit, 0, 1);
 
+        let started = Instant::now();
         let commit = self.maybe_commit(read_result, prev_hash)?;
+        stages.push(StageTime::since("commit", started));
+
+        let (block_hash, stats) = match commit {
+            None => (None, UpdateStats::default()),
+            Some((hash, stats)) => (Some(hash), stats),
+        };
 
-        let res = match commit {
-            None => IngestResult::UpToDate,
-            Some(hash) => IngestResult::Updated { block_hash: hash },
+        let stats = UpdateStats {
+            bytes_fetched: Some(bytes_fetched),
+            fetched_from_cache: Some(fetched_from_cache),
+            stages: stages,
+            ..stats
+        };
+
+        let res = match block_hash {
+            None => IngestResult::UpToDate { stats: stats },
+            Some(hash) => IngestResult::Updated {
+                block_hash: hash,
+                stats: stats,
+            },
         };
 
         Ok((res, cacheable))
     }
 
+    // Runs all steps unconditionally and without checkpointing
+    fn preview_inner(&mut self, num_rows: usize) -> Result<IngestPreview, IngestError> {
+        let layout = &self.layout;
+        for dir in &[&layout.cache_dir, &layout.data_dir, &layout.checkpoints_dir] {
+            std::fs::create_dir_all(dir).map_err(|e| IngestError::internal(e))?;
+        }
+
+        let fetched_path = self.layout.cache_dir.join("fetched.bin");
+        let prepared_path = self.layout.cache_dir.join("prepared.bin");
+
+        let fetch_result = self.fetch_service.fetch(
+            &self.source.fetch,
+            None,
+            &fetched_path,
+            Some(&mut FetchProgressListenerBridge {
+                listener: self.listener.clone(),
+            }),
+        )?;
+
+        self.listener
+            .lock()
+            .unwrap()
+            .on_stage_progress(IngestStage::Prepare, 0, 1);
+
+        let null_steps = Vec::new();
+        let prepare_result = self.prep_service.prepare(
+            self.source.prepare.as_ref().unwrap_or(&null_steps),
+            &self.source.read,
+            fetch_result.checkpoint.last_fetched,
+            None,
+            &fetched_path,
+            &prepared_path,
+        )?;
+
+        self.listener
+            .lock()
+            .unwrap()
+            .on_stage_progress(IngestStage::Read, 0, 1);
+
+        self.read_service.read(
+            &self.dataset_id,
+            &self.layout,
+            &self.source,
+            fetch_result.checkpoint.source_event_time,
+            &self.vocab,
+            prepare_result.checkpoint.last_prepared,
+            None,
+            &prepared_path,
+        )?;
+
+        let batches = read_parquet_dir(&self.layout.data_dir)
+            .map_err(|e| IngestError::failed_stage(IngestStage::Read, e))?;
+
+        Ok(Self::to_preview(&batches, num_rows))
+    }
+
+    fn to_preview(batches: &[RecordBatch], num_rows: usize) -> IngestPreview {
+        let columns = match batches.first() {
+            None => Vec::new(),
+            Some(batch) => batch
+                .schema()
+                .fields()
+                .iter()
+                .map(|f| (f.name().clone(), format!("{:?}", f.data_type())))
+                .collect(),
+        };
+
+        let rows = batches
+            .iter()
+            .flat_map(|b| (0..b.num_rows()).map(move |r| (b, r)))
+            .take(num_rows)
+            .map(|(batch, r)| {
+                batch
+                    .columns()
+                    .iter()
+                    .map(|c| match Cell::from_array(c.as_ref(), r) {
+                        Some(Cell::Null) => None,
+                        Some(cell) => Some(cell.to_string()),
+                        None => Some(format!("<{:?}>", c.data_type())),
+                    })
+                    .collect()
+            })
+            .collect();
+
+        IngestPreview {
+            columns: columns,
+            rows: rows,
+            num_records: batches.iter().map(|b| b.num_rows() as u64).sum(),
+        }
+    }
+
+    // Supplied data bypasses the fetch step and does not touch any checkpoints
+    // so that the next pull from the source is not affected by it
+    fn ingest_from_inner(
+        &mut self,
+        data: &mut dyn Read,
+        event_time: Option<DateTime<Utc>>,
+    ) -> Result<IngestResult, IngestError> {
+        let prev_hash = self.meta_chain.read_ref(&BlockRef::Head).unwrap();
+
+        let received_path = self.layout.cache_dir.join("received.bin");
+        let prepared_path = self.layout.cache_dir.join("received.prepared.bin");
+
+        let res = self.ingest_received(data, event_time, &received_path, &prepared_path, prev_hash);
+
+        std::fs::remove_file(&received_path).ok();
+        std::fs::remove_file(&prepared_path).ok();
+
+        match res? {
+            (None, stats) => Ok(IngestResult::UpToDate { stats: stats }),
+            (Some(hash), stats) => Ok(IngestResult::Updated {
+                block_hash: hash,
+                stats: stats,
+            }),
+        }
+    }
+
+    fn ingest_received(
+        &mut self,
+        data: &mut dyn Read,
+        event_time: Option<DateTime<Utc>>,
+        received_path: &Path,
+        prepared_path: &Path,
+        prev_hash: String,
+    ) -> Result<(Option<String>, UpdateStats), IngestError> {
+        let mut stages = Vec::new();
+
+        let started = Instant::now();
+        std::fs::create_dir_all(&self.layout.cache_dir).map_err(|e| IngestError::internal(e))?;
+        let mut received_file =
+            std::fs::File::create(received_path).map_err(|e| IngestError::internal(e))?;
+        let bytes_received =
+            std::io::copy(data, &mut received_file).map_err(|e| IngestError::internal(e))?;
+        let received_at = Utc::now();
+        stages.push(StageTime::since("receive", started));
+
+        self.listener
+            .lock()
+            .unwrap()
+            .on_stage_progress(IngestStage::Prepare, 0, 1);
+
+        let started = Instant::now();
+        let null_steps = Vec::new();
+        let prepare_result = self.prep_service.prepare(
+            self.source.prepare.as_ref().unwrap_or(&null_steps),
+            &self.source.read,
+            received_at,
+            None,
+            received_path,
+            prepared_path,
+        )?;
+        stages.push(StageTime::since("prepare", started));
+
+        self.listener
+            .lock()
+            .unwrap()
+            .on_stage_progress(IngestStage::Read, 0, 1);
+
+        let started = Instant::now();
+        let files_before =
+            list_parquet_files(&self.layout.data_dir).map_err(|e| IngestError::internal(e))?;
+
+        let read_result = self.read_service.read(
+            &self.dataset_id,
+            &self.layout,
+            &self.source,
+            event_time,
+            &self.vocab,
+            prepare_result.checkpoint.last_prepared,
+            None,
+            prepared_path,
+        )?;
+
+        let read_result = self.validate_new_data(&files_before, read_result)?;
+        stages.push(StageTime::since("read", started));
+
+        self.listener
+            .lock()
+            .unwrap()
+            .on_stage_progress(IngestStage::Commit, 0, 1);
+
+        let started = Instant::now();
+        let commit = self.maybe_commit(read_result, prev_hash)?;
+        stages.push(StageTime::since("commit", started));
+
+        let (block_hash, stats) = match commit {
+            None => (None, UpdateStats::default()),
+            Some((hash, stats)) => (Some(hash), stats),
+        };
+
+        Ok((
+            block_hash,
+            UpdateStats {
+                bytes_fetched: Some(bytes_received),
+                fetched_from_cache: Some(false),
+                stages: stages,
+                ..stats
+            },
+        ))
+    }
+
     fn maybe_fetch(&mut self) -> Result<ExecutionResult<FetchCheckpoint>, IngestError> {
         let checkpoint_path = self.layout.cache_dir.join("fetch.yaml");
 
@@ -175,6 +504,7 @@ impl IngestTask {
                     let prep_steps = self.source.prepare.as_ref().unwrap_or(&null_steps);
                     self.prep_service.prepare(
                         prep_steps,
+                        &self.source.read,
                         fetch_result.checkpoint.last_fetched,
                         old_checkpoint,
                         &self.layout.cache_dir.join("fetched.bin"),
@@ -205,37 +535,277 @@ impl IngestTask {
                         }
                     }
 
-                    self.read_service.read(
-                        &self.dataset_id,
-                        &self.layout,
-                        &self.source,
-                        source_event_time,
-                        &self.vocab,
-                        prep_result.checkpoint.last_prepared,
-                        old_checkpoint,
-                        &self.layout.cache_dir.join("prepared.bin"),
-                    )
+                    let files_before = list_parquet_files(&self.layout.data_dir)
+                        .map_err(|e| IngestError::internal(e))?;
+
+                    let read_result = self
+                        .read_service
+                        .read(
+                            &self.dataset_id,
+                            &self.layout,
+                            &self.source,
+                            source_event_time,
+                            &self.vocab,
+                            prep_result.checkpoint.last_prepared,
+                            old_checkpoint,
+                            &self.layout.cache_dir.join("prepared.bin"),
+                        )
+                        .map_err(|e| {
+                            // Engine could've written some output before it failed or got cancelled
+                            self.discard_new_files(&files_before);
+                            e
+                        })?;
+
+                    // Failing here leaves the read checkpoint untouched
+                    // so the problem is detected again on the next pull
+                    self.validate_new_data(&files_before, read_result)
                 },
             )
             .map_err(|e| IngestError::internal(e))?
     }
 
+    // Validates the data written by the read step before it gets committed:
+    // records its schema, checks it for drift and evaluates data quality
+    // expectations. New data is discarded if any of the checks fail.
+    fn validate_new_data(
+        &self,
+        files_before: &[PathBuf],
+        read_result: ExecutionResult<ReadCheckpoint>,
+    ) -> Result<ExecutionResult<ReadCheckpoint>, IngestError> {
+        let new_files: Vec<PathBuf> = list_parquet_files(&self.layout.data_dir)
+            .map_err(|e| IngestError::internal(e))?
+            .into_iter()
+            .filter(|p| !files_before.contains(p))
+            .collect();
+
+        if new_files.is_empty() {
+            return Ok(read_result);
+        }
+
+        let res = self
+            .track_schema(&new_files, read_result)
+            .and_then(|r| self.check_expectations(&new_files, r));
+
+        if res.is_err() {
+            self.discard_new_files(files_before);
+        }
+        res
+    }
+
+    fn discard_new_files(&self, files_before: &[PathBuf]) {
+        for path in list_parquet_files(&self.layout.data_dir)
+            .unwrap_or_default()
+            .iter()
+            .filter(|p| !files_before.contains(p))
+        {
+            std::fs::remove_file(path).ok();
+        }
+    }
+
+    fn track_schema(
+        &self,
+        new_files: &[PathBuf],
+        mut read_result: ExecutionResult<ReadCheckpoint>,
+    ) -> Result<ExecutionResult<ReadCheckpoint>, IngestError> {
+        let output_schema = read_parquet_schema(&new_files[0])
+            .map_err(|e| IngestError::failed_stage(IngestStage::Read, e))?;
+        let output_schema = format_ddl_schema(&output_schema);
+
+        self.check_schema_drift(&output_schema)?;
+
+        read_result.checkpoint.output_schema = Some(output_schema);
+        Ok(read_result)
+    }
+
+    fn check_schema_drift(&self, output_schema: &[String]) -> Result<(), IngestError> {
+        let prev_schema = match self.prev_schema {
+            Some(ref schema) => schema,
+            None => return Ok(()),
+        };
+
+        let changes =
+            diff_ddl_schemas(prev_schema, output_schema).map_err(|e| IngestError::internal(e))?;
+
+        let mut warnings = Vec::new();
+        let mut failures = Vec::new();
+        for change in changes {
+            match schema_change_action(self.schema_policy.as_ref(), &change) {
+                SchemaChangeAction::Ignore => (),
+                SchemaChangeAction::Warn => warnings.push(change),
+                SchemaChangeAction::Fail => failures.push(change),
+            }
+        }
+
+        if !failures.is_empty() {
+            return Err(IngestError::SchemaDrift { changes: failures });
+        }
+
+        for change in warnings.iter() {
+            warn!(self.logger, "Schema of the new data has changed"; "change" => %change);
+        }
+        if !warnings.is_empty() {
+            self.listener.lock().unwrap().on_schema_drift(&warnings);
+        }
+
+        Ok(())
+    }
+
+    fn check_expectations(
+        &self,
+        new_files: &[PathBuf],
+        mut read_result: ExecutionResult<ReadCheckpoint>,
+    ) -> Result<ExecutionResult<ReadCheckpoint>, IngestError> {
+        let expectations = match self.expectations {
+            Some(ref e) if !e.is_empty() => e,
+            _ => return Ok(read_result),
+        };
+
+        let file_batches = new_files
+            .iter()
+            .map(|p| read_parquet_file(p.as_path()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| IngestError::failed_stage(IngestStage::Read, e))?;
+
+        let batches: Vec<RecordBatch> = file_batches.iter().flatten().cloned().collect();
+
+        let report = check_expectations(expectations, &batches)
+            .map_err(|e| IngestError::failed_stage(IngestStage::Read, e))?;
+
+        if report.is_failed() {
+            return Err(IngestError::ExpectationsFailed {
+                results: report.results,
+            });
+        }
+
+        if let Some(ref keep) = report.keep {
+            let num_quarantined = self.quarantine(new_files, &file_batches, keep)?;
+
+            info!(self.logger, "Quarantined records"; "num_records" => num_quarantined);
+
+            let last_block = &mut read_result.checkpoint.last_block;
+            if let Some(mut slice) = last_block.output_slice.take() {
+                slice.num_records -= num_quarantined as i64;
+                if slice.num_records > 0 {
+                    // Files left with no records were removed by the quarantine
+                    let kept_files: Vec<PathBuf> =
+                        new_files.iter().filter(|p| p.exists()).cloned().collect();
+                    slice.hash = files_sha3(&kept_files).map_err(|e| IngestError::internal(e))?;
+                    last_block.output_slice = Some(slice);
+                }
+            }
+        }
+
+        self.listener
+            .lock()
+            .unwrap()
+            .on_expectations_checked(&report.results);
+
+        Ok(read_result)
+    }
+
+    // Moves records rejected by expectations from the new data files into the
+    // quarantine directory and returns the number of moved records
+    fn quarantine(
+        &self,
+        new_files: &[PathBuf],
+        file_batches: &[Vec<RecordBatch>],
+        keep: &[BooleanArray],
+    ) -> Result<usize, IngestError> {
+        std::fs::create_dir_all(&self.layout.quarantine_dir)
+            .map_err(|e| IngestError::internal(e))?;
+
+        let mut masks = keep.iter();
+        let mut num_quarantined = 0;
+
+        for (path, batches) in new_files.iter().zip(file_batches) {
+            let mut kept = Vec::new();
+            let mut rejected = Vec::new();
+            for batch in batches {
+                let mask = masks.next().ok_or_else(|| {
+                    IngestError::internal(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "Expectations report has fewer record masks than there are batches",
+                    ))
+                })?;
+                let inverse: BooleanArray = (0..mask.len())
+                    .map(|i| !mask.value(i))
+                    .collect::<Vec<_>>()
+                    .into();
+                kept.push(filter_record_batch(batch, mask).map_err(|e| IngestError::internal(e))?);
+                rejected.push(
+                    filter_record_batch(batch, &inverse).map_err(|e| IngestError::internal(e))?,
+                );
+            }
+
+            let num_rejected: usize = rejected.iter().map(|b| b.num_rows()).sum();
+            if num_rejected == 0 {
+                continue;
+            }
+            num_quarantined += num_rejected;
+
+            let schema = batches[0].schema();
+            let quarantine_path = self.layout.quarantine_dir.join(path.file_name().unwrap());
+            write_parquet_file(&quarantine_path, schema.clone(), &rejected)
+                .map_err(|e| IngestError::failed_stage(IngestStage::Read, e))?;
+
+            if kept.iter().all(|b| b.num_rows() == 0) {
+                std::fs::remove_file(path).map_err(|e| IngestError::internal(e))?;
+            } else {
+                write_parquet_file(path, schema, &kept)
+                    .map_err(|e| IngestError::failed_stage(IngestStage::Read, e))?;
+            }
+        }
+
+        Ok(num_quarantined)
+    }
+
     fn maybe_commit(
         &mut self,
         read_result: ExecutionResult<ReadCheckpoint>,
         prev_hash: String,
-    ) -> Result<Option<String>, IngestError> {
+    ) -> Result<Option<(String, UpdateStats)>, IngestError> {
         // TODO: Atomicity
         if !read_result.was_up_to_date {
+            // Watermark never moves backwards, e.g. after being advanced manually
+            let prev_watermark = self
+                .meta_chain
+                .iter_blocks()
+                .filter_map(|b| b.output_watermark)
+                .next();
+            let output_watermark = read_result
+                .checkpoint
+                .last_block
+                .output_watermark
+                .max(prev_watermark);
+
             let new_block = MetadataBlock {
                 prev_block_hash: prev_hash,
+                output_watermark: output_watermark,
                 ..read_result.checkpoint.last_block
             };
+
+            let stats = UpdateStats {
+                records_added: new_block
+                    .output_slice
+                    .as_ref()
+                    .map(|s| s.num_records as u64)
+                    .unwrap_or(0),
+                output_intervals: new_block
+                    .output_slice
+                    .iter()
+                    .map(|s| s.interval.clone())
+                    .collect(),
+                prev_watermark: prev_watermark,
+                new_watermark: output_watermark,
+                output_schema: read_result.checkpoint.output_schema,
+                ..UpdateStats::default()
+            };
+
             let hash = self.meta_chain.append(new_block);
 
             info!(self.logger, "Committed new block"; "hash" => &hash);
 
-            Ok(Some(hash))
+            Ok(Some((hash, stats)))
         } else {
             Ok(None)
         }
@@ -0,0 +1,422 @@
+use super::*;
+use crate::infra::serde::yaml::*;
+
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::error::ArrowError;
+use arrow::record_batch::RecordBatch;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use thiserror::Error;
+
+type Row = Vec<Cell>;
+
+const MERGE_BATCH_SIZE: usize = 10_000;
+
+/// Combines newly read data with the data previously ingested into the dataset.
+/// The result contains only the records that should be appended to the dataset.
+pub trait Merger {
+    /// Columns of the previously ingested data that the merge depends on.
+    /// Previous data is not read at all if this is empty.
+    fn prev_columns(&self, new_schema: &Schema) -> Vec<String>;
+
+    /// Previous data may lack some of the columns, e.g. when they were added
+    /// to the source later, in which case they are treated as nulls.
+    /// Resulting records are served in batches of limited size.
+    fn merge(
+        &self,
+        prev: &[RecordBatch],
+        new_schema: SchemaRef,
+        new: &[RecordBatch],
+    ) -> Result<Box<dyn NativeReader>, MergeError>;
+}
+
+pub fn merger_for(strategy: &MergeStrategy, vocab: &DatasetVocabulary) -> Box<dyn Merger> {
+    match strategy {
+        MergeStrategy::Append => Box::new(AppendMerger),
+        MergeStrategy::Ledger(conf) => Box::new(LedgerMerger::new(conf)),
+        MergeStrategy::Snapshot(conf) => Box::new(SnapshotMerger::new(conf, vocab)),
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Append
+///////////////////////////////////////////////////////////////////////////////
+
+/// Appends all new records as is
+pub struct AppendMerger;
+
+impl Merger for AppendMerger {
+    fn prev_columns(&self, _new_schema: &Schema) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn merge(
+        &self,
+        _prev: &[RecordBatch],
+        new_schema: SchemaRef,
+        new: &[RecordBatch],
+    ) -> Result<Box<dyn NativeReader>, MergeError> {
+        let columns = column_names(&new_schema);
+        let rows = to_rows(new, &columns)?;
+        Ok(Box::new(RowsReader::new(new_schema, rows)?))
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Ledger
+///////////////////////////////////////////////////////////////////////////////
+
+/// Appends only the records whose primary key was not seen before
+pub struct LedgerMerger {
+    primary_key: Vec<String>,
+}
+
+impl LedgerMerger {
+    pub fn new(conf: &MergeStrategyLedger) -> Self {
+        Self {
+            primary_key: conf.primary_key.clone(),
+        }
+    }
+}
+
+impl Merger for LedgerMerger {
+    fn prev_columns(&self, _new_schema: &Schema) -> Vec<String> {
+        self.primary_key.clone()
+    }
+
+    fn merge(
+        &self,
+        prev: &[RecordBatch],
+        new_schema: SchemaRef,
+        new: &[RecordBatch],
+    ) -> Result<Box<dyn NativeReader>, MergeError> {
+        let mut seen: HashSet<Row> = HashSet::new();
+        for_each_prev_row(prev, &self.primary_key, |row| {
+            seen.insert(row);
+        })?;
+
+        let columns = column_names(&new_schema);
+        let pk_index = column_indices(&new_schema, &self.primary_key)?;
+
+        let rows: Vec<Row> = to_rows(new, &columns)?
+            .into_iter()
+            .filter(|row| seen.insert(project(row, &pk_index)))
+            .collect();
+
+        Ok(Box::new(RowsReader::new(new_schema, rows)?))
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Snapshot
+///////////////////////////////////////////////////////////////////////////////
+
+/// Treats new data as a complete snapshot of the state and appends the
+/// differences with the previous state as change data capture records
+pub struct SnapshotMerger {
+    primary_key: Vec<String>,
+    compare_columns: Option<Vec<String>>,
+    observation_column: String,
+    obsv_added: String,
+    obsv_changed: String,
+    obsv_removed: String,
+    // System columns are excluded from comparison
+    system_columns: Vec<String>,
+}
+
+impl SnapshotMerger {
+    pub fn new(conf: &MergeStrategySnapshot, vocab: &DatasetVocabulary) -> Self {
+        Self {
+            primary_key: conf.primary_key.clone(),
+            compare_columns: conf.compare_columns.clone(),
+            observation_column: conf
+                .observation_column
+                .clone()
+                .unwrap_or("observed".to_owned()),
+            obsv_added: conf.obsv_added.clone().unwrap_or("I".to_owned()),
+            obsv_changed: conf.obsv_changed.clone().unwrap_or("U".to_owned()),
+            obsv_removed: conf.obsv_removed.clone().unwrap_or("D".to_owned()),
+            system_columns: vec![
+                vocab
+                    .system_time_column
+                    .clone()
+                    .unwrap_or("system_time".to_owned()),
+                vocab
+                    .event_time_column
+                    .clone()
+                    .unwrap_or("event_time".to_owned()),
+            ],
+        }
+    }
+
+    // Reconstructs the latest state from the history of observations,
+    // preserving the order in which keys first appeared
+    fn prev_state(
+        &self,
+        prev: &[RecordBatch],
+        data_columns: &[String],
+        pk_index: &[usize],
+    ) -> Result<(Vec<Row>, HashMap<Row, usize>), MergeError> {
+        let mut columns = data_columns.to_vec();
+        columns.push(self.observation_column.clone());
+        let obsv_removed = Cell::Utf8(self.obsv_removed.clone());
+
+        let mut state: Vec<Option<Row>> = Vec::new();
+        let mut index: HashMap<Row, usize> = HashMap::new();
+
+        for_each_prev_row(prev, &columns, |mut row| {
+            let observation = row.pop().unwrap();
+            let key = project(&row, pk_index);
+            let row = if observation == obsv_removed {
+                None
+            } else {
+                Some(row)
+            };
+
+            match index.get(&key) {
+                Some(i) => state[*i] = row,
+                None => {
+                    index.insert(key, state.len());
+                    state.push(row);
+                }
+            }
+        })?;
+
+        let state: Vec<Row> = state.into_iter().filter_map(|r| r).collect();
+        let index = state
+            .iter()
+            .enumerate()
+            .map(|(i, row)| (project(row, pk_index), i))
+            .collect();
+
+        Ok((state, index))
+    }
+}
+
+impl Merger for SnapshotMerger {
+    fn prev_columns(&self, new_schema: &Schema) -> Vec<String> {
+        let mut columns = column_names(new_schema);
+        columns.push(self.observation_column.clone());
+        columns
+    }
+
+    fn merge(
+        &self,
+        prev: &[RecordBatch],
+        new_schema: SchemaRef,
+        new: &[RecordBatch],
+    ) -> Result<Box<dyn NativeReader>, MergeError> {
+        let data_columns = column_names(&new_schema);
+        let pk_index = column_indices(&new_schema, &self.primary_key)?;
+
+        let compare_columns = match self.compare_columns {
+            Some(ref columns) => columns.clone(),
+            None => data_columns
+                .iter()
+                .filter(|c| !self.primary_key.contains(c) && !self.system_columns.contains(c))
+                .cloned()
+                .collect(),
+        };
+        let compare_index = column_indices(&new_schema, &compare_columns)?;
+
+        let (state, state_index) = self.prev_state(prev, &data_columns, &pk_index)?;
+
+        let mut fields = vec![Field::new(&self.observation_column, DataType::Utf8, false)];
+        fields.extend(new_schema.fields().iter().cloned());
+        let out_schema = Arc::new(Schema::new(fields));
+
+        let observed = |obsv: &String, row: &Row| {
+            let mut out = Vec::with_capacity(row.len() + 1);
+            out.push(Cell::Utf8(obsv.clone()));
+            out.extend(row.iter().cloned());
+            out
+        };
+
+        let mut out_rows = Vec::new();
+        let mut new_keys = HashSet::new();
+
+        for row in to_rows(new, &data_columns)? {
+            let key = project(&row, &pk_index);
+
+            match state_index.get(&key) {
+                None => out_rows.push(observed(&self.obsv_added, &row)),
+                Some(i) => {
+                    if project(&row, &compare_index) != project(&state[*i], &compare_index) {
+                        out_rows.push(observed(&self.obsv_changed, &row));
+                    }
+                }
+            }
+
+            if !new_keys.insert(key) {
+                return Err(MergeError::DuplicateKey {
+                    key: format!("{:?}", project(&row, &pk_index)),
+                });
+            }
+        }
+
+        for row in state.iter() {
+            if !new_keys.contains(&project(row, &pk_index)) {
+                out_rows.push(observed(&self.obsv_removed, row));
+            }
+        }
+
+        Ok(Box::new(RowsReader::new(out_schema, out_rows)?))
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Helpers
+///////////////////////////////////////////////////////////////////////////////
+
+fn column_names(schema: &Schema) -> Vec<String> {
+    schema.fields().iter().map(|f| f.name().clone()).collect()
+}
+
+fn column_indices(schema: &Schema, columns: &[String]) -> Result<Vec<usize>, MergeError> {
+    columns
+        .iter()
+        .map(|c| {
+            schema
+                .index_of(c)
+                .map_err(|_| MergeError::MissingColumn { column: c.clone() })
+        })
+        .collect()
+}
+
+fn project(row: &Row, index: &[usize]) -> Row {
+    index.iter().map(|i| row[*i].clone()).collect()
+}
+
+fn to_rows(batches: &[RecordBatch], columns: &[String]) -> Result<Vec<Row>, MergeError> {
+    let mut rows = Vec::new();
+
+    for batch in batches {
+        let index = column_indices(&batch.schema(), columns)?;
+        for r in 0..batch.num_rows() {
+            rows.push(to_row(batch, index.iter().map(|i| Some(*i)), r)?);
+        }
+    }
+
+    Ok(rows)
+}
+
+// Previous data is visited row by row to avoid holding all of it in memory
+// twice. Columns missing in a batch are filled with nulls.
+fn for_each_prev_row(
+    batches: &[RecordBatch],
+    columns: &[String],
+    mut f: impl FnMut(Row),
+) -> Result<(), MergeError> {
+    for batch in batches {
+        let schema = batch.schema();
+        let index: Vec<Option<usize>> = columns.iter().map(|c| schema.index_of(c).ok()).collect();
+        for r in 0..batch.num_rows() {
+            f(to_row(batch, index.iter().cloned(), r)?);
+        }
+    }
+
+    Ok(())
+}
+
+fn to_row(
+    batch: &RecordBatch,
+    index: impl Iterator<Item = Option<usize>>,
+    r: usize,
+) -> Result<Row, MergeError> {
+    index
+        .map(|i| match i {
+            None => Ok(Cell::Null),
+            Some(i) => {
+                let array = batch.column(i);
+                Cell::from_array(array.as_ref(), r).ok_or_else(|| MergeError::UnsupportedType {
+                    column: batch.schema().field(i).name().clone(),
+                    data_type: array.data_type().clone(),
+                })
+            }
+        })
+        .collect()
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// RowsReader
+///////////////////////////////////////////////////////////////////////////////
+
+/// Serves merged records in batches so that only one batch at a time is
+/// built in the columnar form
+struct RowsReader {
+    schema: SchemaRef,
+    rows: std::vec::IntoIter<Row>,
+    num_read: u64,
+}
+
+impl RowsReader {
+    fn new(schema: SchemaRef, rows: Vec<Row>) -> Result<Self, MergeError> {
+        for field in schema.fields() {
+            if ColumnBuilder::new(field.data_type(), 0).is_none() {
+                return Err(MergeError::UnsupportedType {
+                    column: field.name().clone(),
+                    data_type: field.data_type().clone(),
+                });
+            }
+        }
+
+        Ok(Self {
+            schema: schema,
+            rows: rows.into_iter(),
+            num_read: 0,
+        })
+    }
+}
+
+impl NativeReader for RowsReader {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn next_batch(&mut self) -> Result<Option<RecordBatch>, ReadError> {
+        let rows: Vec<Row> = self.rows.by_ref().take(MERGE_BATCH_SIZE).collect();
+        if rows.is_empty() {
+            return Ok(None);
+        }
+
+        // Types are checked on creation
+        let mut builders: Vec<ColumnBuilder> = self
+            .schema
+            .fields()
+            .iter()
+            .map(|f| ColumnBuilder::new(f.data_type(), rows.len()).unwrap())
+            .collect();
+
+        for row in rows.iter() {
+            self.num_read += 1;
+            for ((builder, cell), field) in builders.iter_mut().zip(row).zip(self.schema.fields()) {
+                builder.append_cell(cell).map_err(|e| ReadError::BadValue {
+                    line: self.num_read,
+                    column: field.name().clone(),
+                    source: e,
+                })?;
+            }
+        }
+
+        let columns = builders.iter_mut().map(|b| b.finish()).collect();
+        Ok(Some(RecordBatch::try_new(self.schema.clone(), columns)?))
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Errors
+///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Error, Debug)]
+pub enum MergeError {
+    #[error("Column {column} not found")]
+    MissingColumn { column: String },
+    #[error("Column {column} has unsupported type {data_type:?}")]
+    UnsupportedType { column: String, data_type: DataType },
+    #[error("Snapshot contains duplicate records for primary key {key}")]
+    DuplicateKey { key: String },
+    #[error("{0}")]
+    ValueError(#[from] ValueParseError),
+    #[error("Arrow error: {0}")]
+    ArrowError(#[from] ArrowError),
+}
@@ -12,3 +12,36 @@ pub use prep_service::*;
 
 mod read_service;
 pub use read_service::*;
+
+mod native_reader;
+pub use native_reader::*;
+
+mod reader_csv;
+pub use reader_csv::*;
+
+mod reader_json;
+pub use reader_json::*;
+
+mod reader_geojson;
+pub use reader_geojson::*;
+
+mod schema_ddl;
+pub use schema_ddl::*;
+
+mod column_builder;
+pub use column_builder::*;
+
+mod data_writer;
+pub use data_writer::*;
+
+mod merge_strategy;
+pub use merge_strategy::*;
+
+mod secret_resolver;
+pub use secret_resolver::*;
+
+mod schema_tracking;
+pub use schema_tracking::*;
+
+mod expectations;
+pub use expectations::*;
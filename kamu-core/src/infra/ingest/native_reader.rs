@@ -0,0 +1,160 @@
+use super::*;
+use crate::infra::serde::yaml::*;
+
+use arrow::datatypes::SchemaRef;
+use arrow::error::ArrowError;
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::{ArrowReader, ParquetFileArrowReader};
+use parquet::errors::ParquetError;
+use parquet::file::reader::SerializedFileReader;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+use thiserror::Error;
+
+const PARQUET_BATCH_SIZE: usize = 10_000;
+
+/// Reads source data in-process without starting an engine
+pub trait NativeReader {
+    fn schema(&self) -> SchemaRef;
+
+    /// Returns the next batch of records or `None` when the input is exhausted
+    fn next_batch(&mut self) -> Result<Option<RecordBatch>, ReadError>;
+}
+
+/// Returns a native reader if the read step can be handled without an engine
+pub fn native_reader_for(
+    read_step: &ReadStep,
+    path: &Path,
+) -> Result<Option<Box<dyn NativeReader>>, ReadError> {
+    match read_step {
+        ReadStep::Csv(conf) if CsvReader::supports(conf) => {
+            Ok(Some(Box::new(CsvReader::new(conf, path)?)))
+        }
+        ReadStep::JsonLines(conf) if JsonLinesReader::supports(conf) => {
+            Ok(Some(Box::new(JsonLinesReader::new(conf, path)?)))
+        }
+        ReadStep::GeoJson(conf) if GeoJsonReader::supports(conf) => {
+            Ok(Some(Box::new(GeoJsonReader::new(conf, path)?)))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Reads all records from Parquet part files in the data directory
+/// in the order they were written
+pub fn read_parquet_dir(data_dir: &Path) -> Result<Vec<RecordBatch>, ReadError> {
+    let mut batches = Vec::new();
+    for path in list_parquet_files(data_dir)? {
+        batches.extend(read_parquet_file(&path)?);
+    }
+
+    Ok(batches)
+}
+
+/// Reads only the specified columns of all records from Parquet part files
+/// in the data directory in the order they were written
+pub fn read_parquet_dir_columns(
+    data_dir: &Path,
+    columns: &[String],
+) -> Result<Vec<RecordBatch>, ReadError> {
+    let mut batches = Vec::new();
+    for path in list_parquet_files(data_dir)? {
+        let file_reader = SerializedFileReader::new(File::open(&path)?)?;
+        let mut arrow_reader = ParquetFileArrowReader::new(Rc::new(file_reader));
+
+        // Columns missing in the file are handled by the consumer
+        let schema = arrow_reader.get_schema()?;
+        let indices: Vec<usize> = columns
+            .iter()
+            .filter_map(|c| schema.index_of(c).ok())
+            .collect();
+        if indices.is_empty() {
+            continue;
+        }
+
+        for batch in arrow_reader.get_record_reader_by_columns(indices, PARQUET_BATCH_SIZE)? {
+            batches.push(batch?);
+        }
+    }
+
+    Ok(batches)
+}
+
+pub fn read_parquet_file(path: &Path) -> Result<Vec<RecordBatch>, ReadError> {
+    let file_reader = SerializedFileReader::new(File::open(path)?)?;
+    let mut arrow_reader = ParquetFileArrowReader::new(Rc::new(file_reader));
+    arrow_reader
+        .get_record_reader(PARQUET_BATCH_SIZE)?
+        .map(|b| b.map_err(ReadError::from))
+        .collect()
+}
+
+/// Lists Parquet part files in the data directory in the order they were written.
+///
+/// Files are ordered by modification time as names given by engines don't
+/// reflect the order of writes. Files written within the same timestamp
+/// resolution fall back to ordering by name, which for files written natively
+/// is the system time of their block.
+pub fn list_parquet_files(data_dir: &Path) -> Result<Vec<PathBuf>, std::io::Error> {
+    if !data_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(data_dir)? {
+        let path = entry?.path();
+        if path.extension().map(|e| e == "parquet").unwrap_or(false) {
+            let modified = std::fs::metadata(&path)?.modified()?;
+            files.push((modified, path));
+        }
+    }
+
+    files.sort();
+    Ok(files.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Reads the schema of a Parquet file without reading its data
+pub fn read_parquet_schema(path: &Path) -> Result<SchemaRef, ReadError> {
+    let file_reader = SerializedFileReader::new(File::open(path)?)?;
+    let mut arrow_reader = ParquetFileArrowReader::new(Rc::new(file_reader));
+    Ok(Arc::new(arrow_reader.get_schema()?))
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Errors
+///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Error, Debug)]
+pub enum ReadError {
+    #[error("Failed to read data: {0}")]
+    IOError(#[from] std::io::Error),
+    #[error("Malformed CSV: {0}")]
+    CsvError(#[from] csv::Error),
+    #[error("Malformed JSON: {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("Malformed record {line}: {reason}")]
+    MalformedRecord { line: u64, reason: String },
+    #[error("{0}")]
+    SchemaError(#[from] DdlError),
+    #[error("Bad value in column {column} at line {line}: {source}")]
+    BadValue {
+        line: u64,
+        column: String,
+        #[source]
+        source: ValueParseError,
+    },
+    #[error("Column {column} has type {data_type:?} which cannot be used as {role}")]
+    BadColumnType {
+        column: String,
+        data_type: arrow::datatypes::DataType,
+        role: &'static str,
+    },
+    #[error("Column {column} is reserved for system time")]
+    ReservedColumn { column: String },
+    #[error("Arrow error: {0}")]
+    ArrowError(#[from] ArrowError),
+    #[error("Parquet error: {0}")]
+    ParquetError(#[from] ParquetError),
+}
@@ -10,49 +10,67 @@ use std::fs::File;
 use std::io::prelude::*;
 use std::io::Error as IOError;
 use std::path::Path;
-use std::process::{Command, Stdio};
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::mpsc::{Receiver, SyncSender};
+use std::thread::JoinHandle;
 
 const BUFFER_SIZE: usize = 8096;
 
-pub struct PrepService {}
+pub struct PrepService {
+    archive_format: Option<ArchiveFormat>,
+    cancel: CancellationToken,
+}
 
 impl PrepService {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            archive_format: None,
+            cancel: CancellationToken::new(),
+        }
+    }
+
+    /// Decompresses data in the specified format instead of the one set by the decompress
+    /// steps, allowing archive formats that have no `CompressionFormat` counterpart
+    pub fn with_archive_format(self, archive_format: ArchiveFormat) -> Self {
+        Self {
+            archive_format: Some(archive_format),
+            ..self
+        }
+    }
+
+    /// Reports failures of commands that were interrupted along with the pull as cancellation
+    pub fn with_cancellation(self, cancel: CancellationToken) -> Self {
+        Self {
+            cancel: cancel,
+            ..self
+        }
     }
 
+    /// The read step determines how data of multiple archive entries can be combined
     pub fn prepare(
         &self,
         prep_steps: &Vec<PrepStep>,
+        read_step: &ReadStep,
         for_fetched_at: DateTime<Utc>,
         _old_checkpoint: Option<PrepCheckpoint>,
         src_path: &Path,
         target_path: &Path,
     ) -> Result<ExecutionResult<PrepCheckpoint>, IngestError> {
-        let mut stream: Box<dyn Stream> =
-            Box::new(File::open(src_path).map_err(|e| IngestError::internal(e))?);
-
-        for step in prep_steps.iter() {
-            stream = match step {
-                PrepStep::Pipe(ref p) => Box::new(
-                    PipeStream::new(&p.command, stream).map_err(|e| IngestError::internal(e))?,
-                ),
-                PrepStep::Decompress(ref dc) => match dc.format {
-                    CompressionFormat::Zip => Box::new(
-                        DecompressZipStream::new(stream).map_err(|e| IngestError::internal(e))?,
-                    ),
-                    CompressionFormat::Gzip => unimplemented!(),
-                },
-            };
+        let target_path_tmp = target_path.with_extension("tmp");
+
+        match self.prepare_impl(prep_steps, read_step, src_path, &target_path_tmp) {
+            Ok(()) => {
+                std::fs::rename(&target_path_tmp, target_path)
+                    .map_err(|e| IngestError::internal(e))?;
+            }
+            Err(e) => {
+                let _ = std::fs::remove_file(&target_path_tmp);
+                // Ctrl+C also terminates the commands, which then exit with an error
+                self.cancel.check()?;
+                return Err(IngestError::failed_stage(IngestStage::Prepare, e));
+            }
         }
 
-        let target_file = File::create(target_path).map_err(|e| IngestError::internal(e))?;
-        let sink = Box::new(FileSink::new(target_file, stream));
-
-        sink.join();
-
         Ok(ExecutionResult {
             was_up_to_date: false,
             checkpoint: PrepCheckpoint {
@@ -61,6 +79,77 @@ impl PrepService {
             },
         })
     }
+
+    fn prepare_impl(
+        &self,
+        prep_steps: &Vec<PrepStep>,
+        read_step: &ReadStep,
+        src_path: &Path,
+        target_path: &Path,
+    ) -> Result<(), PrepError> {
+        // Validate patterns upfront to not leave half-constructed pipelines behind
+        let sub_paths = prep_steps
+            .iter()
+            .enumerate()
+            .map(|(i, step)| match step {
+                PrepStep::Decompress(ref dc) => {
+                    compile_sub_path(dc.sub_path.as_ref()).map_err(|e| PrepError::step_failed(i, e))
+                }
+                _ => Ok(None),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let concat = EntryConcat::for_read_step(read_step);
+
+        let mut stream: Box<dyn Stream> =
+            Box::new(File::open(src_path).map_err(PrepError::SourceFailed)?);
+
+        for (i, (step, sub_path)) in prep_steps.iter().zip(sub_paths).enumerate() {
+            stream = match step {
+                PrepStep::Pipe(ref p) => Box::new(PipeStream::new(i, &p.command, stream)?),
+                PrepStep::Decompress(ref dc) => match self
+                    .archive_format
+                    .unwrap_or_else(|| ArchiveFormat::from(&dc.format))
+                {
+                    ArchiveFormat::Zip => {
+                        Box::new(DecompressZipStream::new(i, stream, sub_path, concat))
+                    }
+                    ArchiveFormat::Gzip => Box::new(DecompressStream::gzip(i, stream)),
+                    ArchiveFormat::Bzip2 => Box::new(DecompressStream::bzip2(i, stream)),
+                    ArchiveFormat::Xz => Box::new(DecompressStream::xz(i, stream)),
+                    ArchiveFormat::Tar => Box::new(UntarStream::new(i, stream, sub_path, concat)),
+                    ArchiveFormat::TarGz => Box::new(UntarStream::new(
+                        i,
+                        Box::new(DecompressStream::gzip(i, stream)),
+                        sub_path,
+                        concat,
+                    )),
+                    ArchiveFormat::TarBz2 => Box::new(UntarStream::new(
+                        i,
+                        Box::new(DecompressStream::bzip2(i, stream)),
+                        sub_path,
+                        concat,
+                    )),
+                    ArchiveFormat::TarXz => Box::new(UntarStream::new(
+                        i,
+                        Box::new(DecompressStream::xz(i, stream)),
+                        sub_path,
+                        concat,
+                    )),
+                },
+            };
+        }
+
+        let target_file = match File::create(target_path) {
+            Ok(f) => f,
+            Err(e) => {
+                let _ = stream.join();
+                return Err(PrepError::SinkFailed(e));
+            }
+        };
+
+        FileSink::new(target_file, stream).join()
+    }
 }
 
 #[skip_serializing_none]
@@ -77,12 +166,12 @@ pub struct PrepCheckpoint {
 // Ghetto Streams
 ///////////////////////////////////////////////////////////////////////////////
 
-// TODO: Error handling (?)
-
 trait Stream: std::io::Read + Send {
     fn as_read(&mut self) -> &mut dyn std::io::Read;
 
-    fn join(self: Box<Self>);
+    /// Waits for this stream and all of its upstreams to finish, reporting the
+    /// error of the earliest step that caused the failure
+    fn join(self: Box<Self>) -> Result<(), PrepError>;
 }
 
 impl Stream for std::fs::File {
@@ -90,7 +179,53 @@ impl Stream for std::fs::File {
         self
     }
 
-    fn join(self: Box<Self>) {}
+    fn join(self: Box<Self>) -> Result<(), PrepError> {
+        Ok(())
+    }
+}
+
+enum PumpError {
+    Read(IOError),
+    Write(IOError),
+}
+
+/// Copies data until the input is exhausted or the output stops accepting it
+fn pump(input: &mut dyn Read, output: &mut dyn Write) -> Result<(), PumpError> {
+    let mut buf = [0; BUFFER_SIZE];
+
+    loop {
+        let read = input.read(&mut buf).map_err(PumpError::Read)?;
+        if read == 0 {
+            break;
+        }
+        match output.write_all(&buf[..read]) {
+            Ok(()) => (),
+            // Consumer has stopped reading - it will report its own errors if any
+            Err(ref e) if e.kind() == std::io::ErrorKind::BrokenPipe => break,
+            Err(e) => return Err(PumpError::Write(e)),
+        }
+    }
+
+    Ok(())
+}
+
+/// Pumps all data from the upstream into the output and joins the upstream.
+///
+/// Read errors are usually a consequence of upstream failure so the upstream
+/// error takes precedence, while write errors are attributed to this step.
+fn drain_into(
+    step: usize,
+    mut input: Box<dyn Stream>,
+    output: &mut dyn Write,
+) -> Result<(), PrepError> {
+    let res = pump(input.as_read(), output);
+    let upstream_res = input.join();
+
+    match res {
+        Ok(()) => upstream_res,
+        Err(PumpError::Read(e)) => upstream_res.and(Err(PrepError::step_failed(step, e))),
+        Err(PumpError::Write(e)) => Err(PrepError::step_failed(step, e)),
+    }
 }
 
 ///////////////////////////////////////////////////////////////////////////////
@@ -98,46 +233,76 @@ impl Stream for std::fs::File {
 ///////////////////////////////////////////////////////////////////////////////
 
 struct PipeStream {
-    ingress: std::thread::JoinHandle<()>,
-    stdout: std::process::ChildStdout,
+    step: usize,
+    command: Vec<String>,
+    child: Child,
+    stdout: ChildStdout,
+    eof: bool,
+    ingress: JoinHandle<Result<(), PrepError>>,
+    stderr_collector: JoinHandle<Vec<u8>>,
 }
 
 impl PipeStream {
-    fn new(cmd: &Vec<String>, mut input: Box<dyn Stream>) -> Result<Self, IOError> {
-        let process = Command::new(cmd.get(0).unwrap())
-            .args(&cmd[1..])
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .spawn()?;
-
-        let mut stdin = process.stdin.unwrap();
+    fn new(step: usize, cmd: &Vec<String>, input: Box<dyn Stream>) -> Result<Self, PrepError> {
+        let spawn_res = match cmd.get(0) {
+            None => Err(IOError::new(
+                std::io::ErrorKind::InvalidInput,
+                "Pipe command is empty",
+            )),
+            Some(program) => Command::new(program)
+                .args(&cmd[1..])
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn(),
+        };
+
+        let mut child = match spawn_res {
+            Ok(child) => child,
+            Err(e) => {
+                let _ = input.join();
+                return Err(PrepError::step_failed(step, e));
+            }
+        };
+
+        let mut stdin = child.stdin.take().unwrap();
+        let mut stderr = child.stderr.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+
         let ingress = std::thread::Builder::new()
             .name("pipe_stream".to_owned())
-            .spawn(move || {
-                let mut buf = [0; BUFFER_SIZE];
-
-                loop {
-                    let read = input.read(&mut buf).unwrap();
-                    if read == 0 {
-                        break;
-                    }
-                    stdin.write_all(&buf[..read]).unwrap();
-                }
+            .spawn(move || drain_into(step, input, &mut stdin))
+            .unwrap();
 
-                input.join();
+        // Collecting stderr concurrently so the process never blocks on it
+        let stderr_collector = std::thread::Builder::new()
+            .name("pipe_stream_stderr".to_owned())
+            .spawn(move || {
+                let mut buf = Vec::new();
+                let _ = stderr.read_to_end(&mut buf);
+                buf
             })
             .unwrap();
 
         Ok(Self {
+            step: step,
+            command: cmd.clone(),
+            child: child,
+            stdout: stdout,
+            eof: false,
             ingress: ingress,
-            stdout: process.stdout.unwrap(),
+            stderr_collector: stderr_collector,
         })
     }
 }
 
 impl Read for PipeStream {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize, IOError> {
-        self.stdout.read(buf)
+        let read = self.stdout.read(buf)?;
+        if read == 0 && !buf.is_empty() {
+            self.eof = true;
+        }
+        Ok(read)
     }
 }
 
@@ -146,8 +311,41 @@ impl Stream for PipeStream {
         self
     }
 
-    fn join(self: Box<Self>) {
-        self.ingress.join().unwrap()
+    fn join(self: Box<Self>) -> Result<(), PrepError> {
+        let Self {
+            step,
+            command,
+            mut child,
+            stdout,
+            eof,
+            ingress,
+            stderr_collector,
+        } = *self;
+
+        // Closing stdout unblocks the process if consumer stopped reading early
+        drop(stdout);
+
+        let upstream_res = ingress.join().unwrap();
+        let status = child.wait().map_err(|e| PrepError::step_failed(step, e));
+        let stderr = stderr_collector.join().unwrap();
+
+        upstream_res?;
+        let status = status?;
+
+        // When consumer stops reading before the end of output the process
+        // is likely to fail on a broken pipe, which is not an error
+        if status.success() || !eof {
+            Ok(())
+        } else {
+            Err(PrepError::CommandFailed {
+                step: step,
+                source: CommandError {
+                    command: command,
+                    exit_code: status.code(),
+                    stderr: String::from_utf8_lossy(&stderr).into_owned(),
+                },
+            })
+        }
     }
 }
 
@@ -164,74 +362,457 @@ impl Read for ReaderHelper {
 }
 
 struct DecompressZipStream {
-    ingress: std::thread::JoinHandle<()>,
-    consumer: ringbuf::Consumer<u8>,
-    done_flag: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    ingress: JoinHandle<Result<(), PrepError>>,
+    reader: ChannelReader,
 }
 
 impl DecompressZipStream {
-    fn new(input: Box<dyn Stream>) -> Result<Self, IOError> {
-        let (mut producer, consumer) = ringbuf::RingBuffer::<u8>::new(BUFFER_SIZE).split();
-
-        let done_flag = Arc::new(AtomicBool::new(false));
-        let done_flag_thread = done_flag.clone();
-
-        // TODO: Threading is a complete overkill here
-        // Only reason for this is the `read_zipfile_from_stream` API that forces
-        // use of mutable reference
-        // See: https://github.com/mvdnes/zip-rs/issues/111
+    fn new(
+        step: usize,
+        input: Box<dyn Stream>,
+        pattern: Option<glob::Pattern>,
+        concat: EntryConcat,
+    ) -> Self {
+        let (writer, reader) = channel_pipe();
+
+        // Streaming zip reader relies on sizes in local headers which are often
+        // missing (data descriptors), so we spool the archive into a temp file
+        // and use the central directory instead.
+        // Entries borrow the archive, hence the separate thread.
         let ingress = std::thread::Builder::new()
             .name("decompress_zip_stream".to_owned())
             .spawn(move || {
-                let mut reader = ReaderHelper(input);
+                let mut spool =
+                    tempfile::tempfile().map_err(|e| PrepError::step_failed(step, e))?;
+                drain_into(step, input, &mut spool)?;
 
-                {
-                    let mut zipfile = zip::read::read_zipfile_from_stream(&mut reader)
-                        .unwrap()
-                        .unwrap();
-
-                    loop {
-                        let read = producer.read_from(&mut zipfile, None).unwrap();
-                        if read == 0 {
-                            break;
-                        }
-                    }
-                }
-
-                done_flag_thread.store(true, Ordering::Release);
-                reader.0.join();
+                Self::extract(spool, ArchiveEntrySink::new(writer, pattern, concat))
+                    .map_err(|e| PrepError::step_failed(step, e))
             })
             .unwrap();
 
-        Ok(Self {
+        Self {
             ingress: ingress,
-            consumer: consumer,
-            done_flag: done_flag,
-        })
+            reader: reader,
+        }
+    }
+
+    fn extract(mut spool: File, mut sink: ArchiveEntrySink<ChannelWriter>) -> Result<(), IOError> {
+        spool.seek(std::io::SeekFrom::Start(0))?;
+        let mut archive = zip::ZipArchive::new(spool)?;
+
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i)?;
+            if entry.is_dir() || !sink.accepts(Path::new(entry.name())) {
+                continue;
+            }
+            if !sink.append(&mut entry)? || sink.is_done() {
+                break;
+            }
+        }
+
+        sink.finish()
     }
 }
 
 impl Read for DecompressZipStream {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize, IOError> {
-        while self.consumer.is_empty() && !self.done_flag.load(Ordering::Acquire) {
-            std::thread::sleep(std::time::Duration::from_millis(1));
+        self.reader.read(buf)
+    }
+}
+
+impl Stream for DecompressZipStream {
+    fn as_read(&mut self) -> &mut dyn std::io::Read {
+        self
+    }
+
+    fn join(self: Box<Self>) -> Result<(), PrepError> {
+        let Self { ingress, reader } = *self;
+        drop(reader);
+        ingress.join().unwrap()
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// DecompressStream (gzip, bzip2, xz)
+///////////////////////////////////////////////////////////////////////////////
+
+struct DecompressStream<D> {
+    step: usize,
+    decoder: D,
+    into_inner: fn(D) -> ReaderHelper,
+    // Decoding happens on the consumer's thread, so we remember the error
+    // to attribute it to this step when joined
+    error: Option<IOError>,
+}
+
+impl<D> DecompressStream<D> {
+    fn new(step: usize, decoder: D, into_inner: fn(D) -> ReaderHelper) -> Self {
+        Self {
+            step: step,
+            decoder: decoder,
+            into_inner: into_inner,
+            error: None,
         }
+    }
+}
 
-        if !self.consumer.is_empty() {
-            self.consumer.read(buf)
-        } else {
-            Ok(0)
+impl DecompressStream<flate2::read::MultiGzDecoder<ReaderHelper>> {
+    fn gzip(step: usize, input: Box<dyn Stream>) -> Self {
+        Self::new(
+            step,
+            flate2::read::MultiGzDecoder::new(ReaderHelper(input)),
+            flate2::read::MultiGzDecoder::into_inner,
+        )
+    }
+}
+
+impl DecompressStream<bzip2::read::BzDecoder<ReaderHelper>> {
+    fn bzip2(step: usize, input: Box<dyn Stream>) -> Self {
+        Self::new(
+            step,
+            bzip2::read::BzDecoder::new(ReaderHelper(input)),
+            bzip2::read::BzDecoder::into_inner,
+        )
+    }
+}
+
+impl DecompressStream<xz2::read::XzDecoder<ReaderHelper>> {
+    fn xz(step: usize, input: Box<dyn Stream>) -> Self {
+        Self::new(
+            step,
+            xz2::read::XzDecoder::new(ReaderHelper(input)),
+            xz2::read::XzDecoder::into_inner,
+        )
+    }
+}
+
+impl<D: Read> Read for DecompressStream<D> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IOError> {
+        self.decoder.read(buf).map_err(|e| {
+            if self.error.is_none() {
+                self.error = Some(IOError::new(e.kind(), e.to_string()));
+            }
+            e
+        })
+    }
+}
+
+impl<D: Read + Send> Stream for DecompressStream<D> {
+    fn as_read(&mut self) -> &mut dyn std::io::Read {
+        self
+    }
+
+    fn join(self: Box<Self>) -> Result<(), PrepError> {
+        let this = *self;
+        (this.into_inner)(this.decoder).0.join()?;
+        match this.error {
+            None => Ok(()),
+            Some(e) => Err(PrepError::step_failed(this.step, e)),
         }
     }
 }
 
-impl Stream for DecompressZipStream {
+///////////////////////////////////////////////////////////////////////////////
+// UntarStream
+///////////////////////////////////////////////////////////////////////////////
+
+struct UntarStream {
+    ingress: JoinHandle<Result<(), PrepError>>,
+    reader: ChannelReader,
+}
+
+impl UntarStream {
+    fn new(
+        step: usize,
+        input: Box<dyn Stream>,
+        pattern: Option<glob::Pattern>,
+        concat: EntryConcat,
+    ) -> Self {
+        let (writer, reader) = channel_pipe();
+
+        // Entries of a tar archive borrow the archive itself, so we extract
+        // in a separate thread and hand the data over through a channel
+        let ingress = std::thread::Builder::new()
+            .name("untar_stream".to_owned())
+            .spawn(move || {
+                let mut archive = tar::Archive::new(ReaderHelper(input));
+                let sink = ArchiveEntrySink::new(writer, pattern, concat);
+                let res = Self::extract(&mut archive, sink);
+                let upstream_res = archive.into_inner().0.join();
+                upstream_res.and(res.map_err(|e| PrepError::step_failed(step, e)))
+            })
+            .unwrap();
+
+        Self {
+            ingress: ingress,
+            reader: reader,
+        }
+    }
+
+    fn extract(
+        archive: &mut tar::Archive<ReaderHelper>,
+        mut sink: ArchiveEntrySink<ChannelWriter>,
+    ) -> Result<(), IOError> {
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            if !entry.header().entry_type().is_file() || !sink.accepts(&entry.path()?) {
+                continue;
+            }
+            if !sink.append(&mut entry)? || sink.is_done() {
+                break;
+            }
+        }
+
+        sink.finish()
+    }
+}
+
+impl Read for UntarStream {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IOError> {
+        self.reader.read(buf)
+    }
+}
+
+impl Stream for UntarStream {
     fn as_read(&mut self) -> &mut dyn std::io::Read {
         self
     }
 
-    fn join(self: Box<Self>) {
-        self.ingress.join().unwrap()
+    fn join(self: Box<Self>) -> Result<(), PrepError> {
+        let Self { ingress, reader } = *self;
+        drop(reader);
+        ingress.join().unwrap()
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Archive entry selection
+///////////////////////////////////////////////////////////////////////////////
+
+fn compile_sub_path(sub_path: Option<&String>) -> Result<Option<glob::Pattern>, IOError> {
+    match sub_path {
+        None => Ok(None),
+        Some(p) => glob::Pattern::new(p)
+            .map(Some)
+            .map_err(|e| IOError::new(std::io::ErrorKind::InvalidInput, e)),
+    }
+}
+
+/// How data of multiple archive entries is combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EntryConcat {
+    /// Line-oriented data that is joined as is
+    Lines,
+    /// CSV with a header line that is kept only from the first entry
+    LinesWithHeader,
+    /// Documents and binary data that only a single entry can provide
+    Unsupported,
+}
+
+impl EntryConcat {
+    fn for_read_step(read_step: &ReadStep) -> Self {
+        match read_step {
+            ReadStep::Csv(ref csv) if csv.header == Some(true) => Self::LinesWithHeader,
+            ReadStep::Csv(_) | ReadStep::JsonLines(_) => Self::Lines,
+            ReadStep::GeoJson(_) | ReadStep::EsriShapefile(_) => Self::Unsupported,
+        }
+    }
+}
+
+/// Writes out archive entries selected by `sub_path`.
+///
+/// Without a pattern only the first file of the archive is extracted.
+/// With a pattern all matching entries are concatenated if the data format
+/// allows it, keeping the CSV header only from the first entry.
+struct ArchiveEntrySink<W: Write> {
+    writer: W,
+    pattern: Option<glob::Pattern>,
+    concat: EntryConcat,
+    header: Option<Vec<u8>>,
+    ends_with_newline: bool,
+    num_entries: usize,
+}
+
+impl<W: Write> ArchiveEntrySink<W> {
+    fn new(writer: W, pattern: Option<glob::Pattern>, concat: EntryConcat) -> Self {
+        Self {
+            writer: writer,
+            pattern: pattern,
+            concat: concat,
+            header: None,
+            ends_with_newline: true,
+            num_entries: 0,
+        }
+    }
+
+    fn accepts(&self, path: &Path) -> bool {
+        match self.pattern {
+            None => true,
+            Some(ref p) => p.matches_path(path),
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.pattern.is_none() && self.num_entries > 0
+    }
+
+    fn finish(self) -> Result<(), IOError> {
+        if self.num_entries == 0 {
+            Err(IOError::new(
+                std::io::ErrorKind::NotFound,
+                match self.pattern {
+                    None => "Archive contains no files".to_owned(),
+                    Some(p) => format!("No archive entries match the pattern {}", p),
+                },
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns `false` if the consumer stopped accepting data
+    fn append(&mut self, entry: &mut dyn Read) -> Result<bool, IOError> {
+        match self.append_impl(entry) {
+            Ok(()) => Ok(true),
+            Err(ref e) if e.kind() == std::io::ErrorKind::BrokenPipe => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn append_impl(&mut self, entry: &mut dyn Read) -> Result<(), IOError> {
+        if self.num_entries > 0 && self.concat == EntryConcat::Unsupported {
+            return Err(IOError::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "Multiple archive entries match the pattern {} but the data format \
+                     does not allow concatenating them",
+                    self.pattern.as_ref().unwrap()
+                ),
+            ));
+        }
+
+        self.num_entries += 1;
+
+        let mut reader = std::io::BufReader::with_capacity(BUFFER_SIZE, entry);
+
+        let mut first_line = Vec::new();
+        reader.read_until(b'\n', &mut first_line)?;
+
+        if first_line.is_empty() {
+            return Ok(());
+        }
+
+        if !self.ends_with_newline {
+            self.writer.write_all(b"\n")?;
+            self.ends_with_newline = true;
+        }
+
+        let first_line_complete = first_line.last() == Some(&b'\n');
+        match (self.concat, &self.header) {
+            (EntryConcat::LinesWithHeader, None) => {
+                self.writer.write_all(&first_line)?;
+                self.ends_with_newline = first_line_complete;
+                self.header = Some(first_line);
+            }
+            (EntryConcat::LinesWithHeader, Some(ref header)) => {
+                if trim_newline(header) != trim_newline(&first_line) {
+                    return Err(IOError::new(
+                        std::io::ErrorKind::InvalidData,
+                        "Archive entries have different CSV headers",
+                    ));
+                }
+            }
+            _ => {
+                self.writer.write_all(&first_line)?;
+                self.ends_with_newline = first_line_complete;
+            }
+        }
+
+        let mut buf = [0; BUFFER_SIZE];
+        loop {
+            let read = reader.read(&mut buf)?;
+            if read == 0 {
+                break;
+            }
+            self.writer.write_all(&buf[..read])?;
+            self.ends_with_newline = buf[read - 1] == b'\n';
+        }
+
+        Ok(())
+    }
+}
+
+// Last entry might lack the trailing newline
+fn trim_newline(mut line: &[u8]) -> &[u8] {
+    for c in &[b'\n', b'\r'] {
+        if line.last() == Some(c) {
+            line = &line[..line.len() - 1];
+        }
+    }
+    line
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Channel Pipe
+///////////////////////////////////////////////////////////////////////////////
+
+// Max number of in-flight chunks before the writer blocks
+const CHANNEL_CAPACITY: usize = 16;
+
+fn channel_pipe() -> (ChannelWriter, ChannelReader) {
+    let (tx, rx) = std::sync::mpsc::sync_channel(CHANNEL_CAPACITY);
+    (
+        ChannelWriter(tx),
+        ChannelReader {
+            rx: rx,
+            chunk: Vec::new(),
+            pos: 0,
+        },
+    )
+}
+
+struct ChannelWriter(SyncSender<Vec<u8>>);
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, IOError> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.0
+            .send(buf.to_vec())
+            .map_err(|_| IOError::new(std::io::ErrorKind::BrokenPipe, "Reader has hung up"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), IOError> {
+        Ok(())
+    }
+}
+
+struct ChannelReader {
+    rx: Receiver<Vec<u8>>,
+    chunk: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IOError> {
+        while self.pos >= self.chunk.len() {
+            match self.rx.recv() {
+                Ok(chunk) => {
+                    self.chunk = chunk;
+                    self.pos = 0;
+                }
+                // Writer was dropped - end of stream
+                Err(_) => return Ok(0),
+            }
+        }
+
+        let n = std::cmp::min(buf.len(), self.chunk.len() - self.pos);
+        buf[..n].copy_from_slice(&self.chunk[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
     }
 }
 
@@ -240,7 +821,7 @@ impl Stream for DecompressZipStream {
 ///////////////////////////////////////////////////////////////////////////////
 
 struct FileSink {
-    ingress: std::thread::JoinHandle<()>,
+    ingress: JoinHandle<Result<(), PrepError>>,
 }
 
 impl FileSink {
@@ -248,24 +829,82 @@ impl FileSink {
         let ingress = std::thread::Builder::new()
             .name("file_sink".to_owned())
             .spawn(move || {
-                let mut buf = [0; BUFFER_SIZE];
-
-                loop {
-                    let read = input.read(&mut buf).unwrap();
-                    if read == 0 {
-                        break;
-                    }
-                    file.write_all(&buf[..read]).unwrap();
+                let res = pump(input.as_read(), &mut file)
+                    .and_then(|_| file.sync_all().map_err(PumpError::Write));
+                let upstream_res = input.join();
+
+                match res {
+                    Ok(()) => upstream_res,
+                    Err(PumpError::Read(e)) => upstream_res.and(Err(PrepError::SinkFailed(e))),
+                    Err(PumpError::Write(e)) => Err(PrepError::SinkFailed(e)),
                 }
-
-                input.join();
             })
             .unwrap();
 
         Self { ingress: ingress }
     }
 
-    fn join(self: Box<Self>) {
-        self.ingress.join().unwrap();
+    fn join(self) -> Result<(), PrepError> {
+        self.ingress.join().unwrap()
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Errors
+///////////////////////////////////////////////////////////////////////////////
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum PrepError {
+    #[error("Failed to read source data: {0}")]
+    SourceFailed(#[source] IOError),
+    #[error("Prepare step {step} failed: {source}")]
+    StepFailed {
+        step: usize,
+        #[source]
+        source: IOError,
+    },
+    #[error("Prepare step {step} failed: {source}")]
+    CommandFailed {
+        step: usize,
+        #[source]
+        source: CommandError,
+    },
+    #[error("Failed to write prepared data: {0}")]
+    SinkFailed(#[source] IOError),
+}
+
+impl PrepError {
+    fn step_failed(step: usize, e: IOError) -> Self {
+        Self::StepFailed {
+            step: step,
+            source: e,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub struct CommandError {
+    pub command: Vec<String>,
+    pub exit_code: Option<i32>,
+    pub stderr: String,
+}
+
+impl std::fmt::Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Command {:?} ", self.command)?;
+
+        match self.exit_code {
+            Some(c) => write!(f, "exited with code {}", c)?,
+            None => write!(f, "was terminated by a signal")?,
+        }
+
+        let stderr = self.stderr.trim();
+        if !stderr.is_empty() {
+            write!(f, ", stderr:\n{}", stderr)?;
+        }
+
+        Ok(())
     }
 }
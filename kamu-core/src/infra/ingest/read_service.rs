@@ -8,20 +8,21 @@ use ::serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use serde_with::skip_serializing_none;
 use std::path::Path;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 
 pub struct ReadService {
-    engine_factory: Arc<Mutex<EngineFactory>>,
+    engine_factory: Arc<EngineFactory>,
+    cancel: CancellationToken,
 }
 
 impl ReadService {
-    pub fn new(engine_factory: Arc<Mutex<EngineFactory>>) -> Self {
+    pub fn new(engine_factory: Arc<EngineFactory>, cancel: CancellationToken) -> Self {
         Self {
             engine_factory: engine_factory,
+            cancel: cancel,
         }
     }
 
-    // TODO: Don't use engine for anything but preprocessing
     pub fn read(
         &self,
         dataset_id: &DatasetID,
@@ -33,7 +34,21 @@ impl ReadService {
         _old_checkpoint: Option<ReadCheckpoint>,
         src_path: &Path,
     ) -> Result<ExecutionResult<ReadCheckpoint>, IngestError> {
-        let engine = self.engine_factory.lock().unwrap().get_engine("sparkSQL")?;
+        if let Some(reader) = self
+            .native_reader(source, src_path)
+            .map_err(|e| IngestError::failed_stage(IngestStage::Read, e))?
+        {
+            return self.read_native(
+                reader,
+                dataset_layout,
+                source,
+                source_event_time,
+                vocab,
+                for_prepared_at,
+            );
+        }
+
+        let engine = self.engine_factory.get_engine("sparkSQL")?;
 
         let request = IngestRequest {
             dataset_id: dataset_id.to_owned(),
@@ -45,7 +60,10 @@ impl ReadService {
             data_dir: dataset_layout.data_dir.clone(),
         };
 
-        let response = engine.lock().unwrap().ingest(request)?;
+        let response = engine.ingest(request, &self.cancel).map_err(|e| match e {
+            EngineError::Cancelled(e) => IngestError::Cancelled(e),
+            e => e.into(),
+        })?;
 
         Ok(ExecutionResult {
             was_up_to_date: false,
@@ -53,9 +71,100 @@ impl ReadService {
                 last_read: Utc::now(),
                 for_prepared_at: for_prepared_at,
                 last_block: response.block,
+                output_schema: None,
+            },
+        })
+    }
+
+    // Engine is only needed when data has to be preprocessed
+    fn native_reader(
+        &self,
+        source: &DatasetSourceRoot,
+        src_path: &Path,
+    ) -> Result<Option<Box<dyn NativeReader>>, ReadError> {
+        match source.preprocess {
+            None => native_reader_for(&source.read, src_path),
+            Some(_) => Ok(None),
+        }
+    }
+
+    fn read_native(
+        &self,
+        mut reader: Box<dyn NativeReader>,
+        dataset_layout: &DatasetLayout,
+        source: &DatasetSourceRoot,
+        source_event_time: Option<DateTime<Utc>>,
+        vocab: &DatasetVocabulary,
+        for_prepared_at: DateTime<Utc>,
+    ) -> Result<ExecutionResult<ReadCheckpoint>, IngestError> {
+        let system_time = Utc::now();
+
+        let writer = DataWriter::new(
+            &dataset_layout.data_dir,
+            vocab,
+            system_time,
+            source_event_time,
+        );
+
+        let res = match source.merge {
+            MergeStrategy::Append => writer.write(reader.as_mut()),
+            _ => {
+                let mut merged = self.merge(reader.as_mut(), dataset_layout, source, vocab)?;
+                writer.write(merged.as_mut())
+            }
+        }
+        .map_err(|e| IngestError::failed_stage(IngestStage::Read, e))?;
+
+        Ok(ExecutionResult {
+            was_up_to_date: false,
+            checkpoint: ReadCheckpoint {
+                last_read: Utc::now(),
+                for_prepared_at: for_prepared_at,
+                last_block: MetadataBlock {
+                    block_hash: "".to_owned(),
+                    prev_block_hash: "".to_owned(),
+                    system_time: system_time,
+                    output_slice: res.output_slice,
+                    output_watermark: res.output_watermark,
+                    input_slices: None,
+                    source: None,
+                },
+                output_schema: None,
             },
         })
     }
+
+    fn merge(
+        &self,
+        reader: &mut dyn NativeReader,
+        dataset_layout: &DatasetLayout,
+        source: &DatasetSourceRoot,
+        vocab: &DatasetVocabulary,
+    ) -> Result<Box<dyn NativeReader>, IngestError> {
+        let merger = merger_for(&source.merge, vocab);
+
+        // Only the columns the merge depends on are read from the dataset history
+        let read = |reader: &mut dyn NativeReader| -> Result<_, ReadError> {
+            let mut new = Vec::new();
+            while let Some(batch) = reader.next_batch()? {
+                new.push(batch);
+            }
+            let prev_columns = merger.prev_columns(&reader.schema());
+            let prev = if prev_columns.is_empty() {
+                Vec::new()
+            } else {
+                read_parquet_dir_columns(&dataset_layout.data_dir, &prev_columns)?
+            };
+            Ok((prev, new))
+        };
+
+        let (prev, new) =
+            read(reader).map_err(|e| IngestError::failed_stage(IngestStage::Read, e))?;
+
+        merger
+            .merge(&prev, reader.schema(), &new)
+            .map_err(|e| IngestError::failed_stage(IngestStage::Merge, e))
+    }
 }
 
 #[skip_serializing_none]
@@ -67,4 +176,7 @@ pub struct ReadCheckpoint {
     #[serde(with = "datetime_rfc3339")]
     pub for_prepared_at: DateTime<Utc>,
     pub last_block: MetadataBlock,
+    /// Schema of the data written by the read, filled in once it is validated
+    #[serde(default)]
+    pub output_schema: Option<Vec<String>>,
 }
@@ -0,0 +1,196 @@
+use super::*;
+use crate::infra::serde::yaml::*;
+
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use csv::StringRecord;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+const BATCH_SIZE: usize = 10_000;
+
+/// Native reader for [ReadStepCsv] that mirrors the semantics of Spark's CSV source
+pub struct CsvReader {
+    reader: csv::Reader<File>,
+    schema: SchemaRef,
+    format: ValueFormat,
+    trim_leading: bool,
+    trim_trailing: bool,
+    // First record that was read ahead to determine the number of columns
+    pending: Option<StringRecord>,
+}
+
+impl CsvReader {
+    /// Checks whether all options used in the read step can be handled natively
+    pub fn supports(conf: &ReadStepCsv) -> bool {
+        let single_byte = |s: &Option<String>| match s {
+            None => true,
+            Some(s) => s.len() <= 1 && s.is_ascii(),
+        };
+
+        let encoding_ok = match conf.encoding {
+            None => true,
+            Some(ref e) => {
+                let e = e.to_lowercase();
+                e == "utf-8" || e == "utf8"
+            }
+        };
+
+        let schema_ok = match conf.schema {
+            None => true,
+            Some(ref s) => parse_ddl_schema(s).is_ok(),
+        };
+
+        let format_ok = |f: &Option<String>| match f {
+            None => true,
+            Some(f) => java_to_chrono_format(f).is_some(),
+        };
+
+        encoding_ok
+            && schema_ok
+            && !conf.infer_schema.unwrap_or(false)
+            && conf.empty_value.is_none()
+            && conf
+                .separator
+                .as_ref()
+                .map(|s| s.len() == 1 && s.is_ascii())
+                .unwrap_or(true)
+            && single_byte(&conf.quote)
+            && single_byte(&conf.escape)
+            && single_byte(&conf.comment)
+            && format_ok(&conf.date_format)
+            && format_ok(&conf.timestamp_format)
+    }
+
+    pub fn new(conf: &ReadStepCsv, path: &Path) -> Result<Self, ReadError> {
+        let first_byte = |s: &Option<String>, default: Option<u8>| match s {
+            None => default,
+            Some(s) => s.bytes().next(),
+        };
+
+        let header = conf.header.unwrap_or(false);
+        let quote = first_byte(&conf.quote, Some(b'"'));
+
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(first_byte(&conf.separator, Some(b',')).unwrap())
+            .quoting(quote.is_some())
+            .quote(quote.unwrap_or(b'"'))
+            .escape(first_byte(&conf.escape, Some(b'\\')))
+            .comment(first_byte(&conf.comment, None))
+            .has_headers(header)
+            .flexible(true)
+            .from_path(path)?;
+
+        let mut pending = StringRecord::new();
+        let pending = if reader.read_record(&mut pending)? {
+            Some(pending)
+        } else {
+            None
+        };
+
+        let schema = match conf.schema {
+            Some(ref ddl) => parse_ddl_schema(ddl)?,
+            None => {
+                let names: Vec<String> = if header {
+                    reader.headers()?.iter().map(|h| h.to_owned()).collect()
+                } else {
+                    let len = pending.as_ref().map(|r| r.len()).unwrap_or(0);
+                    (0..len).map(|i| format!("_c{}", i)).collect()
+                };
+                Schema::new(
+                    names
+                        .into_iter()
+                        .map(|n| Field::new(&n, DataType::Utf8, true))
+                        .collect(),
+                )
+            }
+        };
+
+        let format = ValueFormat {
+            null_value: Some(conf.null_value.clone().unwrap_or_default()),
+            nan_value: conf.nan_value.clone().unwrap_or("NaN".to_owned()),
+            positive_inf: conf.positive_inf.clone().unwrap_or("Inf".to_owned()),
+            negative_inf: conf.negative_inf.clone().unwrap_or("-Inf".to_owned()),
+            date_format: conf
+                .date_format
+                .as_ref()
+                .and_then(|f| java_to_chrono_format(f)),
+            timestamp_format: conf
+                .timestamp_format
+                .as_ref()
+                .and_then(|f| java_to_chrono_format(f)),
+        };
+
+        Ok(Self {
+            reader: reader,
+            schema: Arc::new(schema),
+            format: format,
+            trim_leading: conf.ignore_leading_white_space.unwrap_or(false),
+            trim_trailing: conf.ignore_trailing_white_space.unwrap_or(false),
+            pending: pending,
+        })
+    }
+
+    fn trim<'a>(&self, value: &'a str) -> &'a str {
+        let value = if self.trim_leading {
+            value.trim_start()
+        } else {
+            value
+        };
+        if self.trim_trailing {
+            value.trim_end()
+        } else {
+            value
+        }
+    }
+}
+
+impl NativeReader for CsvReader {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn next_batch(&mut self) -> Result<Option<RecordBatch>, ReadError> {
+        let mut builders: Vec<_> = self
+            .schema
+            .fields()
+            .iter()
+            .map(|f| ColumnBuilder::new(f.data_type(), BATCH_SIZE).unwrap())
+            .collect();
+
+        let mut record = StringRecord::new();
+        let mut num_rows = 0;
+
+        while num_rows < BATCH_SIZE {
+            match self.pending.take() {
+                Some(r) => record = r,
+                None => {
+                    if !self.reader.read_record(&mut record)? {
+                        break;
+                    }
+                }
+            }
+
+            for (i, builder) in builders.iter_mut().enumerate() {
+                let value = record.get(i).map(|v| self.trim(v));
+                builder
+                    .append_str(value, &self.format)
+                    .map_err(|e| ReadError::BadValue {
+                        line: record.position().map(|p| p.line()).unwrap_or(0),
+                        column: self.schema.field(i).name().clone(),
+                        source: e,
+                    })?;
+            }
+
+            num_rows += 1;
+        }
+
+        if num_rows == 0 {
+            return Ok(None);
+        }
+
+        let columns = builders.iter_mut().map(|b| b.finish()).collect();
+        Ok(Some(RecordBatch::try_new(self.schema.clone(), columns)?))
+    }
+}
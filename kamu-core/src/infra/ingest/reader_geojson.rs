@@ -0,0 +1,390 @@
+use super::*;
+use crate::infra::serde::yaml::*;
+
+use ::serde::Deserialize;
+use arrow::array::{ArrayRef, BinaryBuilder, StringBuilder};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use serde_json::{Map, Value};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
+
+const BATCH_SIZE: usize = 10_000;
+const GEOMETRY_COLUMN: &str = "geometry";
+
+/// Native reader for [ReadStepGeoJson] that flattens features into rows.
+///
+/// Feature properties become columns and geometry is stored in the `geometry`
+/// column as WKT, or as WKB if the schema declares that column as `BINARY`.
+pub struct GeoJsonReader {
+    features: std::iter::Enumerate<std::vec::IntoIter<Value>>,
+    schema: SchemaRef,
+    // Schema of the property columns only
+    properties_schema: SchemaRef,
+    // Position and encoding of the geometry column in the output schema
+    geometry: Option<(usize, GeometryEncoding)>,
+    format: ValueFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GeometryEncoding {
+    Wkt,
+    Wkb,
+}
+
+impl GeoJsonReader {
+    pub fn supports(conf: &ReadStepGeoJson) -> bool {
+        match conf.schema {
+            None => true,
+            Some(ref s) => Self::parse_schema(s).is_ok(),
+        }
+    }
+
+    pub fn new(conf: &ReadStepGeoJson, path: &Path) -> Result<Self, ReadError> {
+        let file = BufReader::new(File::open(path)?);
+
+        let features = match serde_json::from_reader(file)? {
+            Value::Object(mut obj) => match obj.get("type").and_then(|t| t.as_str()) {
+                Some("FeatureCollection") => match obj.remove("features") {
+                    Some(Value::Array(features)) => features,
+                    _ => return Err(Self::malformed(0, "features array is missing")),
+                },
+                Some("Feature") => vec![Value::Object(obj)],
+                _ => return Err(Self::malformed(0, "expected Feature or FeatureCollection")),
+            },
+            _ => return Err(Self::malformed(0, "expected a JSON object")),
+        };
+
+        let (properties_schema, geometry) = match conf.schema {
+            Some(ref ddl) => Self::parse_schema(ddl)?,
+            None => {
+                let mut inference = JsonSchemaInference::new(false);
+                for (i, feature) in features.iter().enumerate() {
+                    if let Some(props) = Self::properties(i + 1, feature)? {
+                        inference.add(props);
+                    }
+                }
+                (inference.finish(), Some((0, GeometryEncoding::Wkt)))
+            }
+        };
+
+        let mut fields = properties_schema.fields().clone();
+        if let Some((index, encoding)) = geometry {
+            let data_type = match encoding {
+                GeometryEncoding::Wkt => DataType::Utf8,
+                GeometryEncoding::Wkb => DataType::Binary,
+            };
+            fields.insert(index, Field::new(GEOMETRY_COLUMN, data_type, true));
+        }
+
+        Ok(Self {
+            features: features.into_iter().enumerate(),
+            schema: Arc::new(Schema::new(fields)),
+            properties_schema: Arc::new(properties_schema),
+            geometry: geometry,
+            format: ValueFormat {
+                null_value: None,
+                ..ValueFormat::default()
+            },
+        })
+    }
+
+    // Splits the geometry column out of the DDL schema
+    fn parse_schema(
+        ddl: &[String],
+    ) -> Result<(Schema, Option<(usize, GeometryEncoding)>), DdlError> {
+        let mut fields = Vec::new();
+        let mut geometry = None;
+
+        for (i, column) in ddl.iter().enumerate() {
+            let (name, type_str) = split_ddl_column(column)?;
+            if name != GEOMETRY_COLUMN {
+                fields.push(parse_ddl_column(column)?);
+                continue;
+            }
+            let encoding = match &type_str.to_uppercase()[..] {
+                "GEOMETRY" | "STRING" => GeometryEncoding::Wkt,
+                "BINARY" => GeometryEncoding::Wkb,
+                _ => {
+                    return Err(DdlError::new(
+                        column,
+                        "geometry column must be GEOMETRY, STRING or BINARY",
+                    ))
+                }
+            };
+            geometry = Some((i, encoding));
+        }
+
+        Ok((Schema::new(fields), geometry))
+    }
+
+    fn properties(index: usize, feature: &Value) -> Result<Option<&Map<String, Value>>, ReadError> {
+        match feature.get("properties") {
+            None | Some(Value::Null) => Ok(None),
+            Some(Value::Object(props)) => Ok(Some(props)),
+            Some(_) => Err(Self::malformed(index, "properties must be an object")),
+        }
+    }
+
+    fn malformed(index: usize, reason: &str) -> ReadError {
+        ReadError::MalformedRecord {
+            line: index as u64,
+            reason: reason.to_owned(),
+        }
+    }
+}
+
+impl NativeReader for GeoJsonReader {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn next_batch(&mut self) -> Result<Option<RecordBatch>, ReadError> {
+        let empty = Map::new();
+        let mut batch = JsonBatchBuilder::new(self.properties_schema.clone(), BATCH_SIZE);
+        let mut wkt = StringBuilder::new(BATCH_SIZE);
+        let mut wkb = BinaryBuilder::new(BATCH_SIZE);
+
+        while batch.len() < BATCH_SIZE {
+            let (i, feature) = match self.features.next() {
+                None => break,
+                Some(f) => f,
+            };
+
+            // Features are numbered from 1 to match line numbers of other readers
+            let props = Self::properties(i + 1, &feature)?.unwrap_or(&empty);
+            batch.append(i as u64 + 1, props, &self.format)?;
+
+            let geometry = match feature.get("geometry") {
+                None | Some(Value::Null) => None,
+                Some(g) => Some(
+                    Geometry::deserialize(g).map_err(|e| Self::malformed(i + 1, &e.to_string()))?,
+                ),
+            };
+
+            match (self.geometry, geometry) {
+                (None, _) => (),
+                (Some(_), None) => {
+                    wkt.append_null().unwrap();
+                    wkb.append_null().unwrap();
+                }
+                (Some((_, GeometryEncoding::Wkt)), Some(g)) => {
+                    wkt.append_value(&g.to_wkt()).unwrap()
+                }
+                (Some((_, GeometryEncoding::Wkb)), Some(g)) => {
+                    wkb.append_value(&g.to_wkb()).unwrap()
+                }
+            }
+        }
+
+        if batch.len() == 0 {
+            return Ok(None);
+        }
+
+        let mut columns = batch.finish()?.columns().to_vec();
+        if let Some((index, encoding)) = self.geometry {
+            let array: ArrayRef = match encoding {
+                GeometryEncoding::Wkt => Arc::new(wkt.finish()),
+                GeometryEncoding::Wkb => Arc::new(wkb.finish()),
+            };
+            columns.insert(index, array);
+        }
+
+        Ok(Some(RecordBatch::try_new(self.schema.clone(), columns)?))
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Geometry
+///////////////////////////////////////////////////////////////////////////////
+
+type Coord = Vec<f64>;
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+enum Geometry {
+    Point { coordinates: Coord },
+    MultiPoint { coordinates: Vec<Coord> },
+    LineString { coordinates: Vec<Coord> },
+    MultiLineString { coordinates: Vec<Vec<Coord>> },
+    Polygon { coordinates: Vec<Vec<Coord>> },
+    MultiPolygon { coordinates: Vec<Vec<Vec<Coord>>> },
+    GeometryCollection { geometries: Vec<Geometry> },
+}
+
+impl Geometry {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Point { .. } => "POINT",
+            Self::MultiPoint { .. } => "MULTIPOINT",
+            Self::LineString { .. } => "LINESTRING",
+            Self::MultiLineString { .. } => "MULTILINESTRING",
+            Self::Polygon { .. } => "POLYGON",
+            Self::MultiPolygon { .. } => "MULTIPOLYGON",
+            Self::GeometryCollection { .. } => "GEOMETRYCOLLECTION",
+        }
+    }
+
+    fn wkb_type(&self) -> u32 {
+        match self {
+            Self::Point { .. } => 1,
+            Self::LineString { .. } => 2,
+            Self::Polygon { .. } => 3,
+            Self::MultiPoint { .. } => 4,
+            Self::MultiLineString { .. } => 5,
+            Self::MultiPolygon { .. } => 6,
+            Self::GeometryCollection { .. } => 7,
+        }
+    }
+
+    fn first_coord(&self) -> Option<&Coord> {
+        match self {
+            Self::Point { coordinates } if coordinates.is_empty() => None,
+            Self::Point { coordinates } => Some(coordinates),
+            Self::MultiPoint { coordinates } | Self::LineString { coordinates } => {
+                coordinates.first()
+            }
+            Self::MultiLineString { coordinates } | Self::Polygon { coordinates } => {
+                coordinates.iter().flatten().next()
+            }
+            Self::MultiPolygon { coordinates } => coordinates.iter().flatten().flatten().next(),
+            Self::GeometryCollection { geometries } => {
+                geometries.iter().filter_map(|g| g.first_coord()).next()
+            }
+        }
+    }
+
+    fn has_z(&self) -> bool {
+        self.first_coord().map(|c| c.len() > 2).unwrap_or(false)
+    }
+
+    ///////////////////////////////////////////////////////////////////////////
+
+    fn to_wkt(&self) -> String {
+        let mut s = self.name().to_owned();
+        if self.has_z() {
+            s.push_str(" Z");
+        }
+        if self.first_coord().is_none() {
+            s.push_str(" EMPTY");
+            return s;
+        }
+        s.push(' ');
+
+        let coord = |c: &Coord| {
+            c.iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let list =
+            |cs: &Vec<Coord>| format!("({})", cs.iter().map(coord).collect::<Vec<_>>().join(", "));
+        let rings = |rs: &Vec<Vec<Coord>>| {
+            format!("({})", rs.iter().map(list).collect::<Vec<_>>().join(", "))
+        };
+
+        let body = match self {
+            Self::Point { coordinates } => format!("({})", coord(coordinates)),
+            Self::MultiPoint { coordinates } => format!(
+                "({})",
+                coordinates
+                    .iter()
+                    .map(|c| format!("({})", coord(c)))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Self::LineString { coordinates } => list(coordinates),
+            Self::MultiLineString { coordinates } | Self::Polygon { coordinates } => {
+                rings(coordinates)
+            }
+            Self::MultiPolygon { coordinates } => format!(
+                "({})",
+                coordinates.iter().map(rings).collect::<Vec<_>>().join(", ")
+            ),
+            Self::GeometryCollection { geometries } => format!(
+                "({})",
+                geometries
+                    .iter()
+                    .map(|g| g.to_wkt())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+
+        s.push_str(&body);
+        s
+    }
+
+    ///////////////////////////////////////////////////////////////////////////
+
+    fn to_wkb(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.write_wkb(&mut buf, if self.has_z() { 3 } else { 2 });
+        buf
+    }
+
+    // Uses little-endian ISO WKB, with Z variants of types for 3D geometries
+    fn write_wkb(&self, buf: &mut Vec<u8>, dims: usize) {
+        let write_u32 = |buf: &mut Vec<u8>, v: u32| buf.extend_from_slice(&v.to_le_bytes());
+        let write_coord = |buf: &mut Vec<u8>, c: &Coord| {
+            for i in 0..dims {
+                let v = c.get(i).cloned().unwrap_or(std::f64::NAN);
+                buf.extend_from_slice(&v.to_le_bytes());
+            }
+        };
+        let write_list = |buf: &mut Vec<u8>, cs: &Vec<Coord>| {
+            write_u32(buf, cs.len() as u32);
+            for c in cs {
+                write_coord(buf, c);
+            }
+        };
+        let write_rings = |buf: &mut Vec<u8>, rs: &Vec<Vec<Coord>>| {
+            write_u32(buf, rs.len() as u32);
+            for r in rs {
+                write_list(buf, r);
+            }
+        };
+        let write_header = |buf: &mut Vec<u8>, wkb_type: u32| {
+            buf.push(1);
+            write_u32(buf, if dims == 3 { wkb_type + 1000 } else { wkb_type });
+        };
+
+        write_header(buf, self.wkb_type());
+
+        match self {
+            // Empty points are encoded with NaN coordinates
+            Self::Point { coordinates } => write_coord(buf, coordinates),
+            Self::LineString { coordinates } => write_list(buf, coordinates),
+            Self::Polygon { coordinates } => write_rings(buf, coordinates),
+            Self::MultiPoint { coordinates } => {
+                write_u32(buf, coordinates.len() as u32);
+                for c in coordinates {
+                    write_header(buf, 1);
+                    write_coord(buf, c);
+                }
+            }
+            Self::MultiLineString { coordinates } => {
+                write_u32(buf, coordinates.len() as u32);
+                for cs in coordinates {
+                    write_header(buf, 2);
+                    write_list(buf, cs);
+                }
+            }
+            Self::MultiPolygon { coordinates } => {
+                write_u32(buf, coordinates.len() as u32);
+                for rs in coordinates {
+                    write_header(buf, 3);
+                    write_rings(buf, rs);
+                }
+            }
+            Self::GeometryCollection { geometries } => {
+                write_u32(buf, geometries.len() as u32);
+                for g in geometries {
+                    g.write_wkb(buf, dims);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use super::*;
+use crate::infra::serde::yaml::*;
+
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use serde_json::{Map, Value};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+
+const BATCH_SIZE: usize = 10_000;
+
+type JsonRecords = Box<dyn Iterator<Item = Result<(u64, Value), ReadError>>>;
+
+/// Native reader for [ReadStepJsonLines]
+pub struct JsonLinesReader {
+    records: JsonRecords,
+    schema: SchemaRef,
+    format: ValueFormat,
+}
+
+impl JsonLinesReader {
+    pub fn supports(conf: &ReadStepJsonLines) -> bool {
+        let encoding_ok = match conf.encoding {
+            None => true,
+            Some(ref e) => {
+                let e = e.to_lowercase();
+                e == "utf-8" || e == "utf8"
+            }
+        };
+
+        let schema_ok = match conf.schema {
+            None => true,
+            Some(ref s) => parse_ddl_schema(s).is_ok(),
+        };
+
+        let format_ok = |f: &Option<String>| match f {
+            None => true,
+            Some(f) => java_to_chrono_format(f).is_some(),
+        };
+
+        encoding_ok
+            && schema_ok
+            && format_ok(&conf.date_format)
+            && format_ok(&conf.timestamp_format)
+    }
+
+    pub fn new(conf: &ReadStepJsonLines, path: &Path) -> Result<Self, ReadError> {
+        let multi_line = conf.multi_line.unwrap_or(false);
+
+        let schema = match conf.schema {
+            Some(ref ddl) => parse_ddl_schema(ddl)?,
+            None => {
+                // Inference requires an extra pass over the data
+                let mut inference =
+                    JsonSchemaInference::new(conf.primitives_as_string.unwrap_or(false));
+                for res in Self::open_records(path, multi_line)? {
+                    let (line, record) = res?;
+                    inference.add(as_object(line, &record)?);
+                }
+                inference.finish()
+            }
+        };
+
+        Ok(Self {
+            records: Self::open_records(path, multi_line)?,
+            schema: Arc::new(schema),
+            format: ValueFormat {
+                null_value: None,
+                date_format: conf
+                    .date_format
+                    .as_ref()
+                    .and_then(|f| java_to_chrono_format(f)),
+                timestamp_format: conf
+                    .timestamp_format
+                    .as_ref()
+                    .and_then(|f| java_to_chrono_format(f)),
+                ..ValueFormat::default()
+            },
+        })
+    }
+
+    // In multi-line mode the entire file is a single JSON document that
+    // contains either one record or an array of records
+    fn open_records(path: &Path, multi_line: bool) -> Result<JsonRecords, ReadError> {
+        let file = BufReader::new(File::open(path)?);
+
+        if multi_line {
+            let records = match serde_json::from_reader(file)? {
+                Value::Array(values) => values,
+                value => vec![value],
+            };
+            Ok(Box::new(
+                records
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, v)| Ok((i as u64 + 1, v))),
+            ))
+        } else {
+            Ok(Box::new(
+                file.lines()
+                    .enumerate()
+                    .filter(|(_, l)| match l {
+                        Ok(l) => !l.trim().is_empty(),
+                        Err(_) => true,
+                    })
+                    .map(|(i, l)| -> Result<(u64, Value), ReadError> {
+                        Ok((i as u64 + 1, serde_json::from_str(&l?)?))
+                    }),
+            ))
+        }
+    }
+}
+
+impl NativeReader for JsonLinesReader {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn next_batch(&mut self) -> Result<Option<RecordBatch>, ReadError> {
+        let mut batch = JsonBatchBuilder::new(self.schema.clone(), BATCH_SIZE);
+
+        while batch.len() < BATCH_SIZE {
+            match self.records.next() {
+                None => break,
+                Some(res) => {
+                    let (line, record) = res?;
+                    batch.append(line, as_object(line, &record)?, &self.format)?;
+                }
+            }
+        }
+
+        if batch.len() == 0 {
+            Ok(None)
+        } else {
+            Ok(Some(batch.finish()?))
+        }
+    }
+}
+
+pub(crate) fn as_object(line: u64, value: &Value) -> Result<&Map<String, Value>, ReadError> {
+    value.as_object().ok_or_else(|| ReadError::MalformedRecord {
+        line: line,
+        reason: "expected a JSON object".to_owned(),
+    })
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// JsonSchemaInference
+///////////////////////////////////////////////////////////////////////////////
+
+/// Infers flat schema from JSON objects, nested values are kept as JSON strings
+pub(crate) struct JsonSchemaInference {
+    primitives_as_string: bool,
+    columns: Vec<(String, Option<DataType>)>,
+}
+
+impl JsonSchemaInference {
+    pub fn new(primitives_as_string: bool) -> Self {
+        Self {
+            primitives_as_string: primitives_as_string,
+            columns: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, record: &Map<String, Value>) {
+        for (name, value) in record {
+            let value_type = self.value_type(value);
+
+            match self.columns.iter_mut().find(|(n, _)| n == name) {
+                None => self.columns.push((name.clone(), value_type)),
+                Some((_, column_type)) => {
+                    *column_type = match (column_type.take(), value_type) {
+                        (None, t) | (t, None) => t,
+                        (Some(a), Some(b)) if a == b => Some(a),
+                        (Some(DataType::Int64), Some(DataType::Float64))
+                        | (Some(DataType::Float64), Some(DataType::Int64)) => {
+                            Some(DataType::Float64)
+                        }
+                        _ => Some(DataType::Utf8),
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn finish(self) -> Schema {
+        Schema::new(
+            self.columns
+                .into_iter()
+                .map(|(name, t)| Field::new(&name, t.unwrap_or(DataType::Utf8), true))
+                .collect(),
+        )
+    }
+
+    fn value_type(&self, value: &Value) -> Option<DataType> {
+        match value {
+            Value::Null => None,
+            _ if self.primitives_as_string => Some(DataType::Utf8),
+            Value::Bool(_) => Some(DataType::Boolean),
+            Value::Number(n) if n.is_i64() => Some(DataType::Int64),
+            Value::Number(_) => Some(DataType::Float64),
+            Value::String(_) | Value::Array(_) | Value::Object(_) => Some(DataType::Utf8),
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// JsonBatchBuilder
+///////////////////////////////////////////////////////////////////////////////
+
+pub(crate) struct JsonBatchBuilder {
+    schema: SchemaRef,
+    builders: Vec<ColumnBuilder>,
+    len: usize,
+}
+
+impl JsonBatchBuilder {
+    pub fn new(schema: SchemaRef, capacity: usize) -> Self {
+        let builders = schema
+            .fields()
+            .iter()
+            .map(|f| ColumnBuilder::new(f.data_type(), capacity).unwrap())
+            .collect();
+
+        Self {
+            schema: schema,
+            builders: builders,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn append(
+        &mut self,
+        line: u64,
+        record: &Map<String, Value>,
+        format: &ValueFormat,
+    ) -> Result<(), ReadError> {
+        for (field, builder) in self.schema.fields().iter().zip(self.builders.iter_mut()) {
+            let res = match record.get(field.name()) {
+                None | Some(Value::Null) => {
+                    builder.append_null();
+                    Ok(())
+                }
+                Some(Value::String(s)) => builder.append_str(Some(s), format),
+                Some(v) => builder.append_str(Some(&v.to_string()), format),
+            };
+
+            res.map_err(|e| ReadError::BadValue {
+                line: line,
+                column: field.name().clone(),
+                source: e,
+            })?;
+        }
+
+        self.len += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<RecordBatch, ReadError> {
+        let columns = self.builders.iter_mut().map(|b| b.finish()).collect();
+        Ok(RecordBatch::try_new(self.schema, columns)?)
+    }
+}
@@ -0,0 +1,106 @@
+use arrow::datatypes::{DataType, DateUnit, Field, Schema, TimeUnit};
+use thiserror::Error;
+
+/// Parses Spark-style DDL column definitions (e.g. `"date TIMESTAMP"`)
+/// that are used in the `schema` property of read steps
+pub fn parse_ddl_schema<S: AsRef<str>>(columns: &[S]) -> Result<Schema, DdlError> {
+    let fields = columns
+        .iter()
+        .map(|c| parse_ddl_column(c.as_ref()))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Schema::new(fields))
+}
+
+pub fn parse_ddl_column(column: &str) -> Result<Field, DdlError> {
+    let (name, type_str) = split_ddl_column(column)?;
+
+    let data_type =
+        parse_ddl_type(type_str).ok_or_else(|| DdlError::new(column.trim(), "unsupported type"))?;
+
+    Ok(Field::new(name, data_type, true))
+}
+
+/// Splits column definition into name and type parts
+pub fn split_ddl_column(column: &str) -> Result<(&str, &str), DdlError> {
+    let column = column.trim();
+
+    let (name, type_str) = if column.starts_with('`') {
+        match column[1..].find('`') {
+            Some(end) => (&column[1..end + 1], &column[end + 2..]),
+            None => return Err(DdlError::new(column, "unterminated quoted name")),
+        }
+    } else {
+        match column.find(char::is_whitespace) {
+            Some(sep) => (&column[..sep], &column[sep..]),
+            None => return Err(DdlError::new(column, "expected `<name> <type>`")),
+        }
+    };
+
+    Ok((name, type_str.trim()))
+}
+
+pub fn parse_ddl_type(type_str: &str) -> Option<DataType> {
+    match &type_str.to_uppercase()[..] {
+        "STRING" | "VARCHAR" | "TEXT" => Some(DataType::Utf8),
+        "BINARY" => Some(DataType::Binary),
+        "BOOLEAN" | "BOOL" => Some(DataType::Boolean),
+        "TINYINT" | "BYTE" => Some(DataType::Int8),
+        "SMALLINT" | "SHORT" => Some(DataType::Int16),
+        "INT" | "INTEGER" => Some(DataType::Int32),
+        "BIGINT" | "LONG" => Some(DataType::Int64),
+        "FLOAT" | "REAL" => Some(DataType::Float32),
+        "DOUBLE" => Some(DataType::Float64),
+        "DATE" => Some(DataType::Date32(DateUnit::Day)),
+        "TIMESTAMP" => Some(DataType::Timestamp(TimeUnit::Millisecond, None)),
+        _ => None,
+    }
+}
+
+/// Formats the schema as DDL column definitions, an inverse of [parse_ddl_schema]
+pub fn format_ddl_schema(schema: &Schema) -> Vec<String> {
+    schema.fields().iter().map(format_ddl_column).collect()
+}
+
+pub fn format_ddl_column(field: &Field) -> String {
+    let name = field.name();
+    let type_str = format_ddl_type(field.data_type());
+    if name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        format!("{} {}", name, type_str)
+    } else {
+        format!("`{}` {}", name, type_str)
+    }
+}
+
+/// Types that have no DDL equivalent are formatted using their Arrow name
+pub fn format_ddl_type(data_type: &DataType) -> String {
+    match data_type {
+        DataType::Utf8 | DataType::LargeUtf8 => "STRING".to_owned(),
+        DataType::Binary | DataType::LargeBinary => "BINARY".to_owned(),
+        DataType::Boolean => "BOOLEAN".to_owned(),
+        DataType::Int8 => "TINYINT".to_owned(),
+        DataType::Int16 => "SMALLINT".to_owned(),
+        DataType::Int32 => "INT".to_owned(),
+        DataType::Int64 => "BIGINT".to_owned(),
+        DataType::Float32 => "FLOAT".to_owned(),
+        DataType::Float64 => "DOUBLE".to_owned(),
+        DataType::Date32(_) => "DATE".to_owned(),
+        DataType::Timestamp(_, _) => "TIMESTAMP".to_owned(),
+        other => format!("{:?}", other),
+    }
+}
+
+#[derive(Error, Debug)]
+#[error("Invalid schema column definition '{column}': {reason}")]
+pub struct DdlError {
+    pub column: String,
+    pub reason: String,
+}
+
+impl DdlError {
+    pub(crate) fn new(column: &str, reason: &str) -> Self {
+        Self {
+            column: column.to_owned(),
+            reason: reason.to_owned(),
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use super::*;
+use crate::domain::SchemaChange;
+use crate::infra::serde::yaml::*;
+
+/// Compares two schemas in DDL form column by column. Columns are matched by
+/// name, so reordering columns is not considered a change.
+pub fn diff_ddl_schemas<S: AsRef<str>>(
+    prev: &[S],
+    new: &[S],
+) -> Result<Vec<SchemaChange>, DdlError> {
+    let prev = split_ddl_columns(prev)?;
+    let new = split_ddl_columns(new)?;
+
+    let mut changes = Vec::new();
+
+    for (name, prev_type) in prev.iter() {
+        match new.iter().find(|(n, _)| n == name) {
+            None => changes.push(SchemaChange::ColumnRemoved {
+                name: name.clone(),
+                data_type: prev_type.clone(),
+            }),
+            Some((_, new_type)) if new_type != prev_type => {
+                changes.push(SchemaChange::TypeChanged {
+                    name: name.clone(),
+                    old_type: prev_type.clone(),
+                    new_type: new_type.clone(),
+                })
+            }
+            _ => {}
+        }
+    }
+
+    for (name, new_type) in new.iter() {
+        if !prev.iter().any(|(n, _)| n == name) {
+            changes.push(SchemaChange::ColumnAdded {
+                name: name.clone(),
+                data_type: new_type.clone(),
+            });
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Determines how to react to the schema change, warning by default
+pub fn schema_change_action(
+    policy: Option<&SchemaPolicy>,
+    change: &SchemaChange,
+) -> SchemaChangeAction {
+    let action = policy.and_then(|p| match change {
+        SchemaChange::ColumnAdded { .. } => p.on_column_added,
+        SchemaChange::ColumnRemoved { .. } => p.on_column_removed,
+        SchemaChange::TypeChanged { .. } => p.on_type_changed,
+    });
+    action.unwrap_or(SchemaChangeAction::Warn)
+}
+
+fn split_ddl_columns<S: AsRef<str>>(columns: &[S]) -> Result<Vec<(String, String)>, DdlError> {
+    columns
+        .iter()
+        .map(|c| {
+            let (name, type_str) = split_ddl_column(c.as_ref())?;
+            Ok((name.to_owned(), type_str.to_uppercase()))
+        })
+        .collect()
+}
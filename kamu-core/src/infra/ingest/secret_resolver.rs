@@ -0,0 +1,91 @@
+use regex::{Captures, Regex};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Expands secret references in fetch step values so that credentials
+/// never have to be stored in the metadata chain.
+///
+/// Supported references:
+/// - `${{ env.NAME }}` - value of the environment variable `NAME`
+/// - `${{ secrets.NAME }}` - contents of the file `NAME` in the secrets directory
+#[derive(Debug, Clone)]
+pub struct SecretResolver {
+    secrets_dir: Option<PathBuf>,
+    re: Regex,
+}
+
+impl SecretResolver {
+    pub fn new(secrets_dir: Option<&Path>) -> Self {
+        Self {
+            secrets_dir: secrets_dir.map(|p| p.to_owned()),
+            re: Regex::new(r"\$\{\{\s*([a-zA-Z_]+)\.([a-zA-Z0-9_\-]+)\s*\}\}").unwrap(),
+        }
+    }
+
+    pub fn resolve(&self, value: &str) -> Result<String, SecretError> {
+        let mut error = None;
+        let resolved = self.re.replace_all(value, |caps: &Captures| {
+            match self.resolve_ref(&caps[1], &caps[2]) {
+                Ok(v) => v,
+                Err(e) => {
+                    error.get_or_insert(e);
+                    String::new()
+                }
+            }
+        });
+
+        match error {
+            Some(e) => Err(e),
+            None => Ok(resolved.into_owned()),
+        }
+    }
+
+    fn resolve_ref(&self, scope: &str, name: &str) -> Result<String, SecretError> {
+        match scope {
+            "env" => std::env::var(name).map_err(|_| SecretError::not_found(scope, name)),
+            "secrets" => {
+                let path = match self.secrets_dir {
+                    Some(ref dir) => dir.join(name),
+                    None => return Err(SecretError::not_found(scope, name)),
+                };
+                match std::fs::read_to_string(&path) {
+                    // Editors like to leave a trailing newline
+                    Ok(s) => Ok(s.trim_end_matches(&['\r', '\n'][..]).to_owned()),
+                    Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => {
+                        Err(SecretError::not_found(scope, name))
+                    }
+                    Err(e) => Err(SecretError::IOError {
+                        name: name.to_owned(),
+                        source: e,
+                    }),
+                }
+            }
+            _ => Err(SecretError::UnknownScope {
+                scope: scope.to_owned(),
+            }),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum SecretError {
+    #[error("Secret {scope}.{name} is not defined")]
+    NotFound { scope: String, name: String },
+    #[error("Unknown secret scope {scope}, expected env or secrets")]
+    UnknownScope { scope: String },
+    #[error("Failed to read secret {name}")]
+    IOError {
+        name: String,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+impl SecretError {
+    fn not_found(scope: &str, name: &str) -> Self {
+        SecretError::NotFound {
+            scope: scope.to_owned(),
+            name: name.to_owned(),
+        }
+    }
+}
@@ -1,23 +1,31 @@
 use super::ingest::*;
 use crate::domain::*;
+use crate::infra::serde::yaml::*;
+use crate::infra::utils::worker_pool::*;
 use crate::infra::*;
 
+use chrono::{DateTime, Utc};
 use slog::{info, o, Logger};
 use std::cell::RefCell;
+use std::io::Read;
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 
 pub struct IngestServiceImpl {
     volume_layout: VolumeLayout,
     metadata_repo: Rc<RefCell<dyn MetadataRepository>>,
-    engine_factory: Arc<Mutex<EngineFactory>>,
+    engine_factory: Arc<EngineFactory>,
+    fetch_options: FetchOptions,
+    concurrency: ConcurrencyOptions,
+    cancel: CancellationToken,
     logger: Logger,
 }
 
 impl IngestServiceImpl {
     pub fn new(
         metadata_repo: Rc<RefCell<dyn MetadataRepository>>,
-        engine_factory: Arc<Mutex<EngineFactory>>,
+        engine_factory: Arc<EngineFactory>,
+        workspace_layout: &WorkspaceLayout,
         volume_layout: &VolumeLayout,
         logger: Logger,
     ) -> Self {
@@ -25,10 +33,31 @@ impl IngestServiceImpl {
             volume_layout: volume_layout.clone(),
             metadata_repo: metadata_repo,
             engine_factory: engine_factory,
+            fetch_options: FetchOptions {
+                secrets_dir: Some(workspace_layout.secrets_dir.clone()),
+                ..FetchOptions::default()
+            },
+            concurrency: ConcurrencyOptions::default(),
+            cancel: CancellationToken::new(),
             logger: logger,
         }
     }
 
+    pub fn with_concurrency(self, concurrency: ConcurrencyOptions) -> Self {
+        Self {
+            concurrency: concurrency,
+            ..self
+        }
+    }
+
+    /// Stops ingestion at the next safe point once the token gets cancelled
+    pub fn with_cancellation(self, cancel: CancellationToken) -> Self {
+        Self {
+            cancel: cancel,
+            ..self
+        }
+    }
+
     // TODO: error handling
     fn get_dataset_layout(&self, dataset_id: &DatasetID) -> DatasetLayout {
         DatasetLayout::create(&self.volume_layout, dataset_id).unwrap()
@@ -40,8 +69,8 @@ impl IngestServiceImpl {
         result: &IngestResult,
     ) -> Result<(), IngestError> {
         match result {
-            IngestResult::UpToDate => Ok(()),
-            IngestResult::Updated { block_hash } => {
+            IngestResult::UpToDate { .. } => Ok(()),
+            IngestResult::Updated { block_hash, stats } => {
                 let mut metadata_repo = self.metadata_repo.borrow_mut();
 
                 let mut summary = metadata_repo
@@ -61,6 +90,19 @@ impl IngestServiceImpl {
 
                 summary.last_pulled = Some(block.system_time);
 
+                if let Some(ref schema) = stats.output_schema {
+                    metadata_repo
+                        .record_schema(
+                            dataset_id,
+                            SchemaRecord {
+                                block_hash: block_hash.clone(),
+                                system_time: block.system_time,
+                                schema: schema.clone(),
+                            },
+                        )
+                        .map_err(|e| IngestError::internal(e))?;
+                }
+
                 let layout = DatasetLayout::new(&self.volume_layout, dataset_id);
                 summary.data_size = fs_extra::dir::get_size(layout.data_dir).unwrap_or(0);
                 summary.data_size += fs_extra::dir::get_size(layout.checkpoints_dir).unwrap_or(0);
@@ -98,6 +140,19 @@ impl IngestService for IngestServiceImpl {
             .unwrap()
             .vocab;
 
+        let config = self
+            .metadata_repo
+            .borrow()
+            .get_config(dataset_id)
+            .map_err(|e| IngestError::internal(e))?;
+        let prev_schema = self
+            .metadata_repo
+            .borrow()
+            .get_schema_history(dataset_id)
+            .map_err(|e| IngestError::internal(e))?
+            .pop()
+            .map(|r| r.schema);
+
         let layout = self.get_dataset_layout(dataset_id);
 
         let logger = self.logger.new(o!("dataset" => dataset_id.to_string()));
@@ -107,10 +162,14 @@ impl IngestService for IngestServiceImpl {
             layout,
             meta_chain,
             vocab,
+            config,
+            prev_schema,
             listener,
             self.engine_factory.clone(),
+            self.fetch_options.clone(),
+            self.cancel.clone(),
             logger,
-        );
+        )?;
 
         let result = ingest_task.ingest()?;
         self.update_summary(dataset_id, &result)?;
@@ -122,63 +181,291 @@ impl IngestService for IngestServiceImpl {
         dataset_ids: &mut dyn Iterator<Item = &DatasetID>,
         maybe_multi_listener: Option<Arc<Mutex<dyn IngestMultiListener>>>,
     ) -> Vec<(DatasetIDBuf, Result<IngestResult, IngestError>)> {
-        let null_multi_listener: Arc<Mutex<dyn IngestMultiListener>> =
-            Arc::new(Mutex::new(NullIngestMultiListener {}));
-        let multi_listener = maybe_multi_listener.unwrap_or(null_multi_listener);
-
         let dataset_ids_owned: Vec<_> = dataset_ids.map(|id| id.to_owned()).collect();
         info!(self.logger, "Ingesting multiple datasets"; "datasets" => ?dataset_ids_owned);
 
-        let thread_handles: Vec<_> = dataset_ids_owned
-            .into_iter()
-            .map(|id| {
-                let layout = self.get_dataset_layout(&id);
-                let meta_chain = self.metadata_repo.borrow().get_metadata_chain(&id).unwrap();
-                let vocab = self.metadata_repo.borrow().get_summary(&id).unwrap().vocab;
-                let engine_factory = self.engine_factory.clone();
-
-                let null_listener = Arc::new(Mutex::new(NullIngestListener {}));
-                let listener = multi_listener
-                    .lock()
-                    .unwrap()
-                    .begin_ingest(&id)
-                    .unwrap_or(null_listener);
-
-                let logger = self.logger.new(o!("dataset" => id.to_string()));
-
-                std::thread::Builder::new()
-                    .name("ingest_multi".to_owned())
-                    .spawn(move || {
-                        let mut ingest_task = IngestTask::new(
-                            &id,
-                            layout,
-                            meta_chain,
-                            vocab,
-                            listener,
-                            engine_factory,
-                            logger,
-                        );
-
-                        let res = ingest_task.ingest();
-                        (id, res)
-                    })
-                    .unwrap()
-            })
+        let tasks: Vec<_> = dataset_ids_owned
+            .iter()
+            .map(
+                |id| match self.prepare_ingest(id, maybe_multi_listener.clone()) {
+                    Ok(job) => (job.engine, job.run),
+                    Err(err) => (None, Box::new(move || Err(err)) as Task<_>),
+                },
+            )
             .collect();
 
-        let results: Vec<_> = thread_handles
+        let results = run_bounded(tasks, &self.concurrency, "ingest_multi");
+
+        dataset_ids_owned
             .into_iter()
-            .map(|h| h.join().unwrap())
-            .collect();
+            .zip(results)
+            .map(|(id, res)| {
+                let res = self.complete_ingest(&id, res);
+                (id, res)
+            })
+            .collect()
+    }
 
-        results
-            .iter()
-            .filter(|(_, res)| res.is_ok())
-            .for_each(|(dataset_id, res)| {
-                self.update_summary(dataset_id, res.as_ref().unwrap())
-                    .unwrap()
-            });
+    fn prepare_ingest(
+        &mut self,
+        dataset_id: &DatasetID,
+        maybe_multi_listener: Option<Arc<Mutex<dyn IngestMultiListener>>>,
+    ) -> Result<IngestJob, IngestError> {
+        let null_multi_listener: Arc<Mutex<dyn IngestMultiListener>> =
+            Arc::new(Mutex::new(NullIngestMultiListener {}));
+        let multi_listener = maybe_multi_listener.unwrap_or(null_multi_listener);
+
+        let id = dataset_id.to_owned();
+        let layout = self.get_dataset_layout(&id);
+        let meta_chain = self
+            .metadata_repo
+            .borrow()
+            .get_metadata_chain(&id)
+            .map_err(|e| IngestError::internal(e))?;
+        let vocab = self
+            .metadata_repo
+            .borrow()
+            .get_summary(&id)
+            .map_err(|e| IngestError::internal(e))?
+            .vocab;
+        let config = self
+            .metadata_repo
+            .borrow()
+            .get_config(&id)
+            .map_err(|e| IngestError::internal(e))?;
+        let prev_schema = self
+            .metadata_repo
+            .borrow()
+            .get_schema_history(&id)
+            .map_err(|e| IngestError::internal(e))?
+            .pop()
+            .map(|r| r.schema);
+        let engine_factory = self.engine_factory.clone();
+        let fetch_options = self.fetch_options.clone();
+        let cancel = self.cancel.clone();
+
+        // Engine is only involved when data needs preprocessing
+        let engine = match meta_chain.iter_blocks().filter_map(|b| b.source).next() {
+            Some(DatasetSource::Root(src)) => src.preprocess.map(|p| p.engine),
+            _ => None,
+        };
+
+        let null_listener = Arc::new(Mutex::new(NullIngestListener {}));
+        let listener = multi_listener
+            .lock()
+            .unwrap()
+            .begin_ingest(&id)
+            .unwrap_or(null_listener);
+
+        let logger = self.logger.new(o!("dataset" => id.to_string()));
+
+        Ok(IngestJob {
+            engine: engine,
+            run: Box::new(move || {
+                let mut ingest_task = IngestTask::new(
+                    &id,
+                    layout,
+                    meta_chain,
+                    vocab,
+                    config,
+                    prev_schema,
+                    listener,
+                    engine_factory,
+                    fetch_options,
+                    cancel,
+                    logger,
+                )?;
+
+                ingest_task.ingest()
+            }),
+        })
+    }
+
+    fn complete_ingest(
+        &mut self,
+        dataset_id: &DatasetID,
+        result: Result<IngestResult, IngestError>,
+    ) -> Result<IngestResult, IngestError> {
+        let result = result?;
+        self.update_summary(dataset_id, &result)?;
+        Ok(result)
+    }
+
+    fn plan_ingest(&mut self, dataset_id: &DatasetID) -> Result<IngestPlan, IngestError> {
+        let meta_chain = self
+            .metadata_repo
+            .borrow()
+            .get_metadata_chain(dataset_id)
+            .map_err(|e| IngestError::internal(e))?;
+
+        let vocab = self
+            .metadata_repo
+            .borrow()
+            .get_summary(dataset_id)
+            .map_err(|e| IngestError::internal(e))?
+            .vocab;
+
+        let config = self
+            .metadata_repo
+            .borrow()
+            .get_config(dataset_id)
+            .map_err(|e| IngestError::internal(e))?;
+        let prev_schema = self
+            .metadata_repo
+            .borrow()
+            .get_schema_history(dataset_id)
+            .map_err(|e| IngestError::internal(e))?
+            .pop()
+            .map(|r| r.schema);
+
+        let ingest_task = IngestTask::new(
+            dataset_id,
+            self.get_dataset_layout(dataset_id),
+            meta_chain,
+            vocab,
+            config,
+            prev_schema,
+            Arc::new(Mutex::new(NullIngestListener {})),
+            self.engine_factory.clone(),
+            self.fetch_options.clone(),
+            self.cancel.clone(),
+            self.logger.new(o!("dataset" => dataset_id.to_string())),
+        )?;
 
-        results
+        ingest_task.plan()
+    }
+
+    fn preview(
+        &mut self,
+        dataset_id: &DatasetID,
+        num_rows: usize,
+        maybe_listener: Option<Arc<Mutex<dyn IngestListener>>>,
+    ) -> Result<IngestPreview, IngestError> {
+        let null_listener: Arc<Mutex<dyn IngestListener>> =
+            Arc::new(Mutex::new(NullIngestListener {}));
+        let listener = maybe_listener.unwrap_or(null_listener);
+
+        info!(self.logger, "Previewing ingest"; "dataset" => dataset_id.as_str());
+
+        let meta_chain = self
+            .metadata_repo
+            .borrow()
+            .get_metadata_chain(dataset_id)
+            .map_err(|e| IngestError::internal(e))?;
+
+        let vocab = self
+            .metadata_repo
+            .borrow()
+            .get_summary(dataset_id)
+            .map_err(|e| IngestError::internal(e))?
+            .vocab;
+
+        let config = self
+            .metadata_repo
+            .borrow()
+            .get_config(dataset_id)
+            .map_err(|e| IngestError::internal(e))?;
+        let prev_schema = self
+            .metadata_repo
+            .borrow()
+            .get_schema_history(dataset_id)
+            .map_err(|e| IngestError::internal(e))?
+            .pop()
+            .map(|r| r.schema);
+
+        // Has to be inside the volume to be accessible by the engines
+        std::fs::create_dir_all(&self.volume_layout.cache_dir)
+            .map_err(|e| IngestError::internal(e))?;
+        let temp_dir = tempfile::Builder::new()
+            .prefix(".preview-")
+            .tempdir_in(&self.volume_layout.cache_dir)
+            .map_err(|e| IngestError::internal(e))?;
+
+        let layout = DatasetLayout {
+            data_dir: temp_dir.path().join("data"),
+            checkpoints_dir: temp_dir.path().join("checkpoints"),
+            cache_dir: temp_dir.path().join("cache"),
+            quarantine_dir: temp_dir.path().join("quarantine"),
+        };
+
+        let logger = self.logger.new(o!("dataset" => dataset_id.to_string()));
+
+        let mut ingest_task = IngestTask::new(
+            dataset_id,
+            layout,
+            meta_chain,
+            vocab,
+            config,
+            prev_schema,
+            listener,
+            self.engine_factory.clone(),
+            self.fetch_options.clone(),
+            self.cancel.clone(),
+            logger,
+        )?;
+
+        ingest_task.preview(num_rows)
+    }
+
+    fn ingest_from(
+        &mut self,
+        dataset_id: &DatasetID,
+        data: &mut dyn Read,
+        event_time: Option<DateTime<Utc>>,
+        maybe_listener: Option<Arc<Mutex<dyn IngestListener>>>,
+    ) -> Result<IngestResult, IngestError> {
+        let null_listener: Arc<Mutex<dyn IngestListener>> =
+            Arc::new(Mutex::new(NullIngestListener {}));
+        let listener = maybe_listener.unwrap_or(null_listener);
+
+        info!(self.logger, "Ingesting supplied data"; "dataset" => dataset_id.as_str());
+
+        let meta_chain = self
+            .metadata_repo
+            .borrow()
+            .get_metadata_chain(dataset_id)
+            .map_err(|e| IngestError::internal(e))?;
+
+        let vocab = self
+            .metadata_repo
+            .borrow()
+            .get_summary(dataset_id)
+            .map_err(|e| IngestError::internal(e))?
+            .vocab;
+
+        let config = self
+            .metadata_repo
+            .borrow()
+            .get_config(dataset_id)
+            .map_err(|e| IngestError::internal(e))?;
+        let prev_schema = self
+            .metadata_repo
+            .borrow()
+            .get_schema_history(dataset_id)
+            .map_err(|e| IngestError::internal(e))?
+            .pop()
+            .map(|r| r.schema);
+
+        let layout = self.get_dataset_layout(dataset_id);
+
+        let logger = self.logger.new(o!("dataset" => dataset_id.to_string()));
+
+        let mut ingest_task = IngestTask::new(
+            dataset_id,
+            layout,
+            meta_chain,
+            vocab,
+            config,
+            prev_schema,
+            listener,
+            self.engine_factory.clone(),
+            self.fetch_options.clone(),
+            self.cancel.clone(),
+            logger,
+        )?;
+
+        let result = ingest_task.ingest_from(data, event_time)?;
+        self.update_summary(dataset_id, &result)?;
+        Ok(result)
     }
 }
@@ -191,6 +191,7 @@ impl MetadataRepository for MetadataRepositoryImpl {
             layout.cache_dir,
             layout.checkpoints_dir,
             layout.data_dir,
+            layout.quarantine_dir,
             metadata_dir,
         ];
 
@@ -272,6 +273,99 @@ impl MetadataRepository for MetadataRepositoryImpl {
         serde_yaml::to_writer(file, &manifest).map_err(|e| InfraError::from(e).into())?;
         Ok(())
     }
+
+    fn get_config(&self, dataset_id: &DatasetID) -> Result<DatasetConfig, DomainError> {
+        if !self.dataset_exists(dataset_id) {
+            return Err(DomainError::does_not_exist(
+                ResourceKind::Dataset,
+                dataset_id.as_str().to_owned(),
+            ));
+        }
+
+        let path = self.get_dataset_metadata_dir(dataset_id).join("config");
+        if !path.exists() {
+            return Ok(DatasetConfig::default());
+        }
+
+        let file = std::fs::File::open(&path).map_err(|e| InfraError::from(e).into())?;
+        let manifest: Manifest<DatasetConfig> =
+            serde_yaml::from_reader(&file).map_err(|e| InfraError::from(e).into())?;
+
+        assert_eq!(manifest.kind, "DatasetConfig");
+        Ok(manifest.content)
+    }
+
+    fn set_config(
+        &mut self,
+        dataset_id: &DatasetID,
+        config: DatasetConfig,
+    ) -> Result<(), DomainError> {
+        if !self.dataset_exists(dataset_id) {
+            return Err(DomainError::does_not_exist(
+                ResourceKind::Dataset,
+                dataset_id.as_str().to_owned(),
+            ));
+        }
+
+        let path = self.get_dataset_metadata_dir(dataset_id).join("config");
+
+        let file = std::fs::File::create(&path).map_err(|e| InfraError::from(e).into())?;
+
+        let manifest = Manifest {
+            api_version: 1,
+            kind: "DatasetConfig".to_owned(),
+            content: config,
+        };
+
+        serde_yaml::to_writer(file, &manifest).map_err(|e| InfraError::from(e).into())?;
+        Ok(())
+    }
+
+    fn get_schema_history(&self, dataset_id: &DatasetID) -> Result<Vec<SchemaRecord>, DomainError> {
+        if !self.dataset_exists(dataset_id) {
+            return Err(DomainError::does_not_exist(
+                ResourceKind::Dataset,
+                dataset_id.as_str().to_owned(),
+            ));
+        }
+
+        let path = self.get_dataset_metadata_dir(dataset_id).join("schema");
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let file = std::fs::File::open(&path).map_err(|e| InfraError::from(e).into())?;
+        let manifest: Manifest<Vec<SchemaRecord>> =
+            serde_yaml::from_reader(&file).map_err(|e| InfraError::from(e).into())?;
+
+        assert_eq!(manifest.kind, "DatasetSchemaHistory");
+        Ok(manifest.content)
+    }
+
+    fn record_schema(
+        &mut self,
+        dataset_id: &DatasetID,
+        record: SchemaRecord,
+    ) -> Result<(), DomainError> {
+        let mut history = self.get_schema_history(dataset_id)?;
+        if history.last().map(|r| r.schema == record.schema) == Some(true) {
+            return Ok(());
+        }
+        history.push(record);
+
+        let path = self.get_dataset_metadata_dir(dataset_id).join("schema");
+
+        let file = std::fs::File::create(&path).map_err(|e| InfraError::from(e).into())?;
+
+        let manifest = Manifest {
+            api_version: 1,
+            kind: "DatasetSchemaHistory".to_owned(),
+            content: history,
+        };
+
+        serde_yaml::to_writer(file, &manifest).map_err(|e| InfraError::from(e).into())?;
+        Ok(())
+    }
 }
 
 ///////////////////////////////////////////////////////////////////////////////
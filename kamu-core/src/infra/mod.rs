@@ -21,17 +21,26 @@ pub use metadata_repository_impl::*;
 mod metadata_chain_impl;
 pub use metadata_chain_impl::*;
 
+mod pull_scheduler;
+pub use pull_scheduler::*;
+
 mod pull_service_impl;
 pub use pull_service_impl::*;
 
 mod resource_loader_impl;
 pub use resource_loader_impl::*;
 
+mod source_watcher;
+pub use source_watcher::*;
+
 mod transform_service_impl;
 pub use transform_service_impl::*;
 
 mod volume_layout;
 pub use volume_layout::*;
 
+mod watermark_service_impl;
+pub use watermark_service_impl::*;
+
 mod workspace_layout;
 pub use workspace_layout::*;
@@ -0,0 +1,572 @@
+use super::*;
+use crate::domain::*;
+use crate::infra::serde::yaml::*;
+
+use chrono::{DateTime, Duration, Utc};
+use slog::{info, warn, Logger};
+use std::backtrace::Backtrace;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+// Schedules are re-read at least this often to pick up added or modified datasets
+const MAX_IDLE_SECONDS: i64 = 60;
+// Changes to source files are batched until they stop for this long
+const DEBOUNCE_SECONDS: u64 = 2;
+const STATE_FILE_NAME: &str = "schedule.yaml";
+
+/// Periodically pulls root datasets according to the poll schedules set in
+/// their configuration, together with all datasets that depend on them.
+/// Datasets fetched from the local file system are also pulled as soon as
+/// their files change.
+///
+/// Datasets are pulled sequentially and the next run is only scheduled after
+/// the previous one finishes, so runs of the same dataset never overlap.
+pub struct PullScheduler {
+    metadata_repo: Rc<RefCell<dyn MetadataRepository>>,
+    pull_svc: Rc<RefCell<dyn PullService>>,
+    volume_layout: VolumeLayout,
+    logger: Logger,
+}
+
+impl PullScheduler {
+    pub fn new(
+        metadata_repo: Rc<RefCell<dyn MetadataRepository>>,
+        pull_svc: Rc<RefCell<dyn PullService>>,
+        volume_layout: &VolumeLayout,
+        logger: Logger,
+    ) -> Self {
+        Self {
+            metadata_repo: metadata_repo,
+            pull_svc: pull_svc,
+            volume_layout: volume_layout.clone(),
+            logger: logger,
+        }
+    }
+
+    /// Runs the scheduling loop until SIGINT or SIGTERM is received.
+    ///
+    /// A signal received in the middle of a pull lets it finish and record
+    /// its outcome before exiting.
+    pub fn run(
+        &mut self,
+        listener: Option<Arc<Mutex<dyn PullSchedulerListener>>>,
+    ) -> Result<(), SchedulerError> {
+        let exit = Arc::new(AtomicBool::new(false));
+        signal_hook::flag::register(signal_hook::SIGINT, exit.clone())
+            .map_err(|e| SchedulerError::internal(e))?;
+        signal_hook::flag::register(signal_hook::SIGTERM, exit.clone())
+            .map_err(|e| SchedulerError::internal(e))?;
+
+        let mut watcher = SourceWatcher::new(
+            std::time::Duration::from_secs(DEBOUNCE_SECONDS),
+            self.logger.clone(),
+        )
+        .map_err(|e| SchedulerError::internal(e))?;
+
+        info!(self.logger, "Starting pull scheduler");
+
+        while !exit.load(Ordering::Relaxed) {
+            self.sync_watcher(&mut watcher, listener.clone())?;
+
+            let next_run = self.tick(Utc::now(), listener.clone())?;
+
+            if let Some(ref l) = listener {
+                l.lock().unwrap().on_idle(next_run);
+            }
+
+            let wake_up = next_run
+                .unwrap_or_else(|| Utc::now() + Duration::seconds(MAX_IDLE_SECONDS))
+                .min(Utc::now() + Duration::seconds(MAX_IDLE_SECONDS));
+
+            let mut changed = Vec::new();
+            while !exit.load(Ordering::Relaxed) && Utc::now() < wake_up && changed.is_empty() {
+                changed = watcher
+                    .poll(std::time::Duration::from_millis(100))
+                    .map_err(|e| SchedulerError::internal(e))?;
+            }
+
+            if !changed.is_empty() {
+                self.pull_changed(Utc::now(), &changed, listener.clone())?;
+            }
+        }
+
+        info!(self.logger, "Stopping pull scheduler");
+        Ok(())
+    }
+
+    /// Pulls all datasets that are due at the specified time and returns
+    /// the time of the closest next run
+    pub fn tick(
+        &mut self,
+        now: DateTime<Utc>,
+        listener: Option<Arc<Mutex<dyn PullSchedulerListener>>>,
+    ) -> Result<Option<DateTime<Utc>>, SchedulerError> {
+        let mut due = Vec::new();
+        let mut next_run: Option<DateTime<Utc>> = None;
+
+        for scheduled in self.get_scheduled_datasets(now, listener.clone())? {
+            if scheduled.state.next_run <= now {
+                due.push(scheduled);
+            } else {
+                next_run = Some(
+                    next_run
+                        .map(|t| t.min(scheduled.state.next_run))
+                        .unwrap_or(scheduled.state.next_run),
+                );
+            }
+        }
+
+        if due.is_empty() {
+            return Ok(next_run);
+        }
+
+        let due_ids: Vec<DatasetIDBuf> = due.iter().map(|s| s.dataset_id.clone()).collect();
+        let results = self.pull_with_dependents(&due_ids, listener)?;
+
+        // Next runs are computed from the completion time so that a pull that
+        // took longer than the interval does not trigger a backlog of runs
+        let finished = Utc::now().max(now);
+
+        for scheduled in due {
+            let state = ScheduleState {
+                last_run: Some(now),
+                last_outcome: Some(Self::get_outcome(&results, &scheduled.dataset_id)),
+                next_run: next_run_after(&scheduled.schedule, finished)
+                    .map_err(|e| SchedulerError::invalid_schedule(&scheduled.dataset_id, e))?,
+            };
+
+            next_run = Some(
+                next_run
+                    .map(|t| t.min(state.next_run))
+                    .unwrap_or(state.next_run),
+            );
+
+            self.write_state(&scheduled.dataset_id, state)?;
+        }
+
+        Ok(next_run)
+    }
+
+    /// Pulls datasets whose source files have changed along with their
+    /// dependents, outside of their regular schedule
+    pub fn pull_changed(
+        &mut self,
+        now: DateTime<Utc>,
+        dataset_ids: &[DatasetIDBuf],
+        listener: Option<Arc<Mutex<dyn PullSchedulerListener>>>,
+    ) -> Result<(), SchedulerError> {
+        info!(self.logger, "Source files changed"; "datasets" => ?dataset_ids);
+
+        if let Some(ref l) = listener {
+            l.lock().unwrap().on_files_changed(dataset_ids);
+        }
+
+        let results = self.pull_with_dependents(dataset_ids, listener)?;
+
+        // Scheduled runs stay where they were
+        for dataset_id in dataset_ids {
+            if let Some(mut state) = self.read_state(dataset_id)? {
+                state.last_run = Some(now);
+                state.last_outcome = Some(Self::get_outcome(&results, dataset_id));
+                self.write_state(dataset_id, state)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn pull_with_dependents(
+        &mut self,
+        dataset_ids: &[DatasetIDBuf],
+        listener: Option<Arc<Mutex<dyn PullSchedulerListener>>>,
+    ) -> Result<Vec<(DatasetIDBuf, Result<PullResult, PullError>)>, SchedulerError> {
+        let dependents = self.get_dependents(dataset_ids)?;
+
+        info!(self.logger, "Pulling datasets"; "datasets" => ?dataset_ids, "dependents" => ?dependents);
+
+        if let Some(ref l) = listener {
+            l.lock().unwrap().on_pull_start(dataset_ids, &dependents);
+        }
+
+        let results = self.pull_svc.borrow_mut().pull_multi(
+            &mut dataset_ids
+                .iter()
+                .chain(dependents.iter())
+                .map(|id| id.as_ref()),
+            false,
+            false,
+            None,
+            None,
+        );
+
+        if let Some(ref l) = listener {
+            l.lock().unwrap().on_pull_end(&results);
+        }
+
+        Ok(results)
+    }
+
+    fn get_outcome(
+        results: &[(DatasetIDBuf, Result<PullResult, PullError>)],
+        dataset_id: &DatasetID,
+    ) -> ScheduleRunOutcome {
+        match results.iter().find(|(id, _)| id == dataset_id) {
+            Some((_, Ok(PullResult::UpToDate { .. }))) => ScheduleRunOutcome::UpToDate,
+            Some((_, Ok(PullResult::Updated { .. }))) => ScheduleRunOutcome::Updated,
+            _ => ScheduleRunOutcome::Failed,
+        }
+    }
+
+    /// Subscribes the watcher to the local files of all root datasets
+    pub fn sync_watcher(
+        &self,
+        watcher: &mut SourceWatcher,
+        listener: Option<Arc<Mutex<dyn PullSchedulerListener>>>,
+    ) -> Result<(), SchedulerError> {
+        let sources: Vec<(DatasetIDBuf, FetchStep)> = self
+            .get_root_sources()?
+            .into_iter()
+            .filter(|(_, _, source)| Self::is_supported(source))
+            .map(|(id, _, source)| (id, source.fetch))
+            .collect();
+
+        for error in watcher.sync(&sources) {
+            warn!(self.logger, "Failed to watch source files"; "datasets" => ?error.dataset_ids, "error" => %error);
+            if let Some(ref l) = listener {
+                l.lock().unwrap().on_watch_error(&error);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns all root datasets that declare a poll schedule along with
+    /// their last recorded run
+    pub fn get_scheduled_datasets(
+        &self,
+        now: DateTime<Utc>,
+        listener: Option<Arc<Mutex<dyn PullSchedulerListener>>>,
+    ) -> Result<Vec<ScheduledDataset>, SchedulerError> {
+        let mut scheduled = Vec::new();
+
+        for (dataset_id, summary, source) in self.get_root_sources()? {
+            let config = self.metadata_repo.borrow().get_config(&dataset_id)?;
+            let schedule = match config.schedule {
+                Some(schedule) => schedule,
+                None => continue,
+            };
+
+            if !Self::is_supported(&source) {
+                self.report_invalid_schedule(
+                    &dataset_id,
+                    ScheduleError::UnsupportedSource,
+                    listener.clone(),
+                );
+                continue;
+            }
+
+            let state = match self.read_state(&dataset_id)? {
+                Some(state) => state,
+                None => ScheduleState {
+                    last_run: None,
+                    last_outcome: None,
+                    // Datasets that were never pulled are due immediately
+                    next_run: match summary.last_pulled {
+                        None => now,
+                        Some(last_pulled) => match next_run_after(&schedule, last_pulled) {
+                            Ok(t) => t,
+                            Err(e) => {
+                                self.report_invalid_schedule(&dataset_id, e, listener.clone());
+                                continue;
+                            }
+                        },
+                    },
+                },
+            };
+
+            // Validate the schedule upfront to not fail after the pull
+            if let Err(e) = next_run_after(&schedule, state.next_run) {
+                self.report_invalid_schedule(&dataset_id, e, listener.clone());
+                continue;
+            }
+
+            scheduled.push(ScheduledDataset {
+                dataset_id: dataset_id,
+                schedule: schedule,
+                state: state,
+            });
+        }
+
+        Ok(scheduled)
+    }
+
+    fn get_root_sources(
+        &self,
+    ) -> Result<Vec<(DatasetIDBuf, DatasetSummary, DatasetSourceRoot)>, SchedulerError> {
+        let metadata_repo = self.metadata_repo.borrow();
+        let mut dataset_ids: Vec<DatasetIDBuf> = metadata_repo.get_all_datasets().collect();
+        dataset_ids.sort();
+
+        let mut sources = Vec::new();
+
+        for dataset_id in dataset_ids {
+            let summary = metadata_repo.get_summary(&dataset_id)?;
+            if summary.kind != DatasetKind::Root {
+                continue;
+            }
+
+            if let Some(DatasetSource::Root(source)) = metadata_repo
+                .get_metadata_chain(&dataset_id)?
+                .iter_blocks()
+                .filter_map(|b| b.source)
+                .next()
+            {
+                sources.push((dataset_id, summary, source));
+            }
+        }
+
+        Ok(sources)
+    }
+
+    // Pulling such sources would fail on every run or file change
+    fn is_supported(source: &DatasetSourceRoot) -> bool {
+        match source.fetch {
+            FetchStep::Url(_) => true,
+            FetchStep::FilesGlob(_) => false,
+        }
+    }
+
+    fn report_invalid_schedule(
+        &self,
+        dataset_id: &DatasetID,
+        error: ScheduleError,
+        listener: Option<Arc<Mutex<dyn PullSchedulerListener>>>,
+    ) {
+        warn!(self.logger, "Skipping dataset with invalid schedule"; "dataset" => dataset_id.as_str(), "error" => %error);
+        if let Some(l) = listener {
+            l.lock().unwrap().on_invalid_schedule(dataset_id, &error);
+        }
+    }
+
+    // Returns all datasets that transitively depend on the specified ones
+    fn get_dependents(
+        &self,
+        dataset_ids: &[DatasetIDBuf],
+    ) -> Result<Vec<DatasetIDBuf>, SchedulerError> {
+        let metadata_repo = self.metadata_repo.borrow();
+
+        let mut summaries = Vec::new();
+        for id in metadata_repo.get_all_datasets() {
+            summaries.push(metadata_repo.get_summary(&id)?);
+        }
+
+        let mut affected: HashSet<DatasetIDBuf> = dataset_ids.iter().cloned().collect();
+        let mut dependents = Vec::new();
+
+        loop {
+            let new: Vec<DatasetIDBuf> = summaries
+                .iter()
+                .filter(|s| !affected.contains(&s.id))
+                .filter(|s| s.dependencies.iter().any(|d| affected.contains(d)))
+                .map(|s| s.id.clone())
+                .collect();
+
+            if new.is_empty() {
+                break;
+            }
+
+            affected.extend(new.iter().cloned());
+            dependents.extend(new);
+        }
+
+        dependents.sort();
+        Ok(dependents)
+    }
+
+    fn get_state_path(&self, dataset_id: &DatasetID) -> PathBuf {
+        DatasetLayout::new(&self.volume_layout, dataset_id)
+            .cache_dir
+            .join(STATE_FILE_NAME)
+    }
+
+    pub fn read_state(
+        &self,
+        dataset_id: &DatasetID,
+    ) -> Result<Option<ScheduleState>, SchedulerError> {
+        let path = self.get_state_path(dataset_id);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let file = std::fs::File::open(&path).map_err(|e| SchedulerError::internal(e))?;
+        let manifest: Manifest<ScheduleState> =
+            serde_yaml::from_reader(file).map_err(|e| SchedulerError::internal(e))?;
+        assert_eq!(manifest.kind, "ScheduleState");
+        Ok(Some(manifest.content))
+    }
+
+    fn write_state(
+        &self,
+        dataset_id: &DatasetID,
+        state: ScheduleState,
+    ) -> Result<(), SchedulerError> {
+        let path = self.get_state_path(dataset_id);
+        std::fs::create_dir_all(path.parent().unwrap()).map_err(|e| SchedulerError::internal(e))?;
+
+        let file = std::fs::File::create(&path).map_err(|e| SchedulerError::internal(e))?;
+        let manifest = Manifest {
+            api_version: 1,
+            kind: "ScheduleState".to_owned(),
+            content: state,
+        };
+        serde_yaml::to_writer(file, &manifest).map_err(|e| SchedulerError::internal(e))?;
+        Ok(())
+    }
+}
+
+pub struct ScheduledDataset {
+    pub dataset_id: DatasetIDBuf,
+    pub schedule: PollSchedule,
+    pub state: ScheduleState,
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Listener
+///////////////////////////////////////////////////////////////////////////////
+
+pub trait PullSchedulerListener {
+    fn on_pull_start(&mut self, _due: &[DatasetIDBuf], _dependents: &[DatasetIDBuf]) {}
+    fn on_pull_end(&mut self, _results: &[(DatasetIDBuf, Result<PullResult, PullError>)]) {}
+    fn on_idle(&mut self, _next_run: Option<DateTime<Utc>>) {}
+    fn on_invalid_schedule(&mut self, _dataset_id: &DatasetID, _error: &ScheduleError) {}
+    fn on_files_changed(&mut self, _dataset_ids: &[DatasetIDBuf]) {}
+    fn on_watch_error(&mut self, _error: &WatchError) {}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Schedules
+///////////////////////////////////////////////////////////////////////////////
+
+/// Returns the earliest time strictly after the specified one when
+/// the schedule should trigger
+pub fn next_run_after(
+    schedule: &PollSchedule,
+    after: DateTime<Utc>,
+) -> Result<DateTime<Utc>, ScheduleError> {
+    match schedule {
+        PollSchedule::Interval(interval) => Ok(after + parse_interval(&interval.every)?),
+        PollSchedule::Cron(cron) => {
+            // Standard five-field expressions don't specify seconds
+            let expression = if cron.expression.split_whitespace().count() == 5 {
+                format!("0 {}", cron.expression)
+            } else {
+                cron.expression.clone()
+            };
+
+            let parsed =
+                cron::Schedule::from_str(&expression).map_err(|e| ScheduleError::InvalidCron {
+                    expression: cron.expression.clone(),
+                    reason: e.to_string(),
+                })?;
+
+            parsed
+                .after(&after)
+                .next()
+                .ok_or_else(|| ScheduleError::InvalidCron {
+                    expression: cron.expression.clone(),
+                    reason: "Expression never triggers".to_owned(),
+                })
+        }
+    }
+}
+
+/// Parses intervals like `30s`, `15m`, `1h30m` or `1d`
+pub fn parse_interval(s: &str) -> Result<Duration, ScheduleError> {
+    let err = || ScheduleError::InvalidInterval {
+        interval: s.to_owned(),
+    };
+
+    let mut total = Duration::zero();
+    let mut number = String::new();
+
+    for c in s.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let n: i64 = number.parse().map_err(|_| err())?;
+        number.clear();
+
+        total = total
+            + match c {
+                's' => Duration::seconds(n),
+                'm' => Duration::minutes(n),
+                'h' => Duration::hours(n),
+                'd' => Duration::days(n),
+                'w' => Duration::weeks(n),
+                _ => return Err(err()),
+            };
+    }
+
+    if !number.is_empty() || total <= Duration::zero() {
+        return Err(err());
+    }
+
+    Ok(total)
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Errors
+///////////////////////////////////////////////////////////////////////////////
+
+type BoxedError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Error, Debug)]
+pub enum ScheduleError {
+    #[error("Invalid interval {interval}, expected a value like 30s, 15m, 1h30m or 1d")]
+    InvalidInterval { interval: String },
+    #[error("Invalid cron expression {expression}: {reason}")]
+    InvalidCron { expression: String, reason: String },
+    #[error("Fetching files by glob pattern is not supported yet")]
+    UnsupportedSource,
+}
+
+#[derive(Error, Debug)]
+pub enum SchedulerError {
+    #[error("Dataset {dataset_id} has invalid schedule: {source}")]
+    InvalidSchedule {
+        dataset_id: DatasetIDBuf,
+        #[source]
+        source: ScheduleError,
+    },
+    #[error("{0}")]
+    DomainError(#[from] DomainError),
+    #[error("Internal error: {source}")]
+    InternalError {
+        source: BoxedError,
+        backtrace: Backtrace,
+    },
+}
+
+impl SchedulerError {
+    pub fn invalid_schedule(dataset_id: &DatasetID, e: ScheduleError) -> Self {
+        SchedulerError::InvalidSchedule {
+            dataset_id: dataset_id.to_owned(),
+            source: e,
+        }
+    }
+
+    pub fn internal(e: impl std::error::Error + Send + Sync + 'static) -> Self {
+        SchedulerError::InternalError {
+            source: e.into(),
+            backtrace: Backtrace::capture(),
+        }
+    }
+}
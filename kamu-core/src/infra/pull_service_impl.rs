@@ -1,8 +1,9 @@
 use crate::domain::*;
+use crate::infra::utils::worker_pool::*;
 
 use slog::{info, Logger};
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 
@@ -10,9 +11,18 @@ pub struct PullServiceImpl {
     metadata_repo: Rc<RefCell<dyn MetadataRepository>>,
     ingest_svc: Rc<RefCell<dyn IngestService>>,
     transform_svc: Rc<RefCell<dyn TransformService>>,
+    concurrency: ConcurrencyOptions,
+    cancel: CancellationToken,
     logger: Logger,
 }
 
+// Outcome of a job executed on a worker thread
+enum PullJobResult {
+    Ingest(Result<IngestResult, IngestError>),
+    Transform(Result<TransformResult, TransformError>),
+    Cancelled,
+}
+
 impl PullServiceImpl {
     pub fn new(
         metadata_repo: Rc<RefCell<dyn MetadataRepository>>,
@@ -24,10 +34,53 @@ impl PullServiceImpl {
             metadata_repo: metadata_repo,
             ingest_svc: ingest_svc,
             transform_svc: transform_svc,
+            concurrency: ConcurrencyOptions::default(),
+            cancel: CancellationToken::new(),
             logger: logger,
         }
     }
 
+    pub fn with_concurrency(self, concurrency: ConcurrencyOptions) -> Self {
+        Self {
+            concurrency: concurrency,
+            ..self
+        }
+    }
+
+    /// Datasets that were not pulled by the time the token gets cancelled
+    /// are reported as cancelled
+    pub fn with_cancellation(self, cancel: CancellationToken) -> Self {
+        Self {
+            cancel: cancel,
+            ..self
+        }
+    }
+
+    fn get_datasets_to_pull(
+        &self,
+        dataset_ids: &mut dyn Iterator<Item = &DatasetID>,
+        recursive: bool,
+        all: bool,
+    ) -> Vec<(DatasetIDBuf, i32)> {
+        let starting_dataset_ids: HashSet<DatasetIDBuf> = if !all {
+            dataset_ids.map(|id| id.to_owned()).collect()
+        } else {
+            self.metadata_repo.borrow().get_all_datasets().collect()
+        };
+
+        let datasets_labeled = self
+            .get_datasets_ordered_by_depth(&mut starting_dataset_ids.iter().map(|id| id.as_ref()));
+
+        if recursive || all {
+            datasets_labeled
+        } else {
+            datasets_labeled
+                .into_iter()
+                .filter(|(id, _)| starting_dataset_ids.contains(id))
+                .collect()
+        }
+    }
+
     fn get_datasets_ordered_by_depth<'i>(
         &self,
         starting_dataset_ids: impl Iterator<Item = &'i DatasetID>,
@@ -76,11 +129,13 @@ impl PullServiceImpl {
     ) -> Result<PullResult, PullError> {
         match res {
             Ok(res) => Ok(match res {
-                IngestResult::UpToDate => PullResult::UpToDate,
-                IngestResult::Updated { block_hash } => PullResult::Updated {
+                IngestResult::UpToDate { stats } => PullResult::UpToDate { stats: stats },
+                IngestResult::Updated { block_hash, stats } => PullResult::Updated {
                     block_hash: block_hash,
+                    stats: stats,
                 },
             }),
+            Err(IngestError::Cancelled(e)) => Err(PullError::Cancelled(e)),
             Err(err) => Err(err.into()),
         }
     }
@@ -90,22 +145,81 @@ impl PullServiceImpl {
     ) -> Result<PullResult, PullError> {
         match res {
             Ok(res) => Ok(match res {
-                TransformResult::UpToDate => PullResult::UpToDate,
-                TransformResult::Updated { block_hash } => PullResult::Updated {
+                TransformResult::UpToDate => PullResult::UpToDate {
+                    stats: UpdateStats::default(),
+                },
+                TransformResult::Updated { block_hash, stats } => PullResult::Updated {
                     block_hash: block_hash,
+                    stats: stats,
                 },
             }),
+            Err(TransformError::Cancelled(e)) => Err(PullError::Cancelled(e)),
             Err(err) => Err(err.into()),
         }
     }
 
-    fn slice<'a>(
+    // Prepares a job for the dataset once all of its inputs are pulled
+    fn start_pull(
+        &self,
+        dataset_id: &DatasetID,
+        is_root: bool,
+        ingest_listener: &Option<Arc<Mutex<dyn IngestMultiListener>>>,
+        transform_listener: &Option<Arc<Mutex<dyn TransformMultiListener>>>,
+    ) -> (Option<String>, Task<PullJobResult>) {
+        if self.cancel.is_cancelled() {
+            return (None, Box::new(|| PullJobResult::Cancelled));
+        }
+
+        // See: https://internals.rust-lang.org/t/should-option-mut-t-implement-copy/3715/6
+        // For listener option magic explanation
+        if is_root {
+            match self
+                .ingest_svc
+                .borrow_mut()
+                .prepare_ingest(dataset_id, ingest_listener.clone())
+            {
+                Ok(job) => {
+                    let run = job.run;
+                    (job.engine, Box::new(move || PullJobResult::Ingest(run())))
+                }
+                Err(err) => (None, Box::new(move || PullJobResult::Ingest(Err(err)))),
+            }
+        } else {
+            match self
+                .transform_svc
+                .borrow_mut()
+                .prepare_transform(dataset_id, transform_listener.clone())
+            {
+                Ok(job) => {
+                    let run = job.run;
+                    (
+                        job.engine,
+                        Box::new(move || PullJobResult::Transform(run())),
+                    )
+                }
+                Err(err) => (None, Box::new(move || PullJobResult::Transform(Err(err)))),
+            }
+        }
+    }
+
+    fn finish_pull(
         &self,
-        to_slice: &'a [(DatasetIDBuf, i32)],
-    ) -> (i32, &'a [(DatasetIDBuf, i32)], &'a [(DatasetIDBuf, i32)]) {
-        let d = to_slice.get(0).unwrap().1;
-        let count = to_slice.iter().take_while(|(_, depth)| *depth == d).count();
-        (d, &to_slice[..count], &to_slice[count..])
+        dataset_id: &DatasetID,
+        result: PullJobResult,
+    ) -> Result<PullResult, PullError> {
+        match result {
+            PullJobResult::Ingest(res) => Self::convert_ingest_result(
+                self.ingest_svc
+                    .borrow_mut()
+                    .complete_ingest(dataset_id, res),
+            ),
+            PullJobResult::Transform(res) => Self::convert_transform_result(
+                self.transform_svc
+                    .borrow_mut()
+                    .complete_transform(dataset_id, res),
+            ),
+            PullJobResult::Cancelled => Err(PullError::Cancelled(CancelledError)),
+        }
     }
 }
 
@@ -118,64 +232,112 @@ impl PullService for PullServiceImpl {
         ingest_listener: Option<Arc<Mutex<dyn IngestMultiListener>>>,
         transform_listener: Option<Arc<Mutex<dyn TransformMultiListener>>>,
     ) -> Vec<(DatasetIDBuf, Result<PullResult, PullError>)> {
-        let starting_dataset_ids: std::collections::HashSet<DatasetIDBuf> = if !all {
-            dataset_ids.map(|id| id.to_owned()).collect()
-        } else {
-            self.metadata_repo.borrow().get_all_datasets().collect()
-        };
+        let datasets_to_pull = self.get_datasets_to_pull(dataset_ids, recursive, all);
 
-        info!(self.logger, "Performing pull_multi"; "datasets" => ?starting_dataset_ids);
+        info!(self.logger, "Performing pull_multi"; "datasets" => ?datasets_to_pull);
 
-        let datasets_labeled = self
-            .get_datasets_ordered_by_depth(&mut starting_dataset_ids.iter().map(|id| id.as_ref()));
+        let roots: HashSet<_> = datasets_to_pull
+            .iter()
+            .filter(|(_, depth)| *depth == 0)
+            .map(|(id, _)| id.clone())
+            .collect();
 
-        let datasets_to_pull = if recursive || all {
-            datasets_labeled
-        } else {
-            datasets_labeled
-                .into_iter()
-                .filter(|(id, _)| starting_dataset_ids.contains(id))
-                .collect()
-        };
+        // Dependencies outside of the pulled set are ignored by the scheduler
+        let nodes: Vec<_> = datasets_to_pull
+            .iter()
+            .map(|(id, _)| {
+                let summary = self.metadata_repo.borrow().get_summary(id).unwrap();
+                (id.clone(), summary.dependencies)
+            })
+            .collect();
 
-        let mut results = Vec::with_capacity(datasets_to_pull.len());
+        // Each dataset starts as soon as all of its inputs are pulled
+        let graph_results = run_graph(
+            nodes,
+            &self.concurrency,
+            "pull_multi",
+            |id| {
+                self.start_pull(
+                    id,
+                    roots.contains(id),
+                    &ingest_listener,
+                    &transform_listener,
+                )
+            },
+            |id, res| self.finish_pull(id, res),
+        );
 
-        let mut rest = &datasets_to_pull[..];
-        while !rest.is_empty() {
-            let (depth, level, tail) = self.slice(rest);
-            rest = tail;
+        let mut results = graph_results.finished;
 
-            // See: https://internals.rust-lang.org/t/should-option-mut-t-implement-copy/3715/6
-            // For listener option magic explanation
-            let results_level: Vec<_> = if depth == 0 {
-                self.ingest_svc
-                    .borrow_mut()
-                    .ingest_multi(
-                        &mut level.iter().map(|(id, _)| id.as_ref()),
-                        ingest_listener.clone(),
-                    )
-                    .into_iter()
-                    .map(|(id, res)| (id, Self::convert_ingest_result(res)))
-                    .collect()
-            } else {
-                self.transform_svc
-                    .borrow_mut()
-                    .transform_multi(
-                        &mut level.iter().map(|(id, _)| id.as_ref()),
-                        transform_listener.clone(),
-                    )
+        if self.cancel.is_cancelled() {
+            let remaining: Vec<_> = graph_results
+                .blocked
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect();
+            info!(self.logger, "Pull was cancelled"; "remaining" => remaining.len());
+            results.extend(
+                remaining
                     .into_iter()
-                    .map(|(id, res)| (id, Self::convert_transform_result(res)))
-                    .collect()
-            };
-
-            let errors = results_level.iter().any(|(_, r)| r.is_err());
-            results.extend(results_level);
-            if errors {
-                break;
+                    .map(|id| (id, Err(PullError::Cancelled(CancelledError)))),
+            );
+        } else {
+            if !graph_results.blocked.is_empty() {
+                info!(self.logger, "Skipping datasets"; "blocked" => ?graph_results.blocked);
             }
+
+            results.extend(graph_results.blocked.into_iter().map(|(id, failed_id)| {
+                let reason = SkipReason::InputFailed {
+                    dataset_id: failed_id,
+                };
+                (id, Ok(PullResult::Skipped { reason: reason }))
+            }));
         }
 
+        // Datasets finish in arbitrary order, so we report them in the order of depth
+        let order: HashMap<_, _> = datasets_to_pull
+            .iter()
+            .enumerate()
+            .map(|(i, (id, _))| (id.clone(), i))
+            .collect();
+        results.sort_by_key(|(id, _)| order[id]);
+
         results
     }
+
+    fn plan_pull(
+        &mut self,
+        dataset_ids: &mut dyn Iterator<Item = &DatasetID>,
+        recursive: bool,
+        all: bool,
+    ) -> Result<Vec<PullPlanStep>, PullError> {
+        let datasets_to_pull = self.get_datasets_to_pull(dataset_ids, recursive, all);
+
+        info!(self.logger, "Planning pull"; "datasets" => ?datasets_to_pull);
+
+        let in_plan: HashSet<_> = datasets_to_pull.iter().map(|(id, _)| id.clone()).collect();
+
+        datasets_to_pull
+            .into_iter()
+            .map(|(id, depth)| -> Result<PullPlanStep, PullError> {
+                let summary = self.metadata_repo.borrow().get_summary(&id)?;
+
+                let action = if depth == 0 {
+                    PullAction::Ingest(self.ingest_svc.borrow_mut().plan_ingest(&id)?)
+                } else {
+                    PullAction::Transform(self.transform_svc.borrow_mut().plan_transform(&id)?)
+                };
+
+                Ok(PullPlanStep {
+                    dataset_id: id,
+                    depends_on: summary
+                        .dependencies
+                        .into_iter()
+                        .filter(|d| in_plan.contains(d))
+                        .collect(),
+                    action: action,
+                })
+            })
+            .collect()
+    }
 }
@@ -13,15 +13,21 @@ impl ResourceLoaderImpl {
 }
 
 impl ResourceLoader for ResourceLoaderImpl {
-    fn load_dataset_snapshot_from_path(&self, path: &Path) -> Result<DatasetSnapshot, DomainError> {
+    fn load_dataset_snapshot_from_path(
+        &self,
+        path: &Path,
+    ) -> Result<(DatasetSnapshot, DatasetConfig), DomainError> {
         let file = std::fs::File::open(path).map_err(|e| InfraError::from(e).into())?;
-        let manifest: Manifest<DatasetSnapshot> =
+        let manifest: DatasetSnapshotManifest =
             serde_yaml::from_reader(file).map_err(|e| InfraError::from(e).into())?;
         assert_eq!(manifest.kind, "DatasetSnapshot");
-        Ok(manifest.content)
+        Ok((manifest.content, manifest.config.unwrap_or_default()))
     }
 
-    fn load_dataset_snapshot_from_ref(&self, sref: &str) -> Result<DatasetSnapshot, DomainError> {
+    fn load_dataset_snapshot_from_ref(
+        &self,
+        sref: &str,
+    ) -> Result<(DatasetSnapshot, DatasetConfig), DomainError> {
         self.load_dataset_snapshot_from_path(Path::new(sref))
     }
 }
@@ -1,9 +1,10 @@
-use super::dtos_odf::DatasetVocabulary;
-use super::formats::datetime_rfc3339_opt;
+use super::dtos_odf::{CompressionFormat, DatasetSnapshot, DatasetVocabulary};
+use super::formats::{datetime_rfc3339, datetime_rfc3339_opt};
 use crate::domain::*;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
+use serde_yaml::Number;
 
 #[skip_serializing_none]
 #[serde(deny_unknown_fields, rename_all = "camelCase")]
@@ -14,6 +15,18 @@ pub struct Manifest<T> {
     pub content: T,
 }
 
+/// Dataset snapshot file can carry kamu-specific configuration of the dataset
+/// next to the snapshot itself
+#[skip_serializing_none]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DatasetSnapshotManifest {
+    pub api_version: i32,
+    pub kind: String,
+    pub content: DatasetSnapshot,
+    pub config: Option<DatasetConfig>,
+}
+
 #[skip_serializing_none]
 #[serde(deny_unknown_fields, rename_all = "camelCase")]
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -37,6 +50,37 @@ pub struct DatasetSummary {
     pub vocab: DatasetVocabulary,
 }
 
+#[skip_serializing_none]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduleState {
+    #[serde(default, with = "datetime_rfc3339_opt")]
+    pub last_run: Option<DateTime<Utc>>,
+    pub last_outcome: Option<ScheduleRunOutcome>,
+    #[serde(with = "datetime_rfc3339")]
+    pub next_run: DateTime<Utc>,
+}
+
+#[skip_serializing_none]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScheduleRunOutcome {
+    UpToDate,
+    Updated,
+    Failed,
+}
+
+/// Schema of the dataset's data in DDL form as of the block that changed it
+#[skip_serializing_none]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaRecord {
+    pub block_hash: String,
+    #[serde(with = "datetime_rfc3339")]
+    pub system_time: DateTime<Utc>,
+    pub schema: Vec<String>,
+}
+
 impl Default for DatasetVocabulary {
     fn default() -> Self {
         Self {
@@ -45,3 +89,199 @@ impl Default for DatasetVocabulary {
         }
     }
 }
+
+#[skip_serializing_none]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RequestHeader {
+    pub name: String,
+    pub value: String,
+}
+
+#[skip_serializing_none]
+#[serde(deny_unknown_fields, rename_all = "camelCase", tag = "kind")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FetchAuth {
+    #[serde(rename_all = "camelCase")]
+    Basic(FetchAuthBasic),
+    #[serde(rename_all = "camelCase")]
+    Bearer(FetchAuthBearer),
+}
+
+#[skip_serializing_none]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FetchAuthBasic {
+    pub username: String,
+    pub password: String,
+}
+
+#[skip_serializing_none]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FetchAuthBearer {
+    pub token: String,
+}
+
+#[skip_serializing_none]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaPolicy {
+    pub on_column_added: Option<SchemaChangeAction>,
+    pub on_column_removed: Option<SchemaChangeAction>,
+    pub on_type_changed: Option<SchemaChangeAction>,
+}
+
+#[skip_serializing_none]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SchemaChangeAction {
+    Ignore,
+    Warn,
+    Fail,
+}
+
+#[skip_serializing_none]
+#[serde(deny_unknown_fields, rename_all = "camelCase", tag = "kind")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Expectation {
+    #[serde(rename_all = "camelCase")]
+    NotNull(ExpectationNotNull),
+    #[serde(rename_all = "camelCase")]
+    Unique(ExpectationUnique),
+    #[serde(rename_all = "camelCase")]
+    Range(ExpectationRange),
+    #[serde(rename_all = "camelCase")]
+    Pattern(ExpectationPattern),
+    #[serde(rename_all = "camelCase")]
+    RowCount(ExpectationRowCount),
+}
+
+#[skip_serializing_none]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpectationNotNull {
+    pub columns: Vec<String>,
+    pub on_violation: Option<ExpectationAction>,
+}
+
+#[skip_serializing_none]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpectationUnique {
+    pub columns: Vec<String>,
+    pub on_violation: Option<ExpectationAction>,
+}
+
+#[skip_serializing_none]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpectationRange {
+    pub column: String,
+    pub min: Option<Number>,
+    pub max: Option<Number>,
+    pub on_violation: Option<ExpectationAction>,
+}
+
+#[skip_serializing_none]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpectationPattern {
+    pub column: String,
+    pub regex: String,
+    pub on_violation: Option<ExpectationAction>,
+}
+
+#[skip_serializing_none]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpectationRowCount {
+    pub min: Option<u64>,
+    pub max: Option<u64>,
+}
+
+#[skip_serializing_none]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExpectationAction {
+    Fail,
+    Quarantine,
+}
+
+#[skip_serializing_none]
+#[serde(deny_unknown_fields, rename_all = "camelCase", tag = "kind")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PollSchedule {
+    #[serde(rename_all = "camelCase")]
+    Interval(PollScheduleInterval),
+    #[serde(rename_all = "camelCase")]
+    Cron(PollScheduleCron),
+}
+
+#[skip_serializing_none]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PollScheduleInterval {
+    pub every: String,
+}
+
+#[skip_serializing_none]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PollScheduleCron {
+    pub expression: String,
+}
+
+/// Configuration of a dataset that is not a part of its metadata
+#[skip_serializing_none]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DatasetConfig {
+    pub fetch: Option<FetchConfig>,
+    pub decompress: Option<DecompressConfig>,
+    pub schema_policy: Option<SchemaPolicy>,
+    pub expectations: Option<Vec<Expectation>>,
+    pub schedule: Option<PollSchedule>,
+}
+
+/// Applies to URL sources only
+#[skip_serializing_none]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FetchConfig {
+    pub headers: Option<Vec<RequestHeader>>,
+    pub auth: Option<FetchAuth>,
+    /// Expected digest of the fetched data in the `<algorithm>:<hex digest>` form
+    pub checksum: Option<String>,
+}
+
+/// Overrides the format of all decompress steps of the source
+#[skip_serializing_none]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecompressConfig {
+    pub format: ArchiveFormat,
+}
+
+#[skip_serializing_none]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArchiveFormat {
+    Gzip,
+    Bzip2,
+    Xz,
+    Zip,
+    Tar,
+    TarGz,
+    TarBz2,
+    TarXz,
+}
+
+impl From<&CompressionFormat> for ArchiveFormat {
+    fn from(format: &CompressionFormat) -> Self {
+        match format {
+            CompressionFormat::Gzip => ArchiveFormat::Gzip,
+            CompressionFormat::Zip => ArchiveFormat::Zip,
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use crate::domain::*;
+use crate::infra::serde::yaml::*;
+
+use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+use slog::{debug, info, Logger};
+use std::collections::{BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::time::Duration;
+use thiserror::Error;
+use url::Url;
+
+/// Subscribes to file system notifications for the local files that root
+/// datasets are fetched from and reports which datasets were affected.
+///
+/// Parent directories are watched instead of the files themselves so that
+/// files replaced via rename or created after the watch started are noticed.
+pub struct SourceWatcher {
+    watcher: RecommendedWatcher,
+    events: Receiver<DebouncedEvent>,
+    watched_dirs: HashSet<PathBuf>,
+    targets: Vec<(DatasetIDBuf, WatchTarget)>,
+    logger: Logger,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchTarget {
+    /// Directory to subscribe to
+    pub dir: PathBuf,
+    /// Pattern that changed file paths are matched against
+    pub pattern: glob::Pattern,
+}
+
+impl SourceWatcher {
+    /// Creates a watcher that delivers change notifications once the files
+    /// stayed unmodified for the specified period
+    pub fn new(debounce: Duration, logger: Logger) -> Result<Self, notify::Error> {
+        let (tx, rx) = channel();
+        Ok(Self {
+            watcher: notify::watcher(tx, debounce)?,
+            events: rx,
+            watched_dirs: HashSet::new(),
+            targets: Vec::new(),
+            logger: logger,
+        })
+    }
+
+    /// Updates subscriptions to match the specified fetch steps, returning
+    /// the directories that could not be watched.
+    ///
+    /// Failed directories are retried on the next sync.
+    pub fn sync(&mut self, sources: &[(DatasetIDBuf, FetchStep)]) -> Vec<WatchError> {
+        let mut errors = Vec::new();
+        let mut targets = Vec::new();
+        let mut dirs: HashSet<PathBuf> = HashSet::new();
+
+        for (dataset_id, fetch_step) in sources {
+            if let Some(target) = get_watch_target(fetch_step) {
+                dirs.insert(target.dir.clone());
+                targets.push((dataset_id.clone(), target));
+            }
+        }
+
+        let stale: Vec<PathBuf> = self
+            .watched_dirs
+            .iter()
+            .filter(|dir| !dirs.contains(*dir))
+            .cloned()
+            .collect();
+
+        for dir in stale {
+            info!(self.logger, "Unwatching directory"; "dir" => ?dir);
+            // Directory might have been deleted along with its watch
+            let _ = self.watcher.unwatch(&dir);
+            self.watched_dirs.remove(&dir);
+        }
+
+        for dir in dirs {
+            if self.watched_dirs.contains(&dir) {
+                continue;
+            }
+
+            info!(self.logger, "Watching directory"; "dir" => ?dir);
+            match self.watcher.watch(&dir, RecursiveMode::NonRecursive) {
+                Ok(_) => {
+                    self.watched_dirs.insert(dir);
+                }
+                Err(e) => errors.push(WatchError {
+                    dataset_ids: targets
+                        .iter()
+                        .filter(|(_, t)| t.dir == dir)
+                        .map(|(id, _)| id.clone())
+                        .collect(),
+                    dir: dir,
+                    source: e,
+                }),
+            }
+        }
+
+        self.targets = targets;
+        errors
+    }
+
+    /// Waits for up to the specified time for any of the watched files to
+    /// change and returns the affected datasets
+    pub fn poll(&mut self, timeout: Duration) -> Result<Vec<DatasetIDBuf>, WatcherStoppedError> {
+        let mut changed = BTreeSet::new();
+
+        let mut event = match self.events.recv_timeout(timeout) {
+            Ok(event) => Some(event),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => return Err(WatcherStoppedError),
+        };
+
+        // Drain all events that arrived together
+        while let Some(e) = event {
+            debug!(self.logger, "Received file system event"; "event" => ?e);
+
+            let path = match e {
+                DebouncedEvent::Create(p) | DebouncedEvent::Write(p) => Some(p),
+                DebouncedEvent::Rename(_, to) => Some(to),
+                _ => None,
+            };
+
+            if let Some(path) = path {
+                changed.extend(
+                    self.targets
+                        .iter()
+                        .filter(|(_, t)| t.pattern.matches_path(&path))
+                        .map(|(id, _)| id.clone()),
+                );
+            }
+
+            event = self.events.try_recv().ok();
+        }
+
+        Ok(changed.into_iter().collect())
+    }
+}
+
+/// Returns the directory and file pattern to watch for the fetch step,
+/// or `None` if the data does not come from a local file.
+///
+/// Globs are not watched as they are not supported by the fetch service yet.
+pub fn get_watch_target(fetch_step: &FetchStep) -> Option<WatchTarget> {
+    match fetch_step {
+        FetchStep::Url(furl) => {
+            let url = Url::parse(&furl.url).ok()?;
+            if url.scheme() != "file" {
+                return None;
+            }
+
+            let path = url.to_file_path().ok()?;
+            let dir = canonical_or_self(path.parent()?);
+            let path = dir.join(path.file_name()?);
+
+            Some(WatchTarget {
+                dir: dir,
+                pattern: glob::Pattern::new(&glob::Pattern::escape(path.to_str()?)).ok()?,
+            })
+        }
+        FetchStep::FilesGlob(_) => None,
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Errors
+///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Error, Debug)]
+#[error("Failed to watch directory {dir:?}: {source}")]
+pub struct WatchError {
+    pub dir: PathBuf,
+    pub dataset_ids: Vec<DatasetIDBuf>,
+    pub source: notify::Error,
+}
+
+#[derive(Error, Debug)]
+#[error("File watcher has stopped")]
+pub struct WatcherStoppedError;
+
+///////////////////////////////////////////////////////////////////////////////
+
+// Event paths are reported relative to the watched directory, so relative
+// paths and symlinks have to be resolved the same way
+fn canonical_or_self(path: &Path) -> PathBuf {
+    let path = if path.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        path
+    };
+    path.canonicalize().unwrap_or_else(|_| path.to_owned())
+}
@@ -1,24 +1,51 @@
 use crate::domain::*;
 use crate::infra::serde::yaml::*;
+use crate::infra::utils::worker_pool::*;
 use crate::infra::*;
 
+use chrono::{DateTime, Utc};
 use slog::{info, Logger};
 use std::cell::RefCell;
 use std::collections::BTreeMap;
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Limits how much of the pending input data is processed by a single engine call.
+///
+/// Inputs that accumulated more data are transformed in several batches, each
+/// committed as a separate block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransformBatchOptions {
+    /// Maximum number of blocks of each input processed in one batch
+    pub max_input_blocks: Option<usize>,
+    /// Maximum system time span of blocks of each input processed in one batch
+    pub max_interval: Option<chrono::Duration>,
+}
+
+impl Default for TransformBatchOptions {
+    fn default() -> Self {
+        Self {
+            max_input_blocks: None,
+            max_interval: None,
+        }
+    }
+}
 
 pub struct TransformServiceImpl {
     metadata_repo: Rc<RefCell<dyn MetadataRepository>>,
-    engine_factory: Arc<Mutex<EngineFactory>>,
+    engine_factory: Arc<EngineFactory>,
     volume_layout: VolumeLayout,
+    concurrency: ConcurrencyOptions,
+    batching: TransformBatchOptions,
+    cancel: CancellationToken,
     logger: Logger,
 }
 
 impl TransformServiceImpl {
     pub fn new(
         metadata_repo: Rc<RefCell<dyn MetadataRepository>>,
-        engine_factory: Arc<Mutex<EngineFactory>>,
+        engine_factory: Arc<EngineFactory>,
         volume_layout: &VolumeLayout,
         logger: Logger,
     ) -> Self {
@@ -26,20 +53,52 @@ impl TransformServiceImpl {
             metadata_repo: metadata_repo,
             engine_factory: engine_factory,
             volume_layout: volume_layout.clone(),
+            concurrency: ConcurrencyOptions::default(),
+            batching: TransformBatchOptions::default(),
+            cancel: CancellationToken::new(),
             logger: logger,
         }
     }
 
+    pub fn with_concurrency(self, concurrency: ConcurrencyOptions) -> Self {
+        Self {
+            concurrency: concurrency,
+            ..self
+        }
+    }
+
+    pub fn with_batching(self, batching: TransformBatchOptions) -> Self {
+        Self {
+            batching: batching,
+            ..self
+        }
+    }
+
+    /// Stops running engines and discards their output once the token gets cancelled
+    pub fn with_cancellation(self, cancel: CancellationToken) -> Self {
+        Self {
+            cancel: cancel,
+            ..self
+        }
+    }
+
     // Note: Can be called from multiple threads
     fn do_transform(
-        request: ExecuteQueryRequest,
+        requests: Vec<ExecuteQueryRequest>,
         meta_chain: Box<dyn MetadataChain>,
         listener: Arc<Mutex<dyn TransformListener>>,
-        engine_factory: Arc<Mutex<EngineFactory>>,
+        engine_factory: Arc<EngineFactory>,
+        cancel: CancellationToken,
     ) -> Result<TransformResult, TransformError> {
         listener.lock().unwrap().begin();
 
-        match Self::do_transform_inner(request, meta_chain, engine_factory) {
+        match Self::do_transform_batches(
+            requests,
+            meta_chain,
+            listener.clone(),
+            engine_factory,
+            cancel,
+        ) {
             Ok(res) => {
                 listener.lock().unwrap().success(&res);
                 Ok(res)
@@ -52,71 +111,188 @@ impl TransformServiceImpl {
     }
 
     // Note: Can be called from multiple threads
-    fn do_transform_inner(
-        request: ExecuteQueryRequest,
+    fn do_transform_batches(
+        requests: Vec<ExecuteQueryRequest>,
         mut meta_chain: Box<dyn MetadataChain>,
-        engine_factory: Arc<Mutex<EngineFactory>>,
+        listener: Arc<Mutex<dyn TransformListener>>,
+        engine_factory: Arc<EngineFactory>,
+        cancel: CancellationToken,
     ) -> Result<TransformResult, TransformError> {
+        let num_batches = requests.len() as u64;
+        let mut result = TransformResult::UpToDate;
+
+        // Blocks of finished batches stay committed if a later batch fails
+        for (i, request) in requests.into_iter().enumerate() {
+            let started = Instant::now();
+            let (block_hash, batch_stats) = Self::do_transform_inner(
+                request,
+                meta_chain.as_mut(),
+                engine_factory.clone(),
+                &cancel,
+            )?;
+
+            let stage = if num_batches > 1 {
+                format!("transform {}/{}", i + 1, num_batches)
+            } else {
+                "transform".to_owned()
+            };
+            let stage_time = StageTime::since(&stage, started);
+
+            result = match result {
+                TransformResult::UpToDate => TransformResult::Updated {
+                    block_hash: block_hash,
+                    stats: UpdateStats {
+                        stages: vec![stage_time],
+                        ..batch_stats
+                    },
+                },
+                TransformResult::Updated { mut stats, .. } => {
+                    stats.records_added += batch_stats.records_added;
+                    stats.output_intervals.extend(batch_stats.output_intervals);
+                    stats.new_watermark = batch_stats.new_watermark;
+                    stats.output_schema = batch_stats.output_schema.or(stats.output_schema);
+                    stats.stages.push(stage_time);
+                    TransformResult::Updated {
+                        block_hash: block_hash,
+                        stats: stats,
+                    }
+                }
+            };
+
+            listener
+                .lock()
+                .unwrap()
+                .on_batch_progress(i as u64 + 1, num_batches);
+        }
+
+        Ok(result)
+    }
+
+    // Note: Can be called from multiple threads
+    fn do_transform_inner(
+        request: ExecuteQueryRequest,
+        meta_chain: &mut dyn MetadataChain,
+        engine_factory: Arc<EngineFactory>,
+        cancel: &CancellationToken,
+    ) -> Result<(String, UpdateStats), TransformError> {
+        cancel.check()?;
+
         let prev_hash = meta_chain.read_ref(&BlockRef::Head).unwrap();
 
-        let engine = engine_factory
-            .lock()
-            .unwrap()
-            .get_engine(&request.source.transform.engine)?;
+        let engine = engine_factory.get_engine(&request.source.transform.engine)?;
 
-        let result = engine.lock().unwrap().transform(request)?;
+        let data_dir = request.data_dirs.get(&request.dataset_id).cloned();
+
+        let files_before = match data_dir {
+            Some(ref dir) => {
+                ingest::list_parquet_files(dir).map_err(|e| TransformError::internal(e))?
+            }
+            None => Vec::new(),
+        };
+
+        let result = match engine.transform(request, cancel) {
+            Ok(result) => result,
+            Err(err) => {
+                // Engine could've written some output before it failed or got cancelled
+                if let Some(ref dir) = data_dir {
+                    for path in ingest::list_parquet_files(dir)
+                        .unwrap_or_default()
+                        .iter()
+                        .filter(|p| !files_before.contains(p))
+                    {
+                        std::fs::remove_file(path).ok();
+                    }
+                }
+                return Err(match err {
+                    EngineError::Cancelled(e) => TransformError::Cancelled(e),
+                    e => e.into(),
+                });
+            }
+        };
+
+        let data_path = match (data_dir, result.data_file_name) {
+            (Some(dir), Some(file_name)) => Some(dir.join(file_name)),
+            _ => None,
+        };
+
+        let output_schema = match data_path {
+            Some(ref path) if path.exists() => {
+                let schema =
+                    ingest::read_parquet_schema(path).map_err(|e| TransformError::internal(e))?;
+                Some(ingest::format_ddl_schema(&schema))
+            }
+            _ => None,
+        };
 
         let new_block = MetadataBlock {
             prev_block_hash: prev_hash,
             ..result.block
         };
+
+        let prev_watermark = meta_chain
+            .iter_blocks()
+            .filter_map(|b| b.output_watermark)
+            .next();
+
+        let stats = UpdateStats {
+            records_added: new_block
+                .output_slice
+                .as_ref()
+                .map(|s| s.num_records as u64)
+                .unwrap_or(0),
+            output_intervals: new_block
+                .output_slice
+                .iter()
+                .map(|s| s.interval.clone())
+                .collect(),
+            prev_watermark: prev_watermark,
+            new_watermark: new_block.output_watermark.or(prev_watermark),
+            output_schema: output_schema,
+            ..UpdateStats::default()
+        };
+
         let block_hash = meta_chain.append(new_block);
 
-        Ok(TransformResult::Updated {
-            block_hash: block_hash,
-        })
+        Ok((block_hash, stats))
     }
 
     pub fn get_next_operation(
         &self,
         dataset_id: &DatasetID,
     ) -> Result<Option<ExecuteQueryRequest>, DomainError> {
-        let output_chain = self.metadata_repo.borrow().get_metadata_chain(dataset_id)?;
-
-        // TODO: limit traversal depth
-        let mut sources: Vec<_> = output_chain
-            .iter_blocks()
-            .filter_map(|b| b.source)
-            .collect();
-
-        // TODO: source could've changed several times
-        if sources.len() > 1 {
-            unimplemented!("Transform evolution is not yet supported");
-        }
+        Ok(self.get_operations(dataset_id)?.into_iter().next())
+    }
 
-        let source = match sources.pop().unwrap() {
-            DatasetSource::Derivative(src) => src,
-            _ => panic!("Transform called on non-derivative dataset {}", dataset_id),
-        };
+    /// Splits all pending input data into batches according to the batching options
+    pub fn get_operations(
+        &self,
+        dataset_id: &DatasetID,
+    ) -> Result<Vec<ExecuteQueryRequest>, DomainError> {
+        let output_chain = self.metadata_repo.borrow().get_metadata_chain(dataset_id)?;
+        let source = self.get_source(dataset_id)?;
 
         let mut non_empty = 0;
-        let input_slices: BTreeMap<_, _> = source
+        let pending_inputs: Vec<_> = source
             .inputs
             .iter()
             .enumerate()
             .map(|(index, input_id)| {
-                let (slice, empty) = self
-                    .get_input_slice(index, input_id, output_chain.as_ref())
+                let (blocks, iv_unprocessed, empty) = self
+                    .get_pending_blocks(index, input_id, output_chain.as_ref())
                     .unwrap();
 
                 if !empty {
                     non_empty += 1;
                 }
 
-                (input_id.clone(), slice)
+                (blocks, iv_unprocessed)
             })
             .collect();
 
+        if non_empty == 0 {
+            return Ok(Vec::new());
+        }
+
         let mut vocabs: BTreeMap<_, _> = source
             .inputs
             .iter()
@@ -156,27 +332,56 @@ impl TransformServiceImpl {
 
         data_dirs.insert(dataset_id.to_owned(), output_layout.data_dir);
 
-        if non_empty > 0 {
-            Ok(Some(ExecuteQueryRequest {
-                dataset_id: dataset_id.to_owned(),
-                checkpoints_dir: output_layout.checkpoints_dir, // TODO: move down a layer
-                source: source,
-                dataset_vocabs: vocabs,
-                input_slices: input_slices,
-                data_dirs: data_dirs, // TODO: move down a layer
-            }))
-        } else {
-            Ok(None)
+        Ok(self
+            .split_into_batches(pending_inputs)
+            .into_iter()
+            .map(|batch| {
+                let input_slices: BTreeMap<_, _> = source
+                    .inputs
+                    .iter()
+                    .cloned()
+                    .zip(batch.into_iter())
+                    .collect();
+
+                ExecuteQueryRequest {
+                    dataset_id: dataset_id.to_owned(),
+                    checkpoints_dir: output_layout.checkpoints_dir.clone(), // TODO: move down a layer
+                    source: source.clone(),
+                    dataset_vocabs: vocabs.clone(),
+                    input_slices: input_slices,
+                    data_dirs: data_dirs.clone(), // TODO: move down a layer
+                }
+            })
+            .collect())
+    }
+
+    fn get_source(&self, dataset_id: &DatasetID) -> Result<DatasetSourceDerivative, DomainError> {
+        let output_chain = self.metadata_repo.borrow().get_metadata_chain(dataset_id)?;
+
+        // TODO: limit traversal depth
+        let mut sources: Vec<_> = output_chain
+            .iter_blocks()
+            .filter_map(|b| b.source)
+            .collect();
+
+        // TODO: source could've changed several times
+        if sources.len() > 1 {
+            unimplemented!("Transform evolution is not yet supported");
+        }
+
+        match sources.pop().unwrap() {
+            DatasetSource::Derivative(src) => Ok(src),
+            _ => panic!("Transform called on non-derivative dataset {}", dataset_id),
         }
     }
 
     // TODO: Avoid iterating through output chain multiple times
-    fn get_input_slice(
+    fn get_pending_blocks(
         &self,
         index: usize,
         dataset_id: &DatasetID,
         output_chain: &dyn MetadataChain,
-    ) -> Result<(InputDataSlice, bool), DomainError> {
+    ) -> Result<(Vec<MetadataBlock>, TimeInterval, bool), DomainError> {
         // Determine processed data range
         // Result is either: () or (inf, upper] or (lower, upper]
         let iv_processed = output_chain
@@ -192,42 +397,118 @@ impl TransformServiceImpl {
 
         let input_chain = self.metadata_repo.borrow().get_metadata_chain(dataset_id)?;
 
-        // Filter unprocessed input blocks
-        let blocks_unprocessed: Vec<_> = input_chain
+        // Filter unprocessed input blocks, oldest first
+        let mut blocks_unprocessed: Vec<_> = input_chain
             .iter_blocks()
             .take_while(|b| iv_unprocessed.contains_point(&b.system_time))
             .collect();
+        blocks_unprocessed.reverse();
 
-        // Determine available data/watermark range
-        // Result is either: () or (-inf, upper]
-        let iv_available = blocks_unprocessed
-            .first()
-            .map(|b| TimeInterval::unbounded_closed_right(b.system_time.clone()))
-            .unwrap_or(TimeInterval::empty());
+        let empty = !blocks_unprocessed
+            .iter()
+            .any(|b| b.output_slice.is_some() || b.output_watermark.is_some());
 
-        // Result is either: () or (lower, upper]
-        let iv_to_process = iv_available.intersect(&iv_unprocessed);
+        Ok((blocks_unprocessed, iv_unprocessed, empty))
+    }
 
-        let explicit_watermarks: Vec<_> = blocks_unprocessed
+    /// Splits pending blocks of all inputs into batches of input slices.
+    ///
+    /// All inputs are cut at the same system time, so every batch sees the inputs
+    /// as they were at one point in time. The cut is placed where the first of the
+    /// inputs reaches the batching limits.
+    fn split_into_batches(
+        &self,
+        pending_inputs: Vec<(Vec<MetadataBlock>, TimeInterval)>,
+    ) -> Vec<Vec<InputDataSlice>> {
+        let mut pending: Vec<(&[MetadataBlock], TimeInterval)> = pending_inputs
             .iter()
-            .rev()
-            .filter(|b| b.output_watermark.is_some())
-            .map(|b| Watermark {
-                system_time: b.system_time.clone(),
-                event_time: b.output_watermark.unwrap().clone(),
-            })
+            .map(|(blocks, iv_unprocessed)| (&blocks[..], iv_unprocessed.clone()))
             .collect();
 
-        let empty = !blocks_unprocessed.iter().any(|b| b.output_slice.is_some())
-            && explicit_watermarks.is_empty();
+        let mut batches = Vec::new();
+
+        while pending.iter().any(|(blocks, _)| !blocks.is_empty()) {
+            let cut = pending
+                .iter()
+                .filter_map(|(blocks, _)| self.get_batch_cut(blocks))
+                .min();
 
-        Ok((
-            InputDataSlice {
-                interval: iv_to_process,
-                explicit_watermarks: explicit_watermarks,
-            },
-            empty,
-        ))
+            let mut slices = Vec::new();
+
+            for (blocks, iv_unprocessed) in pending.iter_mut() {
+                let rest: &[MetadataBlock] = *blocks;
+                let len = match cut {
+                    Some(cut) => rest.iter().take_while(|b| b.system_time <= cut).count(),
+                    None => rest.len(),
+                };
+                let (batch, tail) = rest.split_at(len);
+                *blocks = tail;
+
+                // Inputs that have no blocks before the cut get empty slices
+                if batch.is_empty() {
+                    slices.push(InputDataSlice {
+                        interval: TimeInterval::empty(),
+                        explicit_watermarks: Vec::new(),
+                    });
+                    continue;
+                }
+
+                // Determine available data/watermark range
+                // Result is either: (-inf, upper] or (lower, upper]
+                let iv_to_process =
+                    TimeInterval::unbounded_closed_right(batch.last().unwrap().system_time.clone())
+                        .intersect(iv_unprocessed);
+
+                let explicit_watermarks: Vec<_> = batch
+                    .iter()
+                    .filter(|b| b.output_watermark.is_some())
+                    .map(|b| Watermark {
+                        system_time: b.system_time.clone(),
+                        event_time: b.output_watermark.unwrap().clone(),
+                    })
+                    .collect();
+
+                *iv_unprocessed = iv_to_process.right_complement();
+
+                slices.push(InputDataSlice {
+                    interval: iv_to_process,
+                    explicit_watermarks: explicit_watermarks,
+                });
+            }
+
+            batches.push(slices);
+        }
+
+        batches
+    }
+
+    /// Returns system time of the last block that fits into the next batch of an input,
+    /// or `None` if all its blocks fit
+    fn get_batch_cut(&self, blocks: &[MetadataBlock]) -> Option<DateTime<Utc>> {
+        let mut len = blocks.len();
+
+        if let Some(max_blocks) = self.batching.max_input_blocks {
+            len = len.min(max_blocks.max(1));
+        }
+
+        if let Some(max_interval) = self.batching.max_interval {
+            if let Some(first) = blocks.first() {
+                let limit = first.system_time + max_interval;
+                len = len.min(
+                    blocks
+                        .iter()
+                        .take_while(|b| b.system_time <= limit)
+                        .count()
+                        .max(1),
+                );
+            }
+        }
+
+        if len < blocks.len() {
+            Some(blocks[len - 1].system_time.clone())
+        } else {
+            None
+        }
     }
 
     fn update_summary(
@@ -237,26 +518,42 @@ impl TransformServiceImpl {
     ) -> Result<(), TransformError> {
         match result {
             TransformResult::UpToDate => Ok(()),
-            TransformResult::Updated { block_hash } => {
+            TransformResult::Updated { block_hash, stats } => {
                 let mut metadata_repo = self.metadata_repo.borrow_mut();
 
                 let mut summary = metadata_repo
                     .get_summary(dataset_id)
                     .map_err(|e| TransformError::internal(e))?;
 
-                let block = metadata_repo
-                    .get_metadata_chain(dataset_id)
-                    .unwrap()
-                    .get_block(block_hash)
-                    .unwrap();
+                let chain = metadata_repo.get_metadata_chain(dataset_id).unwrap();
+                let block = chain.get_block(block_hash).unwrap();
 
-                summary.num_records = match block.output_slice {
-                    Some(slice) => summary.num_records + slice.num_records as u64,
-                    _ => 0,
-                };
+                // Every batch commits its own block, including those of earlier pulls
+                // that failed part way
+                let last_pulled = summary.last_pulled;
+                let new_records: u64 = chain
+                    .iter_blocks()
+                    .take_while(|b| last_pulled.map(|t| b.system_time > t).unwrap_or(true))
+                    .filter_map(|b| b.output_slice)
+                    .map(|slice| slice.num_records as u64)
+                    .sum();
 
+                summary.num_records += new_records;
                 summary.last_pulled = Some(block.system_time);
 
+                if let Some(ref schema) = stats.output_schema {
+                    metadata_repo
+                        .record_schema(
+                            dataset_id,
+                            SchemaRecord {
+                                block_hash: block_hash.clone(),
+                                system_time: block.system_time,
+                                schema: schema.clone(),
+                            },
+                        )
+                        .map_err(|e| TransformError::internal(e))?;
+                }
+
                 let layout = DatasetLayout::new(&self.volume_layout, dataset_id);
                 summary.data_size = fs_extra::dir::get_size(layout.data_dir).unwrap_or(0);
                 summary.data_size += fs_extra::dir::get_size(layout.checkpoints_dir).unwrap_or(0);
@@ -280,24 +577,29 @@ impl TransformService for TransformServiceImpl {
 
         info!(self.logger, "Transforming single dataset"; "dataset" => dataset_id.as_str());
 
-        // TODO: There might be more operations to do
-        if let Some(request) = self
-            .get_next_operation(dataset_id)
-            .map_err(|e| TransformError::internal(e))?
-        {
-            let meta_chain = self
-                .metadata_repo
-                .borrow()
-                .get_metadata_chain(&dataset_id)
-                .unwrap();
+        let requests = self
+            .get_operations(dataset_id)
+            .map_err(|e| TransformError::internal(e))?;
 
-            let res =
-                Self::do_transform(request, meta_chain, listener, self.engine_factory.clone())?;
-            self.update_summary(dataset_id, &res)?;
-            Ok(res)
-        } else {
-            Ok(TransformResult::UpToDate)
+        if requests.is_empty() {
+            return Ok(TransformResult::UpToDate);
         }
+
+        let meta_chain = self
+            .metadata_repo
+            .borrow()
+            .get_metadata_chain(&dataset_id)
+            .unwrap();
+
+        let res = Self::do_transform(
+            requests,
+            meta_chain,
+            listener,
+            self.engine_factory.clone(),
+            self.cancel.clone(),
+        )?;
+        self.update_summary(dataset_id, &res)?;
+        Ok(res)
     }
 
     fn transform_multi(
@@ -305,72 +607,94 @@ impl TransformService for TransformServiceImpl {
         dataset_ids: &mut dyn Iterator<Item = &DatasetID>,
         maybe_multi_listener: Option<Arc<Mutex<dyn TransformMultiListener>>>,
     ) -> Vec<(DatasetIDBuf, Result<TransformResult, TransformError>)> {
-        let null_multi_listener = Arc::new(Mutex::new(NullTransformMultiListener {}));
-        let multi_listener = maybe_multi_listener.unwrap_or(null_multi_listener);
-
         let dataset_ids_owned: Vec<_> = dataset_ids.map(|id| id.to_owned()).collect();
         info!(self.logger, "Transforming multiple datasets"; "datasets" => ?dataset_ids_owned);
 
-        // TODO: handle errors without crashing
-        let requests: Vec<_> = dataset_ids_owned
-            .into_iter()
-            .map(|dataset_id| {
-                let next_op = self
-                    .get_next_operation(&dataset_id)
-                    .map_err(|e| TransformError::internal(e))
-                    .unwrap();
-                (dataset_id, next_op)
-            })
+        let tasks: Vec<_> = dataset_ids_owned
+            .iter()
+            .map(
+                |id| match self.prepare_transform(id, maybe_multi_listener.clone()) {
+                    Ok(job) => (job.engine, job.run),
+                    Err(err) => (None, Box::new(move || Err(err)) as Task<_>),
+                },
+            )
             .collect();
 
-        let mut results: Vec<(DatasetIDBuf, Result<TransformResult, TransformError>)> =
-            Vec::with_capacity(requests.len());
+        let results = run_bounded(tasks, &self.concurrency, "transform_multi");
 
-        let thread_handles: Vec<_> = requests
+        dataset_ids_owned
             .into_iter()
-            .filter_map(|(dataset_id, maybe_request)| match maybe_request {
-                None => {
-                    results.push((dataset_id, Ok(TransformResult::UpToDate)));
-                    None
-                }
-                Some(request) => {
-                    let null_listener = Arc::new(Mutex::new(NullTransformListener {}));
-                    let listener = multi_listener
-                        .lock()
-                        .unwrap()
-                        .begin_transform(&dataset_id)
-                        .unwrap_or(null_listener);
-                    let meta_chain = self
-                        .metadata_repo
-                        .borrow()
-                        .get_metadata_chain(&dataset_id)
-                        .unwrap();
-                    let engine_factory = self.engine_factory.clone();
-
-                    let thread_handle = std::thread::Builder::new()
-                        .name("transform_multi".to_owned())
-                        .spawn(move || {
-                            let res =
-                                Self::do_transform(request, meta_chain, listener, engine_factory);
-                            (dataset_id, res)
-                        })
-                        .unwrap();
-
-                    Some(thread_handle)
-                }
+            .zip(results)
+            .map(|(id, res)| {
+                let res = self.complete_transform(&id, res);
+                (id, res)
             })
-            .collect();
+            .collect()
+    }
 
-        results.extend(thread_handles.into_iter().map(|h| h.join().unwrap()));
+    fn plan_transform(&mut self, dataset_id: &DatasetID) -> Result<TransformPlan, TransformError> {
+        let requests = self
+            .get_operations(dataset_id)
+            .map_err(|e| TransformError::internal(e))?;
+
+        Ok(TransformPlan {
+            engine: self
+                .get_source(dataset_id)
+                .map_err(|e| TransformError::internal(e))?
+                .transform
+                .engine,
+            batches: requests.into_iter().map(|r| r.input_slices).collect(),
+        })
+    }
 
-        results
-            .iter()
-            .filter(|(_, res)| res.is_ok())
-            .for_each(|(dataset_id, res)| {
-                self.update_summary(dataset_id, res.as_ref().unwrap())
-                    .unwrap()
+    fn prepare_transform(
+        &mut self,
+        dataset_id: &DatasetID,
+        maybe_multi_listener: Option<Arc<Mutex<dyn TransformMultiListener>>>,
+    ) -> Result<TransformJob, TransformError> {
+        let null_multi_listener = Arc::new(Mutex::new(NullTransformMultiListener {}));
+        let multi_listener = maybe_multi_listener.unwrap_or(null_multi_listener);
+
+        let requests = self
+            .get_operations(dataset_id)
+            .map_err(|e| TransformError::internal(e))?;
+
+        if requests.is_empty() {
+            return Ok(TransformJob {
+                engine: None,
+                run: Box::new(|| Ok(TransformResult::UpToDate)),
             });
+        }
 
-        results
+        let null_listener = Arc::new(Mutex::new(NullTransformListener {}));
+        let listener = multi_listener
+            .lock()
+            .unwrap()
+            .begin_transform(dataset_id)
+            .unwrap_or(null_listener);
+        let meta_chain = self
+            .metadata_repo
+            .borrow()
+            .get_metadata_chain(dataset_id)
+            .map_err(|e| TransformError::internal(e))?;
+        let engine_factory = self.engine_factory.clone();
+        let cancel = self.cancel.clone();
+
+        Ok(TransformJob {
+            engine: Some(requests[0].source.transform.engine.clone()),
+            run: Box::new(move || {
+                Self::do_transform(requests, meta_chain, listener, engine_factory, cancel)
+            }),
+        })
+    }
+
+    fn complete_transform(
+        &mut self,
+        dataset_id: &DatasetID,
+        result: Result<TransformResult, TransformError>,
+    ) -> Result<TransformResult, TransformError> {
+        let result = result?;
+        self.update_summary(dataset_id, &result)?;
+        Ok(result)
     }
 }
@@ -1,5 +1,7 @@
+use crate::domain::CancellationToken;
+
 use std::path::PathBuf;
-use std::process::{Command, Stdio};
+use std::process::{Child, Command, ExitStatus, Stdio};
 use std::time::{Duration, Instant};
 
 use std::backtrace::Backtrace;
@@ -192,6 +194,39 @@ impl DockerClient {
         cmd
     }
 
+    /// Waits for a process that runs or executes in the container to exit.
+    ///
+    /// When the token gets cancelled the container is killed and `None` is returned.
+    pub fn wait_cancellable(
+        &self,
+        process: &mut Child,
+        container_name: &str,
+        cancel: &CancellationToken,
+    ) -> Result<Option<ExitStatus>, std::io::Error> {
+        loop {
+            if let Some(status) = process.try_wait()? {
+                // Ctrl+C reaches the child too, so it can exit before we get to check the token
+                if !status.success() && cancel.is_cancelled() {
+                    self.kill_cmd(container_name)
+                        .stdout(Stdio::null())
+                        .stderr(Stdio::null())
+                        .status()?;
+                    return Ok(None);
+                }
+                return Ok(Some(status));
+            }
+            if cancel.is_cancelled() {
+                self.kill_cmd(container_name)
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .status()?;
+                process.wait()?;
+                return Ok(None);
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+
     pub fn create_network_cmd(&self, network_name: &str) -> Command {
         let mut cmd = Command::new("docker");
         cmd.arg("network").arg("create").arg(network_name);
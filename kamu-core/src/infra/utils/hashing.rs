@@ -0,0 +1,44 @@
+use crypto::digest::Digest;
+use crypto::sha2::Sha256;
+use crypto::sha3::Sha3;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Computes SHA3-256 digest of the file contents
+pub fn file_sha3(path: &Path) -> Result<String, std::io::Error> {
+    file_digest(Sha3::sha3_256(), path)
+}
+
+/// Computes SHA3-256 digest of the contents of several files as if they were
+/// concatenated, so for a single file it matches `file_sha3`
+pub fn files_sha3(paths: &[PathBuf]) -> Result<String, std::io::Error> {
+    let mut digest = Sha3::sha3_256();
+    for path in paths {
+        digest_file(&mut digest, path)?;
+    }
+    Ok(digest.result_str())
+}
+
+/// Computes SHA-256 digest of the file contents
+pub fn file_sha256(path: &Path) -> Result<String, std::io::Error> {
+    file_digest(Sha256::new(), path)
+}
+
+fn file_digest(mut digest: impl Digest, path: &Path) -> Result<String, std::io::Error> {
+    digest_file(&mut digest, path)?;
+    Ok(digest.result_str())
+}
+
+fn digest_file(digest: &mut impl Digest, path: &Path) -> Result<(), std::io::Error> {
+    let mut file = File::open(path)?;
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        digest.input(&buf[..read]);
+    }
+    Ok(())
+}
@@ -1 +1,3 @@
 pub mod docker_client;
+pub mod hashing;
+pub mod worker_pool;
@@ -0,0 +1,310 @@
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::panic::AssertUnwindSafe;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+
+pub const DEFAULT_JOBS: usize = 4;
+
+/// Limits how many datasets are processed at the same time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConcurrencyOptions {
+    /// Maximum number of datasets processed in parallel
+    pub jobs: usize,
+    /// Maximum number of datasets processed in parallel by a specific engine
+    pub engine_jobs: HashMap<String, usize>,
+}
+
+impl Default for ConcurrencyOptions {
+    fn default() -> Self {
+        Self {
+            jobs: DEFAULT_JOBS,
+            engine_jobs: HashMap::new(),
+        }
+    }
+}
+
+pub type Task<R> = Box<dyn FnOnce() -> R + Send>;
+
+/// Runs tasks on a bounded number of threads and returns their results in
+/// the original order.
+///
+/// Every task can be associated with an engine, in which case it will only
+/// start when the engine's own limit allows it. Tasks that are blocked by
+/// an engine limit don't prevent the following ones from starting.
+pub fn run_bounded<R>(
+    tasks: Vec<(Option<String>, Task<R>)>,
+    options: &ConcurrencyOptions,
+    thread_name: &str,
+) -> Vec<R>
+where
+    R: Send + 'static,
+{
+    let num_tasks = tasks.len();
+    let pool = Pool::new(options, num_tasks, thread_name);
+
+    for (index, (engine, task)) in tasks.into_iter().enumerate() {
+        pool.submit(index, engine, task);
+    }
+    pool.close();
+
+    let mut results: Vec<Option<R>> = (0..num_tasks).map(|_| None).collect();
+    for _ in 0..num_tasks {
+        let (index, result) = pool.recv();
+        results[index] = Some(result);
+    }
+
+    pool.join();
+
+    results.into_iter().map(|r| r.unwrap()).collect()
+}
+
+/// Outcome of running a graph of tasks
+pub struct GraphResults<K, T, E> {
+    /// Results of the nodes that ran, in the order they finished
+    pub finished: Vec<(K, Result<T, E>)>,
+    /// Nodes that did not run, along with the failed node that blocked them
+    pub blocked: Vec<(K, K)>,
+}
+
+/// Runs tasks of a dependency graph on a bounded number of threads.
+///
+/// Nodes are given along with their dependencies, dependencies outside of the
+/// graph are ignored. A node's task is created by `start` as soon as all of
+/// its dependencies have finished successfully, and the outcome of the task
+/// is passed to `finish`. Both are called on the calling thread.
+///
+/// When a node fails all nodes that depend on it, directly or transitively,
+/// are not started and are returned as blocked, while the rest of the graph
+/// keeps running. Concurrency limits are applied the same way as in `run_bounded`.
+pub fn run_graph<K, R, T, E>(
+    nodes: Vec<(K, Vec<K>)>,
+    options: &ConcurrencyOptions,
+    thread_name: &str,
+    mut start: impl FnMut(&K) -> (Option<String>, Task<R>),
+    mut finish: impl FnMut(&K, R) -> Result<T, E>,
+) -> GraphResults<K, T, E>
+where
+    K: Clone + Eq + Hash,
+    R: Send + 'static,
+{
+    let num_nodes = nodes.len();
+
+    let index: HashMap<K, usize> = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, (key, _))| (key.clone(), i))
+        .collect();
+
+    let mut num_pending_deps = vec![0; num_nodes];
+    let mut dependents: Vec<Vec<usize>> = vec![Vec::new(); num_nodes];
+    for (i, (_, deps)) in nodes.iter().enumerate() {
+        for dep in deps.iter().filter_map(|d| index.get(d)) {
+            num_pending_deps[i] += 1;
+            dependents[*dep].push(i);
+        }
+    }
+
+    let keys: Vec<K> = nodes.into_iter().map(|(key, _)| key).collect();
+    let mut blocked_by: Vec<Option<usize>> = vec![None; num_nodes];
+
+    let pool = Pool::new(options, num_nodes, thread_name);
+    let mut num_running = 0;
+
+    for i in (0..num_nodes).filter(|i| num_pending_deps[*i] == 0) {
+        let (engine, task) = start(&keys[i]);
+        pool.submit(i, engine, task);
+        num_running += 1;
+    }
+
+    let mut finished = Vec::with_capacity(num_nodes);
+
+    while num_running != 0 {
+        let (i, result) = pool.recv();
+        num_running -= 1;
+
+        let result = finish(&keys[i], result);
+
+        if result.is_ok() {
+            for d in dependents[i].iter().cloned() {
+                num_pending_deps[d] -= 1;
+                if num_pending_deps[d] == 0 && blocked_by[d].is_none() {
+                    let (engine, task) = start(&keys[d]);
+                    pool.submit(d, engine, task);
+                    num_running += 1;
+                }
+            }
+        } else {
+            let mut to_block = dependents[i].clone();
+            while let Some(d) = to_block.pop() {
+                if blocked_by[d].is_none() {
+                    blocked_by[d] = Some(i);
+                    to_block.extend(dependents[d].iter().cloned());
+                }
+            }
+        }
+
+        finished.push((keys[i].clone(), result));
+    }
+
+    pool.close();
+    pool.join();
+
+    let blocked = blocked_by
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, b)| b.map(|b| (keys[i].clone(), keys[b].clone())))
+        .collect();
+
+    GraphResults {
+        finished: finished,
+        blocked: blocked,
+    }
+}
+
+// Worker threads that execute submitted tasks until the pool gets closed
+struct Pool<R> {
+    state: Arc<(Mutex<PoolState<R>>, Condvar)>,
+    results: Receiver<(usize, std::thread::Result<R>)>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl<R> Pool<R>
+where
+    R: Send + 'static,
+{
+    fn new(options: &ConcurrencyOptions, max_tasks: usize, thread_name: &str) -> Self {
+        let num_workers = options.jobs.max(1).min(max_tasks);
+
+        let state = Arc::new((
+            Mutex::new(PoolState {
+                queue: VecDeque::new(),
+                running: HashMap::new(),
+                engine_jobs: options.engine_jobs.clone(),
+                closed: false,
+            }),
+            Condvar::new(),
+        ));
+
+        let (tx, rx) = channel();
+
+        let workers = (0..num_workers)
+            .map(|_| {
+                let state = state.clone();
+                let tx = tx.clone();
+                std::thread::Builder::new()
+                    .name(thread_name.to_owned())
+                    .spawn(move || Self::work(&state, tx))
+                    .unwrap()
+            })
+            .collect();
+
+        Self {
+            state: state,
+            results: rx,
+            workers: workers,
+        }
+    }
+
+    fn work(state: &(Mutex<PoolState<R>>, Condvar), tx: Sender<(usize, std::thread::Result<R>)>) {
+        let (lock, cvar) = state;
+        loop {
+            let (index, engine, task) = {
+                let mut state = lock.lock().unwrap();
+                loop {
+                    if let Some(next) = state.take_next() {
+                        break next;
+                    }
+                    if state.queue.is_empty() && state.closed {
+                        return;
+                    }
+                    state = cvar.wait(state).unwrap();
+                }
+            };
+
+            // Releases the engine slot even if the task panics
+            let _guard = RunningGuard {
+                state: state,
+                engine: engine,
+            };
+
+            // Panics are passed to the calling thread so it doesn't wait forever
+            let result = std::panic::catch_unwind(AssertUnwindSafe(task));
+            tx.send((index, result)).unwrap();
+        }
+    }
+
+    fn submit(&self, index: usize, engine: Option<String>, task: Task<R>) {
+        let (lock, cvar) = &*self.state;
+        lock.lock().unwrap().queue.push_back((index, engine, task));
+        cvar.notify_all();
+    }
+
+    // Lets workers exit once all submitted tasks are done
+    fn close(&self) {
+        let (lock, cvar) = &*self.state;
+        lock.lock().unwrap().closed = true;
+        cvar.notify_all();
+    }
+
+    // Waits for the next task to finish
+    fn recv(&self) -> (usize, R) {
+        let (index, result) = self.results.recv().unwrap();
+        match result {
+            Ok(result) => (index, result),
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    }
+
+    fn join(self) {
+        for worker in self.workers {
+            worker.join().unwrap();
+        }
+    }
+}
+
+struct PoolState<R> {
+    queue: VecDeque<(usize, Option<String>, Task<R>)>,
+    running: HashMap<String, usize>,
+    engine_jobs: HashMap<String, usize>,
+    closed: bool,
+}
+
+impl<R> PoolState<R> {
+    // Takes the first task whose engine is below its limit
+    fn take_next(&mut self) -> Option<(usize, Option<String>, Task<R>)> {
+        let running = &self.running;
+        let engine_jobs = &self.engine_jobs;
+
+        let pos = self.queue.iter().position(|(_, engine, _)| match engine {
+            None => true,
+            Some(engine) => match engine_jobs.get(engine) {
+                None => true,
+                Some(limit) => running.get(engine).cloned().unwrap_or(0) < (*limit).max(1),
+            },
+        })?;
+
+        let next = self.queue.remove(pos).unwrap();
+        if let Some(ref engine) = next.1 {
+            *self.running.entry(engine.clone()).or_insert(0) += 1;
+        }
+        Some(next)
+    }
+}
+
+struct RunningGuard<'a, R> {
+    state: &'a (Mutex<PoolState<R>>, Condvar),
+    engine: Option<String>,
+}
+
+impl<'a, R> Drop for RunningGuard<'a, R> {
+    fn drop(&mut self) {
+        let (lock, cvar) = self.state;
+        if let Some(ref engine) = self.engine {
+            let mut state = lock.lock().unwrap_or_else(|e| e.into_inner());
+            *state.running.get_mut(engine).unwrap() -= 1;
+        }
+        cvar.notify_all();
+    }
+}
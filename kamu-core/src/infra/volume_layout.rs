@@ -11,6 +11,8 @@ pub struct VolumeLayout {
     pub data_dir: PathBuf,
     /// Stores data that is not essential but can improve performance of operations like data polling
     pub cache_dir: PathBuf,
+    /// Stores records that were rejected by data quality expectations
+    pub quarantine_dir: PathBuf,
 }
 
 impl VolumeLayout {
@@ -20,6 +22,7 @@ impl VolumeLayout {
             checkpoints_dir: volume_root.join("checkpoints"),
             data_dir: volume_root.join("data"),
             cache_dir: volume_root.join("cache"),
+            quarantine_dir: volume_root.join("quarantine"),
         }
     }
 
@@ -0,0 +1,71 @@
+use crate::domain::*;
+use crate::infra::serde::yaml::*;
+
+use chrono::{DateTime, Utc};
+use slog::{info, Logger};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub struct WatermarkServiceImpl {
+    metadata_repo: Rc<RefCell<dyn MetadataRepository>>,
+    logger: Logger,
+}
+
+impl WatermarkServiceImpl {
+    pub fn new(metadata_repo: Rc<RefCell<dyn MetadataRepository>>, logger: Logger) -> Self {
+        Self {
+            metadata_repo: metadata_repo,
+            logger: logger,
+        }
+    }
+}
+
+impl WatermarkService for WatermarkServiceImpl {
+    fn set_watermark(
+        &mut self,
+        dataset_id: &DatasetID,
+        watermark: DateTime<Utc>,
+    ) -> Result<WatermarkResult, WatermarkError> {
+        let summary = self.metadata_repo.borrow().get_summary(dataset_id)?;
+        if summary.kind != DatasetKind::Root {
+            return Err(WatermarkError::NotARootDataset {
+                dataset_id: dataset_id.to_owned(),
+            });
+        }
+
+        let mut chain = self.metadata_repo.borrow().get_metadata_chain(dataset_id)?;
+
+        let current = chain
+            .iter_blocks()
+            .filter_map(|b| b.output_watermark)
+            .next();
+        match current {
+            Some(current) if current > watermark => {
+                return Err(WatermarkError::NotMonotonic {
+                    current: current,
+                    watermark: watermark,
+                })
+            }
+            Some(current) if current == watermark => return Ok(WatermarkResult::UpToDate),
+            _ => (),
+        }
+
+        let prev_hash = chain.read_ref(&BlockRef::Head).unwrap();
+
+        let block_hash = chain.append(MetadataBlock {
+            block_hash: "".to_owned(),
+            prev_block_hash: prev_hash,
+            system_time: Utc::now(),
+            output_slice: None,
+            output_watermark: Some(watermark),
+            input_slices: None,
+            source: None,
+        });
+
+        info!(self.logger, "Committed new watermark"; "dataset" => dataset_id.as_str(), "watermark" => %watermark, "hash" => &block_hash);
+
+        Ok(WatermarkResult::Updated {
+            block_hash: block_hash,
+        })
+    }
+}
@@ -11,6 +11,8 @@ pub struct WorkspaceLayout {
     pub remotes_dir: PathBuf,
     /// Directory for storing per-run diagnostics information and logs
     pub run_info_dir: PathBuf,
+    /// Contains secrets referenced by dataset sources, one file per secret
+    pub secrets_dir: PathBuf,
     /// Root directory of a local storage volume
     pub local_volume_dir: PathBuf,
 }
@@ -22,6 +24,7 @@ impl WorkspaceLayout {
             datasets_dir: kamu_root_dir.join("datasets"),
             remotes_dir: kamu_root_dir.join("remotes"),
             run_info_dir: kamu_root_dir.join("run"),
+            secrets_dir: kamu_root_dir.join("secrets"),
             kamu_root_dir: kamu_root_dir,
             local_volume_dir: workspace_root.join(".kamu.local"),
         }
@@ -11,7 +11,7 @@ use parquet::{
 use std::cell::RefCell;
 use std::fs::File;
 use std::rc::Rc;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 
 #[test]
 fn test_ingest_with_engine() {
@@ -23,7 +23,8 @@ fn test_ingest_with_engine() {
     let metadata_repo = Rc::new(RefCell::new(MetadataRepositoryImpl::new(&workspace_layout)));
     let ingest_svc = Rc::new(RefCell::new(IngestServiceImpl::new(
         metadata_repo.clone(),
-        Arc::new(Mutex::new(EngineFactory::new(&workspace_layout))),
+        Arc::new(EngineFactory::new(&workspace_layout)),
+        &workspace_layout,
         &volume_layout,
         slog::Logger::root(slog::Discard, slog::o!()),
     )));
@@ -11,7 +11,7 @@ use parquet::{
 use std::cell::RefCell;
 use std::fs::File;
 use std::rc::Rc;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 
 #[test]
 fn test_transform_with_engine_spark() {
@@ -21,11 +21,12 @@ fn test_transform_with_engine_spark() {
     let volume_layout = VolumeLayout::new(&workspace_layout.local_volume_dir);
 
     let metadata_repo = Rc::new(RefCell::new(MetadataRepositoryImpl::new(&workspace_layout)));
-    let engine_factory = Arc::new(Mutex::new(EngineFactory::new(&workspace_layout)));
+    let engine_factory = Arc::new(EngineFactory::new(&workspace_layout));
 
     let mut ingest_svc = IngestServiceImpl::new(
         metadata_repo.clone(),
         engine_factory.clone(),
+        &workspace_layout,
         &volume_layout,
         slog::Logger::root(slog::Discard, slog::o!()),
     );
@@ -188,11 +189,12 @@ fn test_transform_with_engine_flink() {
     let volume_layout = VolumeLayout::new(&workspace_layout.local_volume_dir);
 
     let metadata_repo = Rc::new(RefCell::new(MetadataRepositoryImpl::new(&workspace_layout)));
-    let engine_factory = Arc::new(Mutex::new(EngineFactory::new(&workspace_layout)));
+    let engine_factory = Arc::new(EngineFactory::new(&workspace_layout));
 
     let mut ingest_svc = IngestServiceImpl::new(
         metadata_repo.clone(),
         engine_factory.clone(),
+        &workspace_layout,
         &volume_layout,
         slog::Logger::root(slog::Discard, slog::o!()),
     );
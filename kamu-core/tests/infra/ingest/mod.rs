@@ -1,2 +1,5 @@
+mod test_expectations;
 mod test_fetch;
+mod test_merge;
 mod test_prep;
+mod test_read;
@@ -0,0 +1,139 @@
+use kamu::domain::*;
+use kamu::infra::ingest::*;
+use kamu::infra::serde::yaml::*;
+
+use arrow::array::{Array, Int64Array, StringArray};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::record_batch::RecordBatch;
+use std::sync::Arc;
+
+fn new_data(rows: &[(Option<&str>, i64)]) -> RecordBatch {
+    RecordBatch::try_new(
+        Arc::new(Schema::new(vec![
+            Field::new("code", DataType::Utf8, true),
+            Field::new("value", DataType::Int64, true),
+        ])),
+        vec![
+            Arc::new(StringArray::from(
+                rows.iter().map(|r| r.0).collect::<Vec<_>>(),
+            )),
+            Arc::new(Int64Array::from(
+                rows.iter().map(|r| r.1).collect::<Vec<_>>(),
+            )),
+        ],
+    )
+    .unwrap()
+}
+
+fn outcomes(report: &ExpectationsReport) -> Vec<(ExpectationOutcome, u64)> {
+    report
+        .results
+        .iter()
+        .map(|r| (r.outcome, r.num_violations))
+        .collect()
+}
+
+#[test]
+fn test_expectations_pass() {
+    let report = check_expectations(
+        &[
+            Expectation::NotNull(ExpectationNotNull {
+                columns: vec!["code".to_owned()],
+                on_violation: None,
+            }),
+            Expectation::RowCount(ExpectationRowCount {
+                min: Some(1),
+                max: Some(3),
+            }),
+        ],
+        &[new_data(&[(Some("a"), 1)]), new_data(&[(Some("b"), 2)])],
+    )
+    .unwrap();
+
+    assert!(!report.is_failed());
+    assert!(report.keep.is_none());
+    assert_eq!(
+        outcomes(&report),
+        [
+            (ExpectationOutcome::Passed, 0),
+            (ExpectationOutcome::Passed, 0)
+        ]
+    );
+}
+
+#[test]
+fn test_expectations_unique_across_batches() {
+    let report = check_expectations(
+        &[Expectation::Unique(ExpectationUnique {
+            columns: vec!["code".to_owned()],
+            on_violation: None,
+        })],
+        &[
+            new_data(&[(Some("a"), 1), (Some("b"), 2)]),
+            new_data(&[(Some("a"), 3)]),
+        ],
+    )
+    .unwrap();
+
+    assert!(report.is_failed());
+    assert_eq!(
+        report.results[0].examples,
+        ["row 2: duplicate key (a)".to_owned()]
+    );
+}
+
+#[test]
+fn test_expectations_pattern_quarantine() {
+    let batches = [
+        new_data(&[(Some("AB1"), 1), (Some("x"), 2)]),
+        new_data(&[(None, 3), (Some("CD2"), 4)]),
+    ];
+
+    let report = check_expectations(
+        &[Expectation::Pattern(ExpectationPattern {
+            column: "code".to_owned(),
+            regex: "[A-Z]{2}[0-9]".to_owned(),
+            on_violation: Some(ExpectationAction::Quarantine),
+        })],
+        &batches,
+    )
+    .unwrap();
+
+    assert!(!report.is_failed());
+    assert_eq!(outcomes(&report), [(ExpectationOutcome::Quarantined, 1)]);
+
+    // Nulls are only checked by the not null expectation
+    let keep = report.keep.unwrap();
+    let kept: Vec<usize> = batches
+        .iter()
+        .zip(keep.iter())
+        .map(|(b, m)| filter_record_batch(b, m).unwrap().num_rows())
+        .collect();
+    assert_eq!(kept, [1, 2]);
+}
+
+#[test]
+fn test_expectations_row_count_and_missing_column() {
+    let report = check_expectations(
+        &[Expectation::RowCount(ExpectationRowCount {
+            min: Some(2),
+            max: None,
+        })],
+        &[new_data(&[(Some("a"), 1)])],
+    )
+    .unwrap();
+
+    assert_eq!(outcomes(&report), [(ExpectationOutcome::Failed, 1)]);
+
+    let res = check_expectations(
+        &[Expectation::Range(ExpectationRange {
+            column: "amount".to_owned(),
+            min: None,
+            max: Some(10.into()),
+            on_violation: None,
+        })],
+        &[new_data(&[(Some("a"), 1)])],
+    );
+
+    assert!(matches!(res, Err(ExpectationError::MissingColumn { column }) if column == "amount"));
+}
@@ -0,0 +1,397 @@
+use kamu::infra::ingest::*;
+use kamu::infra::serde::yaml::*;
+
+use arrow::array::{Array, Int32Array, Int64Array, StringArray};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use std::sync::Arc;
+
+fn data_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("id", DataType::Int32, false),
+        Field::new("name", DataType::Utf8, true),
+        Field::new("value", DataType::Int64, true),
+    ]))
+}
+
+fn new_data(rows: &[(i32, &str, i64)]) -> RecordBatch {
+    RecordBatch::try_new(
+        data_schema(),
+        vec![
+            Arc::new(Int32Array::from(
+                rows.iter().map(|r| r.0).collect::<Vec<_>>(),
+            )),
+            Arc::new(StringArray::from(
+                rows.iter().map(|r| r.1).collect::<Vec<_>>(),
+            )),
+            Arc::new(Int64Array::from(
+                rows.iter().map(|r| r.2).collect::<Vec<_>>(),
+            )),
+        ],
+    )
+    .unwrap()
+}
+
+// Previously ingested data also contains system columns
+fn prev_data(rows: &[(&str, i32, &str, i64)]) -> RecordBatch {
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("system_time", DataType::Int64, false),
+        Field::new("observed", DataType::Utf8, false),
+        Field::new("id", DataType::Int32, false),
+        Field::new("name", DataType::Utf8, true),
+        Field::new("value", DataType::Int64, true),
+    ]));
+    RecordBatch::try_new(
+        schema,
+        vec![
+            Arc::new(Int64Array::from(vec![0; rows.len()])),
+            Arc::new(StringArray::from(
+                rows.iter().map(|r| r.0).collect::<Vec<_>>(),
+            )),
+            Arc::new(Int32Array::from(
+                rows.iter().map(|r| r.1).collect::<Vec<_>>(),
+            )),
+            Arc::new(StringArray::from(
+                rows.iter().map(|r| r.2).collect::<Vec<_>>(),
+            )),
+            Arc::new(Int64Array::from(
+                rows.iter().map(|r| r.3).collect::<Vec<_>>(),
+            )),
+        ],
+    )
+    .unwrap()
+}
+
+// Small results are expected to fit into a single batch
+fn single_batch(mut reader: Box<dyn NativeReader>) -> RecordBatch {
+    let batch = reader.next_batch().unwrap().unwrap();
+    assert!(reader.next_batch().unwrap().is_none());
+    batch
+}
+
+fn data_rows(batch: &RecordBatch, offset: usize) -> Vec<(i32, String, i64)> {
+    let ids = batch
+        .column(offset)
+        .as_any()
+        .downcast_ref::<Int32Array>()
+        .unwrap();
+    let names = batch
+        .column(offset + 1)
+        .as_any()
+        .downcast_ref::<StringArray>()
+        .unwrap();
+    let values = batch
+        .column(offset + 2)
+        .as_any()
+        .downcast_ref::<Int64Array>()
+        .unwrap();
+    (0..batch.num_rows())
+        .map(|i| (ids.value(i), names.value(i).to_owned(), values.value(i)))
+        .collect()
+}
+
+fn observed_rows(batch: &RecordBatch) -> Vec<(String, i32, String, i64)> {
+    let obsv = batch
+        .column(0)
+        .as_any()
+        .downcast_ref::<StringArray>()
+        .unwrap();
+    data_rows(batch, 1)
+        .into_iter()
+        .enumerate()
+        .map(|(i, (id, name, value))| (obsv.value(i).to_owned(), id, name, value))
+        .collect()
+}
+
+fn owned(rows: &[(&str, i32, &str, i64)]) -> Vec<(String, i32, String, i64)> {
+    rows.iter()
+        .map(|r| (r.0.to_owned(), r.1, r.2.to_owned(), r.3))
+        .collect()
+}
+
+fn vocab() -> DatasetVocabulary {
+    DatasetVocabulary {
+        system_time_column: None,
+        event_time_column: None,
+    }
+}
+
+fn snapshot_strategy() -> MergeStrategySnapshot {
+    MergeStrategySnapshot {
+        primary_key: vec!["id".to_owned()],
+        compare_columns: None,
+        observation_column: None,
+        obsv_added: None,
+        obsv_changed: None,
+        obsv_removed: None,
+    }
+}
+
+#[test]
+fn test_merge_append() {
+    let merger = merger_for(&MergeStrategy::Append, &vocab());
+
+    let res = merger
+        .merge(
+            &[prev_data(&[("I", 1, "a", 10)])],
+            data_schema(),
+            &[new_data(&[(1, "a", 10)]), new_data(&[(2, "b", 20)])],
+        )
+        .map(single_batch)
+        .unwrap();
+
+    assert_eq!(
+        data_rows(&res, 0),
+        [(1, "a".to_owned(), 10), (2, "b".to_owned(), 20)]
+    );
+}
+
+#[test]
+fn test_merge_ledger() {
+    let merger = merger_for(
+        &MergeStrategy::Ledger(MergeStrategyLedger {
+            primary_key: vec!["id".to_owned()],
+        }),
+        &vocab(),
+    );
+
+    let res = merger
+        .merge(
+            &[prev_data(&[("I", 1, "a", 10), ("I", 2, "b", 20)])],
+            data_schema(),
+            &[new_data(&[
+                (2, "b", 21),
+                (3, "c", 30),
+                (3, "c", 31),
+                (4, "d", 40),
+            ])],
+        )
+        .map(single_batch)
+        .unwrap();
+
+    assert_eq!(
+        data_rows(&res, 0),
+        [(3, "c".to_owned(), 30), (4, "d".to_owned(), 40)]
+    );
+}
+
+#[test]
+fn test_merge_ledger_missing_key() {
+    let merger = merger_for(
+        &MergeStrategy::Ledger(MergeStrategyLedger {
+            primary_key: vec!["uid".to_owned()],
+        }),
+        &vocab(),
+    );
+
+    let res = merger.merge(&[], data_schema(), &[new_data(&[(1, "a", 10)])]);
+    assert!(matches!(res, Err(MergeError::MissingColumn { column }) if column == "uid"));
+}
+
+#[test]
+fn test_merge_snapshot_initial() {
+    let merger = merger_for(&MergeStrategy::Snapshot(snapshot_strategy()), &vocab());
+
+    let res = merger
+        .merge(
+            &[],
+            data_schema(),
+            &[new_data(&[(1, "a", 10), (2, "b", 20)])],
+        )
+        .map(single_batch)
+        .unwrap();
+
+    assert_eq!(res.schema().field(0).name(), "observed");
+    assert_eq!(
+        observed_rows(&res),
+        owned(&[("I", 1, "a", 10), ("I", 2, "b", 20)])
+    );
+}
+
+#[test]
+fn test_merge_snapshot_changes() {
+    let merger = merger_for(&MergeStrategy::Snapshot(snapshot_strategy()), &vocab());
+
+    let prev = [
+        prev_data(&[("I", 1, "a", 10), ("I", 2, "b", 20), ("I", 3, "c", 30)]),
+        prev_data(&[("U", 2, "b", 25), ("D", 3, "c", 30)]),
+    ];
+
+    let res = merger
+        .merge(
+            &prev,
+            data_schema(),
+            &[new_data(&[
+                (1, "a", 10),
+                (2, "b", 26),
+                (4, "d", 40),
+                (3, "c", 30),
+            ])],
+        )
+        .map(single_batch)
+        .unwrap();
+
+    assert_eq!(
+        observed_rows(&res),
+        owned(&[("U", 2, "b", 26), ("I", 4, "d", 40), ("I", 3, "c", 30)])
+    );
+
+    // Records missing from the snapshot are reported as removed with their last values
+    let res = merger
+        .merge(&prev, data_schema(), &[new_data(&[(2, "b", 25)])])
+        .map(single_batch)
+        .unwrap();
+
+    assert_eq!(observed_rows(&res), owned(&[("D", 1, "a", 10)]));
+}
+
+#[test]
+fn test_merge_snapshot_compare_columns() {
+    let merger = merger_for(
+        &MergeStrategy::Snapshot(MergeStrategySnapshot {
+            compare_columns: Some(vec!["name".to_owned()]),
+            ..snapshot_strategy()
+        }),
+        &vocab(),
+    );
+
+    let res = merger
+        .merge(
+            &[prev_data(&[("I", 1, "a", 10), ("I", 2, "b", 20)])],
+            data_schema(),
+            &[new_data(&[(1, "a", 11), (2, "bb", 20)])],
+        )
+        .map(single_batch)
+        .unwrap();
+
+    assert_eq!(observed_rows(&res), owned(&[("U", 2, "bb", 20)]));
+}
+
+#[test]
+fn test_merge_snapshot_custom_observations() {
+    let merger = merger_for(
+        &MergeStrategy::Snapshot(MergeStrategySnapshot {
+            observation_column: Some("op".to_owned()),
+            obsv_added: Some("+".to_owned()),
+            obsv_changed: Some("~".to_owned()),
+            obsv_removed: Some("-".to_owned()),
+            ..snapshot_strategy()
+        }),
+        &vocab(),
+    );
+
+    let prev = RecordBatch::try_new(
+        Arc::new(Schema::new(vec![
+            Field::new("op", DataType::Utf8, false),
+            Field::new("id", DataType::Int32, false),
+            Field::new("name", DataType::Utf8, true),
+            Field::new("value", DataType::Int64, true),
+        ])),
+        prev_data(&[("+", 1, "a", 10), ("+", 2, "b", 20), ("-", 2, "b", 20)]).columns()[1..]
+            .to_vec(),
+    )
+    .unwrap();
+
+    let res = merger
+        .merge(
+            &[prev],
+            data_schema(),
+            &[new_data(&[(1, "a", 15), (3, "c", 30)])],
+        )
+        .map(single_batch)
+        .unwrap();
+
+    assert_eq!(res.schema().field(0).name(), "op");
+    assert_eq!(
+        observed_rows(&res),
+        owned(&[("~", 1, "a", 15), ("+", 3, "c", 30)])
+    );
+}
+
+#[test]
+fn test_merge_snapshot_duplicate_key() {
+    let merger = merger_for(&MergeStrategy::Snapshot(snapshot_strategy()), &vocab());
+
+    let res = merger.merge(
+        &[],
+        data_schema(),
+        &[new_data(&[(1, "a", 10), (1, "a", 11)])],
+    );
+
+    assert!(matches!(res, Err(MergeError::DuplicateKey { .. })));
+}
+
+#[test]
+fn test_merge_prev_columns() {
+    let schema = data_schema();
+
+    assert!(merger_for(&MergeStrategy::Append, &vocab())
+        .prev_columns(&schema)
+        .is_empty());
+
+    assert_eq!(
+        merger_for(
+            &MergeStrategy::Ledger(MergeStrategyLedger {
+                primary_key: vec!["id".to_owned()],
+            }),
+            &vocab(),
+        )
+        .prev_columns(&schema),
+        ["id"]
+    );
+
+    assert_eq!(
+        merger_for(&MergeStrategy::Snapshot(snapshot_strategy()), &vocab()).prev_columns(&schema),
+        ["id", "name", "value", "observed"]
+    );
+}
+
+#[test]
+fn test_merge_snapshot_column_added() {
+    let merger = merger_for(&MergeStrategy::Snapshot(snapshot_strategy()), &vocab());
+
+    // History predates the value column
+    let prev = RecordBatch::try_new(
+        Arc::new(Schema::new(vec![
+            Field::new("observed", DataType::Utf8, false),
+            Field::new("id", DataType::Int32, false),
+            Field::new("name", DataType::Utf8, true),
+        ])),
+        vec![
+            Arc::new(StringArray::from(vec!["I"])),
+            Arc::new(Int32Array::from(vec![1])),
+            Arc::new(StringArray::from(vec!["a"])),
+        ],
+    )
+    .unwrap();
+
+    let res = merger
+        .merge(
+            &[prev],
+            data_schema(),
+            &[new_data(&[(1, "a", 10), (2, "b", 20)])],
+        )
+        .map(single_batch)
+        .unwrap();
+
+    assert_eq!(
+        observed_rows(&res),
+        owned(&[("U", 1, "a", 10), ("I", 2, "b", 20)])
+    );
+}
+
+#[test]
+fn test_merge_output_in_batches() {
+    let merger = merger_for(&MergeStrategy::Append, &vocab());
+
+    let rows: Vec<(i32, &str, i64)> = (0..25_000).map(|i| (i, "a", i as i64)).collect();
+    let mut reader = merger
+        .merge(&[], data_schema(), &[new_data(&rows)])
+        .unwrap();
+
+    let mut batch_sizes = Vec::new();
+    while let Some(batch) = reader.next_batch().unwrap() {
+        batch_sizes.push(batch.num_rows());
+    }
+    assert_eq!(batch_sizes, [10_000, 10_000, 5_000]);
+}
@@ -1,10 +1,47 @@
 use indoc::indoc;
+use kamu::domain::*;
 use kamu::infra::ingest::*;
 use kamu::infra::serde::yaml::*;
+use kamu_test::*;
 
 use chrono::Utc;
 use std::io::prelude::*;
 
+fn read_csv(header: bool) -> ReadStep {
+    ReadStep::Csv(ReadStepCsv {
+        schema: None,
+        separator: None,
+        encoding: None,
+        quote: None,
+        escape: None,
+        comment: None,
+        header: Some(header),
+        enforce_schema: None,
+        infer_schema: None,
+        ignore_leading_white_space: None,
+        ignore_trailing_white_space: None,
+        null_value: None,
+        empty_value: None,
+        nan_value: None,
+        positive_inf: None,
+        negative_inf: None,
+        date_format: None,
+        timestamp_format: None,
+        multi_line: None,
+    })
+}
+
+fn read_json_lines() -> ReadStep {
+    ReadStep::JsonLines(ReadStepJsonLines {
+        schema: None,
+        date_format: None,
+        encoding: None,
+        multi_line: None,
+        primitives_as_string: None,
+        timestamp_format: None,
+    })
+}
+
 #[test]
 fn test_prep_pipe() {
     let tempdir = tempfile::tempdir().unwrap();
@@ -42,7 +79,14 @@ fn test_prep_pipe() {
     .unwrap();
 
     let res = prep_svc
-        .prepare(&prep_steps, Utc::now(), None, &src_path, &target_path)
+        .prepare(
+            &prep_steps,
+            &read_json_lines(),
+            Utc::now(),
+            None,
+            &src_path,
+            &target_path,
+        )
         .unwrap();
     assert_eq!(res.was_up_to_date, false);
     assert!(target_path.exists());
@@ -88,10 +132,363 @@ fn test_prep_decompress_zip_single_file() {
     let prep_svc = PrepService::new();
 
     let res = prep_svc
-        .prepare(&prep_steps, Utc::now(), None, &src_path, &target_path)
+        .prepare(
+            &prep_steps,
+            &read_csv(true),
+            Utc::now(),
+            None,
+            &src_path,
+            &target_path,
+        )
         .unwrap();
     assert_eq!(res.was_up_to_date, false);
     assert!(target_path.exists());
 
     assert_eq!(std::fs::read_to_string(&target_path).unwrap(), content);
 }
+
+#[test]
+fn test_prep_decompress_zip_multiple_files() {
+    let tempdir = tempfile::tempdir().unwrap();
+
+    let src_path = tempdir.path().join("data.zip");
+    let target_path = tempdir.path().join("prepared.bin");
+
+    let prep_steps = vec![PrepStep::Decompress(PrepStepDecompress {
+        format: CompressionFormat::Zip,
+        sub_path: Some("data_*.csv".to_owned()),
+    })];
+
+    {
+        // Create archive
+        use zip::write::*;
+        let mut zip = ZipWriter::new(std::fs::File::create(&src_path).unwrap());
+
+        let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        zip.start_file("README.txt", options).unwrap();
+        zip.write(b"Not a CSV").unwrap();
+        zip.start_file("data_1.csv", options).unwrap();
+        zip.write(b"city,population\nA,1000\nB,2000\n").unwrap();
+        zip.start_file("data_2.csv", options).unwrap();
+        zip.write(b"city,population\nC,3000").unwrap();
+        zip.start_file("data_3.csv", options).unwrap();
+        zip.write(b"city,population\nD,4000\n").unwrap();
+        zip.finish().unwrap();
+    }
+
+    let prep_svc = PrepService::new();
+
+    let res = prep_svc
+        .prepare(
+            &prep_steps,
+            &read_csv(true),
+            Utc::now(),
+            None,
+            &src_path,
+            &target_path,
+        )
+        .unwrap();
+    assert_eq!(res.was_up_to_date, false);
+    assert!(target_path.exists());
+
+    assert_eq!(
+        std::fs::read_to_string(&target_path).unwrap(),
+        indoc!(
+            "
+            city,population
+            A,1000
+            B,2000
+            C,3000
+            D,4000
+            "
+        )
+    );
+}
+
+#[test]
+fn test_prep_decompress_zip_multiple_files_json_lines() {
+    let tempdir = tempfile::tempdir().unwrap();
+
+    let src_path = tempdir.path().join("data.zip");
+    let target_path = tempdir.path().join("prepared.bin");
+
+    let prep_steps = vec![PrepStep::Decompress(PrepStepDecompress {
+        format: CompressionFormat::Zip,
+        sub_path: Some("*.json".to_owned()),
+    })];
+
+    {
+        // Create archive
+        use zip::write::*;
+        let mut zip = ZipWriter::new(std::fs::File::create(&src_path).unwrap());
+
+        let options = FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        zip.start_file("data_1.json", options).unwrap();
+        zip.write(b"{\"city\":\"A\"}\n{\"city\":\"B\"}\n").unwrap();
+        zip.start_file("data_2.json", options).unwrap();
+        zip.write(b"{\"city\":\"A\"}\n{\"city\":\"C\"}").unwrap();
+        zip.finish().unwrap();
+    }
+
+    let prep_svc = PrepService::new();
+
+    prep_svc
+        .prepare(
+            &prep_steps,
+            &read_json_lines(),
+            Utc::now(),
+            None,
+            &src_path,
+            &target_path,
+        )
+        .unwrap();
+
+    // Identical first records are not mistaken for a header
+    assert_eq!(
+        std::fs::read_to_string(&target_path).unwrap(),
+        "{\"city\":\"A\"}\n{\"city\":\"B\"}\n{\"city\":\"A\"}\n{\"city\":\"C\"}"
+    );
+}
+
+#[test]
+fn test_prep_decompress_zip_multiple_files_not_concatenable() {
+    let tempdir = tempfile::tempdir().unwrap();
+
+    let src_path = tempdir.path().join("data.zip");
+    let target_path = tempdir.path().join("prepared.bin");
+
+    let prep_steps = vec![PrepStep::Decompress(PrepStepDecompress {
+        format: CompressionFormat::Zip,
+        sub_path: Some("*.geojson".to_owned()),
+    })];
+
+    {
+        // Create archive
+        use zip::write::*;
+        let mut zip = ZipWriter::new(std::fs::File::create(&src_path).unwrap());
+
+        let options = FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        for name in &["a.geojson", "b.geojson"] {
+            zip.start_file(*name, options).unwrap();
+            zip.write(b"{\"type\": \"FeatureCollection\", \"features\": []}")
+                .unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    let prep_svc = PrepService::new();
+
+    let res = prep_svc.prepare(
+        &prep_steps,
+        &ReadStep::GeoJson(ReadStepGeoJson { schema: None }),
+        Utc::now(),
+        None,
+        &src_path,
+        &target_path,
+    );
+
+    assert_err!(
+        res,
+        IngestError::StageFailed {
+            stage: IngestStage::Prepare,
+            ..
+        }
+    );
+    assert!(!target_path.exists());
+}
+
+#[test]
+fn test_prep_decompress_gzip() {
+    let tempdir = tempfile::tempdir().unwrap();
+
+    let src_path = tempdir.path().join("data.csv.gz");
+    let target_path = tempdir.path().join("prepared.bin");
+
+    let prep_steps = vec![PrepStep::Decompress(PrepStepDecompress {
+        format: CompressionFormat::Gzip,
+        sub_path: None,
+    })];
+
+    let content = indoc!(
+        "
+        city,population
+        A,1000
+        B,2000
+        C,3000
+        "
+    );
+
+    {
+        use flate2::write::GzEncoder;
+        let mut encoder = GzEncoder::new(
+            std::fs::File::create(&src_path).unwrap(),
+            flate2::Compression::default(),
+        );
+        encoder.write_all(content.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+    }
+
+    let prep_svc = PrepService::new();
+
+    let res = prep_svc
+        .prepare(
+            &prep_steps,
+            &read_csv(true),
+            Utc::now(),
+            None,
+            &src_path,
+            &target_path,
+        )
+        .unwrap();
+    assert_eq!(res.was_up_to_date, false);
+    assert!(target_path.exists());
+
+    assert_eq!(std::fs::read_to_string(&target_path).unwrap(), content);
+}
+
+#[test]
+fn test_prep_decompress_tar_gz_sub_path() {
+    let tempdir = tempfile::tempdir().unwrap();
+
+    let src_path = tempdir.path().join("data.tar.gz");
+    let target_path = tempdir.path().join("prepared.bin");
+
+    let prep_steps = vec![PrepStep::Decompress(PrepStepDecompress {
+        format: CompressionFormat::Gzip,
+        sub_path: Some("data/*.csv".to_owned()),
+    })];
+
+    let content = indoc!(
+        "
+        city,population
+        A,1000
+        B,2000
+        C,3000
+        "
+    );
+
+    {
+        // Create archive
+        use flate2::write::GzEncoder;
+        let encoder = GzEncoder::new(
+            std::fs::File::create(&src_path).unwrap(),
+            flate2::Compression::default(),
+        );
+        let mut tar = tar::Builder::new(encoder);
+
+        for (path, data) in &[("README.txt", "Not a CSV"), ("data/cities.csv", content)] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            tar.append_data(&mut header, path, data.as_bytes()).unwrap();
+        }
+
+        tar.into_inner().unwrap().finish().unwrap();
+    }
+
+    // Tar archives are not among the ODF compression formats, so the step format is overridden
+    let prep_svc = PrepService::new().with_archive_format(ArchiveFormat::TarGz);
+
+    let res = prep_svc
+        .prepare(
+            &prep_steps,
+            &read_csv(true),
+            Utc::now(),
+            None,
+            &src_path,
+            &target_path,
+        )
+        .unwrap();
+    assert_eq!(res.was_up_to_date, false);
+    assert!(target_path.exists());
+
+    assert_eq!(std::fs::read_to_string(&target_path).unwrap(), content);
+}
+
+#[test]
+fn test_prep_pipe_failure() {
+    let tempdir = tempfile::tempdir().unwrap();
+
+    let src_path = tempdir.path().join("data.csv");
+    let target_path = tempdir.path().join("prepared.bin");
+
+    std::fs::write(&src_path, "city,population\nA,1000\n").unwrap();
+
+    let prep_steps = vec![
+        PrepStep::Pipe(PrepStepPipe {
+            command: ["cat"].iter().map(|s| s.to_string()).collect(),
+        }),
+        PrepStep::Pipe(PrepStepPipe {
+            command: ["sh", "-c", "head -n 1; echo 'boom' >&2; exit 3"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+        }),
+    ];
+
+    let prep_svc = PrepService::new();
+
+    let res = prep_svc.prepare(
+        &prep_steps,
+        &read_csv(true),
+        Utc::now(),
+        None,
+        &src_path,
+        &target_path,
+    );
+
+    match res {
+        Err(IngestError::StageFailed {
+            stage: IngestStage::Prepare,
+            source,
+            ..
+        }) => match source.downcast_ref::<PrepError>() {
+            Some(PrepError::CommandFailed { step, source }) => {
+                assert_eq!(*step, 1);
+                assert_eq!(source.exit_code, Some(3));
+                assert_eq!(source.stderr, "boom\n");
+            }
+            other => panic!("Unexpected error: {:?}", other),
+        },
+        other => panic!("Unexpected result: {:?}", other),
+    }
+
+    assert!(!target_path.exists());
+    assert!(!target_path.with_extension("tmp").exists());
+}
+
+#[test]
+fn test_prep_pipe_cancelled() {
+    let tempdir = tempfile::tempdir().unwrap();
+
+    let src_path = tempdir.path().join("data.csv");
+    let target_path = tempdir.path().join("prepared.bin");
+
+    std::fs::write(&src_path, "city,population\nA,1000\n").unwrap();
+
+    // Emulates a command interrupted by the same Ctrl+C that cancelled the pull
+    let prep_steps = vec![PrepStep::Pipe(PrepStepPipe {
+        command: ["sh", "-c", "exit 130"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+    })];
+
+    let cancel = CancellationToken::new();
+    cancel.cancel();
+
+    let prep_svc = PrepService::new().with_cancellation(cancel);
+
+    let res = prep_svc.prepare(
+        &prep_steps,
+        &read_csv(true),
+        Utc::now(),
+        None,
+        &src_path,
+        &target_path,
+    );
+
+    assert_err!(res, IngestError::Cancelled(_));
+    assert!(!target_path.with_extension("tmp").exists());
+}
@@ -0,0 +1,341 @@
+use indoc::indoc;
+use kamu::infra::ingest::*;
+use kamu::infra::serde::yaml::*;
+
+use chrono::prelude::*;
+use parquet::{
+    file::reader::{FileReader, SerializedFileReader},
+    record::RowAccessor,
+};
+use std::fs::File;
+
+fn read_step_csv() -> ReadStepCsv {
+    ReadStepCsv {
+        schema: None,
+        separator: None,
+        encoding: None,
+        quote: None,
+        escape: None,
+        comment: None,
+        header: None,
+        enforce_schema: None,
+        infer_schema: None,
+        ignore_leading_white_space: None,
+        ignore_trailing_white_space: None,
+        null_value: None,
+        empty_value: None,
+        nan_value: None,
+        positive_inf: None,
+        negative_inf: None,
+        date_format: None,
+        timestamp_format: None,
+        multi_line: None,
+    }
+}
+
+#[test]
+fn test_read_csv_native() {
+    let tempdir = tempfile::tempdir().unwrap();
+    let src_path = tempdir.path().join("data.csv");
+    let data_dir = tempdir.path().join("data");
+
+    std::fs::write(
+        &src_path,
+        indoc!(
+            "
+            date;city;population;area
+            # comments are skipped
+            2020/01/01 00:00;\"A;B\";1000;1.5
+            2020/01/02 00:00;;2000;NaN
+            2020/01/03 00:00;C;;
+            "
+        ),
+    )
+    .unwrap();
+
+    let conf = ReadStepCsv {
+        schema: Some(
+            [
+                "date TIMESTAMP",
+                "city STRING",
+                "population INT",
+                "area DOUBLE",
+            ]
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+        ),
+        separator: Some(";".to_owned()),
+        comment: Some("#".to_owned()),
+        header: Some(true),
+        timestamp_format: Some("yyyy/MM/dd HH:mm".to_owned()),
+        ..read_step_csv()
+    };
+
+    assert!(CsvReader::supports(&conf));
+
+    let mut reader = CsvReader::new(&conf, &src_path).unwrap();
+
+    let system_time = Utc.ymd(2020, 2, 1).and_hms(0, 0, 0);
+    let vocab = DatasetVocabulary {
+        system_time_column: None,
+        event_time_column: Some("date".to_owned()),
+    };
+
+    let res = DataWriter::new(&data_dir, &vocab, system_time, None)
+        .write(&mut reader)
+        .unwrap();
+
+    let slice = res.output_slice.unwrap();
+    assert_eq!(slice.num_records, 3);
+    assert_eq!(
+        res.output_watermark,
+        Some(Utc.ymd(2020, 1, 3).and_hms(0, 0, 0))
+    );
+
+    let part_file = data_dir.read_dir().unwrap().next().unwrap().unwrap().path();
+    let parquet_reader = SerializedFileReader::new(File::open(&part_file).unwrap()).unwrap();
+
+    let columns: Vec<_> = parquet_reader
+        .metadata()
+        .file_metadata()
+        .schema_descr()
+        .columns()
+        .iter()
+        .map(|cd| cd.path().string())
+        .collect();
+
+    assert_eq!(
+        columns,
+        ["system_time", "date", "city", "population", "area"]
+    );
+
+    let records: Vec<_> = parquet_reader
+        .get_row_iter(None)
+        .unwrap()
+        .map(|r| {
+            (
+                r.get_string(2).ok().cloned(),
+                r.get_int(3).ok(),
+                r.get_double(4).ok(),
+            )
+        })
+        .collect();
+
+    assert_eq!(records[0], (Some("A;B".to_owned()), Some(1000), Some(1.5)));
+    assert_eq!(records[1].0, None);
+    assert!(records[1].2.unwrap().is_nan());
+    assert_eq!(records[2], (Some("C".to_owned()), None, None));
+}
+
+#[test]
+fn test_read_csv_native_unsupported() {
+    assert!(!CsvReader::supports(&ReadStepCsv {
+        infer_schema: Some(true),
+        ..read_step_csv()
+    }));
+    assert!(!CsvReader::supports(&ReadStepCsv {
+        encoding: Some("windows-1252".to_owned()),
+        ..read_step_csv()
+    }));
+    assert!(!CsvReader::supports(&ReadStepCsv {
+        schema: Some(vec!["geom GEOMETRY".to_owned()]),
+        ..read_step_csv()
+    }));
+}
+
+#[test]
+fn test_list_parquet_files_in_write_order() {
+    let tempdir = tempfile::tempdir().unwrap();
+
+    // Names given by engines don't sort in the order of writes
+    let touch = |name: &str, mtime: i64| {
+        let path = tempdir.path().join(name);
+        std::fs::write(&path, b"").unwrap();
+        filetime::set_file_mtime(&path, filetime::FileTime::from_unix_time(mtime, 0)).unwrap();
+        path
+    };
+    let second = touch("part-0000-a.snappy.parquet", 2);
+    let first = touch("part-0000-b.snappy.parquet", 1);
+    let third_a = touch("20200101T000000.000Z.snappy.parquet", 3);
+    let third_b = touch("20200101T000001.000Z.snappy.parquet", 3);
+    touch("part-0000-c.crc", 0);
+
+    assert_eq!(
+        list_parquet_files(tempdir.path()).unwrap(),
+        [first, second, third_a, third_b]
+    );
+}
+
+fn read_parquet_rows(data_dir: &std::path::Path) -> (Vec<String>, Vec<parquet::record::Row>) {
+    let part_file = data_dir.read_dir().unwrap().next().unwrap().unwrap().path();
+    let parquet_reader = SerializedFileReader::new(File::open(&part_file).unwrap()).unwrap();
+
+    let columns = parquet_reader
+        .metadata()
+        .file_metadata()
+        .schema_descr()
+        .columns()
+        .iter()
+        .map(|cd| cd.path().string())
+        .collect();
+
+    let rows = parquet_reader.get_row_iter(None).unwrap().collect();
+    (columns, rows)
+}
+
+#[test]
+fn test_read_json_lines_native() {
+    let tempdir = tempfile::tempdir().unwrap();
+    let src_path = tempdir.path().join("data.json");
+    let data_dir = tempdir.path().join("data");
+
+    std::fs::write(
+        &src_path,
+        indoc!(
+            r#"
+            {"city": "A", "population": 1000, "tags": ["x"]}
+
+            {"city": "B", "population": 2000.5}
+            {"city": "C"}
+            "#
+        ),
+    )
+    .unwrap();
+
+    let conf = ReadStepJsonLines {
+        schema: None,
+        date_format: None,
+        encoding: None,
+        multi_line: None,
+        primitives_as_string: None,
+        timestamp_format: None,
+    };
+
+    assert!(JsonLinesReader::supports(&conf));
+    let mut reader = JsonLinesReader::new(&conf, &src_path).unwrap();
+
+    let system_time = Utc.ymd(2020, 2, 1).and_hms(0, 0, 0);
+    let vocab = DatasetVocabulary {
+        system_time_column: None,
+        event_time_column: None,
+    };
+
+    let res = DataWriter::new(&data_dir, &vocab, system_time, None)
+        .write(&mut reader)
+        .unwrap();
+
+    assert_eq!(res.output_slice.unwrap().num_records, 3);
+    assert_eq!(res.output_watermark, Some(system_time));
+
+    let (columns, rows) = read_parquet_rows(&data_dir);
+    assert_eq!(
+        columns,
+        ["system_time", "event_time", "city", "population", "tags"]
+    );
+
+    let records: Vec<_> = rows
+        .iter()
+        .map(|r| {
+            (
+                r.get_string(2).unwrap().clone(),
+                r.get_double(3).ok(),
+                r.get_string(4).ok().cloned(),
+            )
+        })
+        .collect();
+
+    assert_eq!(
+        records,
+        [
+            ("A".to_owned(), Some(1000.0), Some("[\"x\"]".to_owned())),
+            ("B".to_owned(), Some(2000.5), None),
+            ("C".to_owned(), None, None),
+        ]
+    );
+}
+
+#[test]
+fn test_read_geojson_native() {
+    let tempdir = tempfile::tempdir().unwrap();
+    let src_path = tempdir.path().join("data.json");
+    let data_dir = tempdir.path().join("data");
+
+    std::fs::write(
+        &src_path,
+        indoc!(
+            r#"
+            {
+                "type": "FeatureCollection",
+                "features": [
+                    {
+                        "type": "Feature",
+                        "properties": {"id": 1, "zone": "A"},
+                        "geometry": {"type": "Point", "coordinates": [-123.1, 49.25]}
+                    },
+                    {
+                        "type": "Feature",
+                        "properties": {"id": 2, "zone": "B"},
+                        "geometry": {
+                            "type": "Polygon",
+                            "coordinates": [[[0, 0], [1, 0], [1, 1], [0, 0]]]
+                        }
+                    }
+                ]
+            }
+            "#
+        ),
+    )
+    .unwrap();
+
+    let conf = ReadStepGeoJson {
+        schema: Some(
+            ["id INT", "geometry GEOMETRY", "zone STRING"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+        ),
+    };
+
+    assert!(GeoJsonReader::supports(&conf));
+    let mut reader = GeoJsonReader::new(&conf, &src_path).unwrap();
+
+    let system_time = Utc.ymd(2020, 2, 1).and_hms(0, 0, 0);
+    let vocab = DatasetVocabulary {
+        system_time_column: None,
+        event_time_column: None,
+    };
+
+    DataWriter::new(&data_dir, &vocab, system_time, None)
+        .write(&mut reader)
+        .unwrap();
+
+    let (columns, rows) = read_parquet_rows(&data_dir);
+    assert_eq!(
+        columns,
+        ["system_time", "event_time", "id", "geometry", "zone"]
+    );
+
+    let records: Vec<_> = rows
+        .iter()
+        .map(|r| {
+            (
+                r.get_int(2).unwrap(),
+                r.get_string(3).unwrap().clone(),
+                r.get_string(4).unwrap().clone(),
+            )
+        })
+        .collect();
+
+    assert_eq!(
+        records,
+        [
+            (1, "POINT (-123.1 49.25)".to_owned(), "A".to_owned()),
+            (
+                2,
+                "POLYGON ((0 0, 1 0, 1 1, 0 0))".to_owned(),
+                "B".to_owned()
+            ),
+        ]
+    );
+}
@@ -1,7 +1,12 @@
 mod engine;
 mod ingest;
 mod serde;
+mod test_ingest_service_impl;
 mod test_metadata_chain_impl;
 mod test_metadata_repository_impl;
+mod test_pull_scheduler;
 mod test_pull_service_impl;
+mod test_source_watcher;
 mod test_transform_service_impl;
+mod test_watermark_service_impl;
+mod utils;
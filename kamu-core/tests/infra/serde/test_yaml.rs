@@ -185,6 +185,118 @@ fn de_dataset_snapshot_derivative() {
     assert_eq!(expected, actual);
 }
 
+#[test]
+fn de_dataset_snapshot_manifest_with_config() {
+    let data = indoc!(
+        "
+        ---
+        apiVersion: 1
+        kind: DatasetSnapshot
+        content:
+          id: kamu.test
+          source:
+            kind: root
+            fetch:
+              kind: url
+              url: https://kamu.dev/test.tar.gz
+            prepare:
+            - kind: decompress
+              format: gzip
+            read:
+              kind: csv
+            merge:
+              kind: append
+        config:
+          fetch:
+            headers:
+            - name: X-Api-Key
+              value: ${{ secrets.api_key }}
+            auth:
+              kind: bearer
+              token: ${{ env.API_TOKEN }}
+            checksum: sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824
+          decompress:
+            format: tarGz
+          schemaPolicy:
+            onColumnRemoved: fail
+            onTypeChanged: ignore
+          expectations:
+          - kind: notNull
+            columns:
+            - id
+          - kind: range
+            column: value
+            min: 0
+            onViolation: quarantine
+          schedule:
+            kind: interval
+            every: 1h"
+    );
+
+    let actual: DatasetSnapshotManifest = serde_yaml::from_str(data).unwrap();
+
+    assert_eq!(actual.content.id.as_str(), "kamu.test");
+    assert_eq!(
+        actual.config,
+        Some(DatasetConfig {
+            fetch: Some(FetchConfig {
+                headers: Some(vec![RequestHeader {
+                    name: "X-Api-Key".to_owned(),
+                    value: "${{ secrets.api_key }}".to_owned(),
+                }]),
+                auth: Some(FetchAuth::Bearer(FetchAuthBearer {
+                    token: "${{ env.API_TOKEN }}".to_owned(),
+                })),
+                checksum: Some(
+                    "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+                        .to_owned()
+                ),
+            }),
+            decompress: Some(DecompressConfig {
+                format: ArchiveFormat::TarGz,
+            }),
+            schema_policy: Some(SchemaPolicy {
+                on_column_added: None,
+                on_column_removed: Some(SchemaChangeAction::Fail),
+                on_type_changed: Some(SchemaChangeAction::Ignore),
+            }),
+            expectations: Some(vec![
+                Expectation::NotNull(ExpectationNotNull {
+                    columns: vec!["id".to_owned()],
+                    on_violation: None,
+                }),
+                Expectation::Range(ExpectationRange {
+                    column: "value".to_owned(),
+                    min: Some(0.into()),
+                    max: None,
+                    on_violation: Some(ExpectationAction::Quarantine),
+                }),
+            ]),
+            schedule: Some(PollSchedule::Interval(PollScheduleInterval {
+                every: "1h".to_owned(),
+            })),
+        })
+    );
+
+    // Config is optional
+    let data = indoc!(
+        "
+        ---
+        apiVersion: 1
+        kind: DatasetSnapshot
+        content:
+          id: kamu.test
+          source:
+            kind: derivative
+            inputs: []
+            transform:
+              engine: sparkSQL"
+    );
+
+    let actual: DatasetSnapshotManifest = serde_yaml::from_str(data).unwrap();
+    assert_eq!(actual.config, None);
+}
+
 #[test]
 fn de_metadata_block() {
     let data = indoc!(
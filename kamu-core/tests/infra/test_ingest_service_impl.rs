@@ -0,0 +1,479 @@
+use kamu::domain::*;
+use kamu::infra::ingest::{list_parquet_files, read_parquet_dir};
+use kamu::infra::serde::yaml::*;
+use kamu::infra::utils::hashing::file_sha3;
+use kamu::infra::*;
+use kamu_test::*;
+
+use chrono::prelude::*;
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+fn csv_read_step(schema: Option<&[&str]>) -> ReadStep {
+    ReadStep::Csv(ReadStepCsv {
+        header: Some(true),
+        schema: schema.map(|s| s.iter().map(|c| c.to_string()).collect()),
+        separator: None,
+        encoding: None,
+        quote: None,
+        escape: None,
+        comment: None,
+        enforce_schema: None,
+        infer_schema: None,
+        ignore_leading_white_space: None,
+        ignore_trailing_white_space: None,
+        null_value: None,
+        empty_value: None,
+        nan_value: None,
+        positive_inf: None,
+        negative_inf: None,
+        date_format: None,
+        timestamp_format: None,
+        multi_line: None,
+    })
+}
+
+fn csv_dataset_snapshot(src_path: &Path) -> DatasetSnapshot {
+    MetadataFactory::dataset_snapshot()
+        .id("foo.bar")
+        .source(
+            MetadataFactory::dataset_source_root()
+                .fetch_file(src_path)
+                .read(csv_read_step(Some(&["city STRING", "population INT"])))
+                .build(),
+        )
+        .build()
+}
+
+fn new_ingest_service(
+    workspace_layout: &WorkspaceLayout,
+    metadata_repo: Rc<RefCell<MetadataRepositoryImpl>>,
+) -> IngestServiceImpl {
+    IngestServiceImpl::new(
+        metadata_repo,
+        Arc::new(EngineFactory::new(workspace_layout)),
+        workspace_layout,
+        &VolumeLayout::new(&workspace_layout.local_volume_dir),
+        slog::Logger::root(slog::Discard, slog::o!()),
+    )
+}
+
+#[test]
+fn test_ingest_from_supplied_data() {
+    let tempdir = tempfile::tempdir().unwrap();
+
+    let workspace_layout = WorkspaceLayout::create(tempdir.path()).unwrap();
+    let volume_layout = VolumeLayout::new(&workspace_layout.local_volume_dir);
+
+    let metadata_repo = Rc::new(RefCell::new(MetadataRepositoryImpl::new(&workspace_layout)));
+    let mut ingest_svc = new_ingest_service(&workspace_layout, metadata_repo.clone());
+
+    // Source is never fetched
+    let dataset_snapshot = csv_dataset_snapshot(&tempdir.path().join("does-not-exist.csv"));
+
+    let dataset_id = dataset_snapshot.id.clone();
+
+    metadata_repo
+        .borrow_mut()
+        .add_dataset(dataset_snapshot)
+        .unwrap();
+
+    let event_time = Utc.ymd(2020, 1, 1).and_hms(0, 0, 0);
+    let mut data: &[u8] = b"city,population\nA,1000\nB,2000\n";
+
+    let res = ingest_svc.ingest_from(&dataset_id, &mut data, Some(event_time), None);
+    let (block_hash, stats) = match res {
+        Ok(IngestResult::Updated { block_hash, stats }) => (block_hash, stats),
+        _ => panic!("Unexpected result {:?}", res),
+    };
+
+    let block = metadata_repo
+        .borrow()
+        .get_metadata_chain(&dataset_id)
+        .unwrap()
+        .get_block(&block_hash)
+        .unwrap();
+
+    assert_eq!(block.output_watermark, Some(event_time));
+
+    let output_slice = block.output_slice.unwrap();
+    assert_eq!(output_slice.num_records, 2);
+    assert_eq!(stats.records_added, 2);
+    assert_eq!(stats.output_intervals, vec![output_slice.interval]);
+    assert_eq!(stats.prev_watermark, None);
+    assert_eq!(stats.new_watermark, Some(event_time));
+    assert_eq!(stats.bytes_fetched, Some(30));
+    assert_eq!(stats.fetched_from_cache, Some(false));
+    assert_eq!(
+        stats
+            .stages
+            .iter()
+            .map(|s| s.stage.as_str())
+            .collect::<Vec<_>>(),
+        vec!["receive", "prepare", "read", "commit"]
+    );
+
+    let dataset_layout = DatasetLayout::new(&volume_layout, &dataset_id);
+    assert!(!dataset_layout.cache_dir.join("fetch.yaml").exists());
+    assert!(!dataset_layout.cache_dir.join("received.bin").exists());
+    assert_eq!(
+        metadata_repo
+            .borrow()
+            .get_summary(&dataset_id)
+            .unwrap()
+            .num_records,
+        2
+    );
+}
+
+#[test]
+fn test_ingest_preview() {
+    let tempdir = tempfile::tempdir().unwrap();
+
+    let workspace_layout = WorkspaceLayout::create(tempdir.path()).unwrap();
+    let volume_layout = VolumeLayout::new(&workspace_layout.local_volume_dir);
+
+    let metadata_repo = Rc::new(RefCell::new(MetadataRepositoryImpl::new(&workspace_layout)));
+    let mut ingest_svc = new_ingest_service(&workspace_layout, metadata_repo.clone());
+
+    let src_path = tempdir.path().join("data.csv");
+    std::fs::write(&src_path, "city,population\nA,1000\nB,\nC,3000\n").unwrap();
+
+    let dataset_snapshot = csv_dataset_snapshot(&src_path);
+    let dataset_id = dataset_snapshot.id.clone();
+
+    metadata_repo
+        .borrow_mut()
+        .add_dataset(dataset_snapshot)
+        .unwrap();
+
+    let head_before = metadata_repo
+        .borrow()
+        .get_metadata_chain(&dataset_id)
+        .unwrap()
+        .read_ref(&BlockRef::Head);
+
+    let preview = ingest_svc.preview(&dataset_id, 2, None).unwrap();
+
+    assert_eq!(
+        preview
+            .columns
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>(),
+        ["system_time", "event_time", "city", "population"]
+    );
+    assert_eq!(preview.num_records, 3);
+    assert_eq!(preview.rows.len(), 2);
+    assert_eq!(preview.rows[1][2..], [Some("B".to_owned()), None]);
+
+    // Nothing is committed or cached
+    let head_after = metadata_repo
+        .borrow()
+        .get_metadata_chain(&dataset_id)
+        .unwrap()
+        .read_ref(&BlockRef::Head);
+    assert_eq!(head_before, head_after);
+
+    let dataset_layout = DatasetLayout::new(&volume_layout, &dataset_id);
+    assert!(!dataset_layout.cache_dir.exists());
+    assert!(!dataset_layout.data_dir.exists());
+    assert_eq!(volume_layout.cache_dir.read_dir().unwrap().count(), 0);
+}
+
+#[test]
+fn test_ingest_derivative_dataset() {
+    let tempdir = tempfile::tempdir().unwrap();
+
+    let workspace_layout = WorkspaceLayout::create(tempdir.path()).unwrap();
+
+    let metadata_repo = Rc::new(RefCell::new(MetadataRepositoryImpl::new(&workspace_layout)));
+    let mut ingest_svc = new_ingest_service(&workspace_layout, metadata_repo.clone());
+
+    let root_snapshot = csv_dataset_snapshot(&tempdir.path().join("data.csv"));
+    let root_id = root_snapshot.id.clone();
+
+    let deriv_snapshot = MetadataFactory::dataset_snapshot()
+        .id("foo.bar.deriv")
+        .source(MetadataFactory::dataset_source_deriv([&root_id].iter()).build())
+        .build();
+    let deriv_id = deriv_snapshot.id.clone();
+
+    metadata_repo
+        .borrow_mut()
+        .add_datasets(&mut vec![root_snapshot, deriv_snapshot].into_iter());
+
+    assert_err!(
+        ingest_svc.plan_ingest(&deriv_id),
+        IngestError::NotARootDataset { .. }
+    );
+    assert_err!(
+        ingest_svc.preview(&deriv_id, 10, None),
+        IngestError::NotARootDataset { .. }
+    );
+    assert_err!(
+        ingest_svc.ingest_from(&deriv_id, &mut "a,b\n".as_bytes(), None, None),
+        IngestError::NotARootDataset { .. }
+    );
+}
+
+#[derive(Default)]
+struct SchemaDriftListener {
+    changes: Vec<SchemaChange>,
+}
+
+impl IngestListener for SchemaDriftListener {
+    fn on_schema_drift(&mut self, changes: &[SchemaChange]) {
+        self.changes.extend(changes.iter().cloned());
+    }
+}
+
+#[test]
+fn test_ingest_schema_drift() {
+    let tempdir = tempfile::tempdir().unwrap();
+
+    let workspace_layout = WorkspaceLayout::create(tempdir.path()).unwrap();
+    let volume_layout = VolumeLayout::new(&workspace_layout.local_volume_dir);
+
+    let metadata_repo = Rc::new(RefCell::new(MetadataRepositoryImpl::new(&workspace_layout)));
+    let mut ingest_svc = new_ingest_service(&workspace_layout, metadata_repo.clone());
+
+    // Without explicit schema the columns are taken from the CSV header
+    let dataset_snapshot = MetadataFactory::dataset_snapshot()
+        .id("foo.bar")
+        .source(
+            MetadataFactory::dataset_source_root()
+                .fetch_file(&tempdir.path().join("does-not-exist.csv"))
+                .read(csv_read_step(None))
+                .build(),
+        )
+        .build();
+
+    let dataset_id = dataset_snapshot.id.clone();
+
+    metadata_repo
+        .borrow_mut()
+        .add_dataset(dataset_snapshot)
+        .unwrap();
+
+    metadata_repo
+        .borrow_mut()
+        .set_config(
+            &dataset_id,
+            DatasetConfig {
+                schema_policy: Some(SchemaPolicy {
+                    on_column_added: Some(SchemaChangeAction::Fail),
+                    on_column_removed: None,
+                    on_type_changed: None,
+                }),
+                ..DatasetConfig::default()
+            },
+        )
+        .unwrap();
+
+    let get_head = || {
+        metadata_repo
+            .borrow()
+            .get_metadata_chain(&dataset_id)
+            .unwrap()
+            .read_ref(&BlockRef::Head)
+            .unwrap()
+    };
+
+    // Initial schema is recorded
+    let mut data: &[u8] = b"city,population\nA,1000\n";
+    ingest_svc
+        .ingest_from(&dataset_id, &mut data, None, None)
+        .unwrap();
+
+    let head = get_head();
+    let history = metadata_repo
+        .borrow()
+        .get_schema_history(&dataset_id)
+        .unwrap();
+
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].block_hash, head);
+    assert_eq!(
+        history[0].schema,
+        [
+            "system_time TIMESTAMP",
+            "event_time TIMESTAMP",
+            "city STRING",
+            "population STRING"
+        ]
+    );
+
+    // Added column is rejected by the policy and the data is discarded
+    let mut data: &[u8] = b"city,population,area\nB,2000,10\n";
+    let res = ingest_svc.ingest_from(&dataset_id, &mut data, None, None);
+
+    match res {
+        Err(IngestError::SchemaDrift { changes }) => assert_eq!(
+            changes,
+            [SchemaChange::ColumnAdded {
+                name: "area".to_owned(),
+                data_type: "STRING".to_owned()
+            }]
+        ),
+        _ => panic!("Unexpected result {:?}", res),
+    }
+
+    let dataset_layout = DatasetLayout::new(&volume_layout, &dataset_id);
+    assert_eq!(get_head(), head);
+    assert_eq!(dataset_layout.data_dir.read_dir().unwrap().count(), 1);
+
+    // Removed column only produces a warning by default
+    let listener = Arc::new(Mutex::new(SchemaDriftListener::default()));
+    let mut data: &[u8] = b"city\nC\n";
+    let res = ingest_svc.ingest_from(&dataset_id, &mut data, None, Some(listener.clone()));
+
+    assert!(matches!(res, Ok(IngestResult::Updated { .. })));
+    assert_ne!(get_head(), head);
+    assert_eq!(
+        listener.lock().unwrap().changes,
+        [SchemaChange::ColumnRemoved {
+            name: "population".to_owned(),
+            data_type: "STRING".to_owned()
+        }]
+    );
+
+    let history = metadata_repo
+        .borrow()
+        .get_schema_history(&dataset_id)
+        .unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[1].block_hash, get_head());
+}
+
+#[derive(Default)]
+struct ExpectationsListener {
+    results: Vec<ExpectationResult>,
+}
+
+impl IngestListener for ExpectationsListener {
+    fn on_expectations_checked(&mut self, results: &[ExpectationResult]) {
+        self.results.extend(results.iter().cloned());
+    }
+}
+
+#[test]
+fn test_ingest_expectations() {
+    let tempdir = tempfile::tempdir().unwrap();
+
+    let workspace_layout = WorkspaceLayout::create(tempdir.path()).unwrap();
+    let volume_layout = VolumeLayout::new(&workspace_layout.local_volume_dir);
+
+    let metadata_repo = Rc::new(RefCell::new(MetadataRepositoryImpl::new(&workspace_layout)));
+    let mut ingest_svc = new_ingest_service(&workspace_layout, metadata_repo.clone());
+
+    let dataset_snapshot = MetadataFactory::dataset_snapshot()
+        .id("foo.bar")
+        .source(
+            MetadataFactory::dataset_source_root()
+                .fetch_file(&tempdir.path().join("does-not-exist.csv"))
+                .read(csv_read_step(Some(&["city STRING", "population INT"])))
+                .build(),
+        )
+        .build();
+
+    let dataset_id = dataset_snapshot.id.clone();
+
+    metadata_repo
+        .borrow_mut()
+        .add_dataset(dataset_snapshot)
+        .unwrap();
+
+    metadata_repo
+        .borrow_mut()
+        .set_config(
+            &dataset_id,
+            DatasetConfig {
+                expectations: Some(vec![
+                    Expectation::NotNull(ExpectationNotNull {
+                        columns: vec!["city".to_owned()],
+                        on_violation: Some(ExpectationAction::Quarantine),
+                    }),
+                    Expectation::Range(ExpectationRange {
+                        column: "population".to_owned(),
+                        min: Some(0.into()),
+                        max: None,
+                        on_violation: None,
+                    }),
+                ]),
+                ..DatasetConfig::default()
+            },
+        )
+        .unwrap();
+
+    let dataset_layout = DatasetLayout::new(&volume_layout, &dataset_id);
+
+    // Records without a city are quarantined
+    let listener = Arc::new(Mutex::new(ExpectationsListener::default()));
+    let mut data: &[u8] = b"city,population\nA,1000\n,500\nB,2000\n";
+    let res = ingest_svc.ingest_from(&dataset_id, &mut data, None, Some(listener.clone()));
+
+    let block_hash = match res {
+        Ok(IngestResult::Updated { block_hash, .. }) => block_hash,
+        _ => panic!("Unexpected result {:?}", res),
+    };
+
+    let block = metadata_repo
+        .borrow()
+        .get_metadata_chain(&dataset_id)
+        .unwrap()
+        .get_block(&block_hash)
+        .unwrap();
+
+    let slice = block.output_slice.unwrap();
+    assert_eq!(slice.num_records, 2);
+    assert_eq!(
+        read_parquet_dir(&dataset_layout.data_dir).unwrap()[0].num_rows(),
+        2
+    );
+
+    // Hash is recomputed for the data that was kept
+    let data_files = list_parquet_files(&dataset_layout.data_dir).unwrap();
+    assert_eq!(slice.hash, file_sha3(&data_files[0]).unwrap());
+    assert_eq!(
+        read_parquet_dir(&dataset_layout.quarantine_dir).unwrap()[0].num_rows(),
+        1
+    );
+
+    assert_eq!(
+        listener
+            .lock()
+            .unwrap()
+            .results
+            .iter()
+            .map(|r| (r.outcome, r.num_violations))
+            .collect::<Vec<_>>(),
+        [
+            (ExpectationOutcome::Quarantined, 1),
+            (ExpectationOutcome::Passed, 0)
+        ]
+    );
+
+    // Out of range values fail the whole ingest
+    let mut data: &[u8] = b"city,population\nC,-5\n";
+    let res = ingest_svc.ingest_from(&dataset_id, &mut data, None, None);
+
+    match res {
+        Err(IngestError::ExpectationsFailed { results }) => {
+            assert_eq!(results[1].outcome, ExpectationOutcome::Failed);
+            assert_eq!(results[1].examples, ["row 0: value -5 is out of range"]);
+        }
+        _ => panic!("Unexpected result {:?}", res),
+    }
+
+    let head = metadata_repo
+        .borrow()
+        .get_metadata_chain(&dataset_id)
+        .unwrap()
+        .read_ref(&BlockRef::Head)
+        .unwrap();
+    assert_eq!(head, block_hash);
+    assert_eq!(dataset_layout.data_dir.read_dir().unwrap().count(), 1);
+}
@@ -1,7 +1,10 @@
 use kamu::domain::*;
+use kamu::infra::serde::yaml::*;
 use kamu::infra::*;
 use kamu_test::*;
 
+use chrono::Utc;
+
 #[test]
 fn test_delete_dataset() {
     let tempdir = tempfile::tempdir().unwrap();
@@ -52,3 +55,89 @@ fn test_delete_dataset() {
         Err(DomainError::DoesNotExist { .. })
     ));
 }
+
+#[test]
+fn test_dataset_config() {
+    let tempdir = tempfile::tempdir().unwrap();
+
+    let workspace_layout = WorkspaceLayout::create(tempdir.path()).unwrap();
+    let mut metadata_repo = MetadataRepositoryImpl::new(&workspace_layout);
+
+    let id = DatasetID::try_from("foo").unwrap();
+
+    assert!(matches!(
+        metadata_repo.get_config(id),
+        Err(DomainError::DoesNotExist { .. })
+    ));
+
+    metadata_repo
+        .add_dataset(
+            MetadataFactory::dataset_snapshot()
+                .id("foo")
+                .source(MetadataFactory::dataset_source_root().build())
+                .build(),
+        )
+        .unwrap();
+
+    assert_eq!(
+        metadata_repo.get_config(id).unwrap(),
+        DatasetConfig::default()
+    );
+
+    let config = DatasetConfig {
+        decompress: Some(DecompressConfig {
+            format: ArchiveFormat::TarGz,
+        }),
+        ..DatasetConfig::default()
+    };
+
+    metadata_repo.set_config(id, config.clone()).unwrap();
+    assert_eq!(metadata_repo.get_config(id).unwrap(), config);
+}
+
+#[test]
+fn test_schema_history() {
+    let tempdir = tempfile::tempdir().unwrap();
+
+    let workspace_layout = WorkspaceLayout::create(tempdir.path()).unwrap();
+    let mut metadata_repo = MetadataRepositoryImpl::new(&workspace_layout);
+
+    let id = DatasetID::try_from("foo").unwrap();
+
+    metadata_repo
+        .add_dataset(
+            MetadataFactory::dataset_snapshot()
+                .id("foo")
+                .source(MetadataFactory::dataset_source_root().build())
+                .build(),
+        )
+        .unwrap();
+
+    assert!(metadata_repo.get_schema_history(id).unwrap().is_empty());
+
+    let record = |hash: &str, schema: &[&str]| SchemaRecord {
+        block_hash: hash.to_owned(),
+        system_time: Utc::now(),
+        schema: schema.iter().map(|c| c.to_string()).collect(),
+    };
+
+    metadata_repo
+        .record_schema(id, record("a", &["id BIGINT"]))
+        .unwrap();
+    metadata_repo
+        .record_schema(id, record("b", &["id BIGINT"]))
+        .unwrap();
+    metadata_repo
+        .record_schema(id, record("c", &["id BIGINT", "name STRING"]))
+        .unwrap();
+
+    // Unchanged schema is not recorded again
+    let history = metadata_repo.get_schema_history(id).unwrap();
+    assert_eq!(
+        history
+            .iter()
+            .map(|r| r.block_hash.as_str())
+            .collect::<Vec<_>>(),
+        ["a", "c"]
+    );
+}
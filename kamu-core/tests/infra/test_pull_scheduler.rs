@@ -0,0 +1,279 @@
+use kamu::domain::*;
+use kamu::infra::serde::yaml::*;
+use kamu::infra::*;
+use kamu_test::*;
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+fn id(s: &str) -> DatasetIDBuf {
+    DatasetIDBuf::try_from(s).unwrap()
+}
+
+fn interval(every: &str) -> PollSchedule {
+    PollSchedule::Interval(PollScheduleInterval {
+        every: every.to_owned(),
+    })
+}
+
+fn set_schedule(repo: &RefCell<MetadataRepositoryImpl>, dataset_id: &str, schedule: PollSchedule) {
+    repo.borrow_mut()
+        .set_config(
+            &id(dataset_id),
+            DatasetConfig {
+                schedule: Some(schedule),
+                ..DatasetConfig::default()
+            },
+        )
+        .unwrap();
+}
+
+#[test]
+fn test_parse_interval() {
+    assert_eq!(parse_interval("30s").unwrap(), Duration::seconds(30));
+    assert_eq!(parse_interval("15m").unwrap(), Duration::minutes(15));
+    assert_eq!(
+        parse_interval("1h30m").unwrap(),
+        Duration::hours(1) + Duration::minutes(30)
+    );
+    assert_eq!(parse_interval("2d").unwrap(), Duration::days(2));
+
+    assert!(parse_interval("").is_err());
+    assert!(parse_interval("10").is_err());
+    assert!(parse_interval("h").is_err());
+    assert!(parse_interval("0m").is_err());
+    assert!(parse_interval("5x").is_err());
+}
+
+#[test]
+fn test_next_run_after() {
+    let t = Utc.ymd(2020, 1, 1).and_hms(12, 10, 0);
+
+    assert_eq!(
+        next_run_after(&interval("1h"), t).unwrap(),
+        Utc.ymd(2020, 1, 1).and_hms(13, 10, 0)
+    );
+
+    // Five-field expressions are accepted alongside ones with seconds
+    let cron = |expr: &str| {
+        PollSchedule::Cron(PollScheduleCron {
+            expression: expr.to_owned(),
+        })
+    };
+    assert_eq!(
+        next_run_after(&cron("*/15 * * * *"), t).unwrap(),
+        Utc.ymd(2020, 1, 1).and_hms(12, 15, 0)
+    );
+    assert_eq!(
+        next_run_after(&cron("0 0 6 * * *"), t).unwrap(),
+        Utc.ymd(2020, 1, 2).and_hms(6, 0, 0)
+    );
+    assert!(next_run_after(&cron("not a cron"), t).is_err());
+}
+
+#[test]
+fn test_scheduler_tick() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let workspace_layout = WorkspaceLayout::create(tmp_dir.path()).unwrap();
+    let volume_layout = VolumeLayout::new(&workspace_layout.local_volume_dir);
+    let repo = Rc::new(RefCell::new(MetadataRepositoryImpl::new(&workspace_layout)));
+    let pull_svc = Rc::new(RefCell::new(TestPullService::new()));
+
+    let mut scheduler = PullScheduler::new(
+        repo.clone(),
+        pull_svc.clone(),
+        &volume_layout,
+        slog::Logger::root(slog::Discard, slog::o!()),
+    );
+
+    // A (hourly) <- C
+    // B (daily)
+    // D (no schedule)
+    let root = |id: &str| {
+        MetadataFactory::dataset_snapshot()
+            .id(id)
+            .source(MetadataFactory::dataset_source_root().build())
+            .build()
+    };
+    for snapshot in vec![
+        root("a"),
+        root("b"),
+        MetadataFactory::dataset_snapshot()
+            .id("c")
+            .source(MetadataFactory::dataset_source_deriv([id("a")].iter()).build())
+            .build(),
+        root("d"),
+    ] {
+        repo.borrow_mut().add_dataset(snapshot).unwrap();
+    }
+    set_schedule(&repo, "a", interval("1h"));
+    set_schedule(&repo, "b", interval("1d"));
+
+    let t0 = Utc.ymd(2020, 1, 1).and_hms(12, 0, 0);
+
+    // Never pulled datasets are due right away
+    let next_run = scheduler.tick(t0, None).unwrap();
+    assert_eq!(
+        pull_svc.borrow().calls,
+        vec![vec![id("a"), id("b"), id("c")]]
+    );
+    assert!(next_run.unwrap() > t0);
+
+    let state_a = scheduler.read_state(&id("a")).unwrap().unwrap();
+    assert_eq!(state_a.last_run, Some(t0));
+    assert_eq!(state_a.last_outcome, Some(ScheduleRunOutcome::Updated));
+    assert!(state_a.next_run >= t0 + Duration::hours(1));
+    assert!(scheduler.read_state(&id("d")).unwrap().is_none());
+
+    // Nothing is due before the next run
+    pull_svc.borrow_mut().calls.clear();
+    scheduler.tick(t0 + Duration::minutes(30), None).unwrap();
+    assert!(pull_svc.borrow().calls.is_empty());
+
+    // Only the hourly dataset and its dependents are due
+    scheduler.tick(state_a.next_run, None).unwrap();
+    assert_eq!(pull_svc.borrow().calls, vec![vec![id("a"), id("c")]]);
+
+    // State persists across scheduler instances
+    let scheduler = PullScheduler::new(
+        repo.clone(),
+        pull_svc.clone(),
+        &volume_layout,
+        slog::Logger::root(slog::Discard, slog::o!()),
+    );
+    let scheduled: Vec<(DatasetIDBuf, Option<DateTime<Utc>>)> = scheduler
+        .get_scheduled_datasets(Utc::now(), None)
+        .unwrap()
+        .into_iter()
+        .map(|s| (s.dataset_id, s.state.last_run))
+        .collect();
+    assert_eq!(
+        scheduled,
+        vec![(id("a"), Some(state_a.next_run)), (id("b"), Some(t0))]
+    );
+}
+
+#[test]
+fn test_scheduler_skips_unsupported_sources() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let workspace_layout = WorkspaceLayout::create(tmp_dir.path()).unwrap();
+    let volume_layout = VolumeLayout::new(&workspace_layout.local_volume_dir);
+    let repo = Rc::new(RefCell::new(MetadataRepositoryImpl::new(&workspace_layout)));
+    let pull_svc = Rc::new(RefCell::new(TestPullService::new()));
+
+    let mut scheduler = PullScheduler::new(
+        repo.clone(),
+        pull_svc.clone(),
+        &volume_layout,
+        slog::Logger::root(slog::Discard, slog::o!()),
+    );
+
+    let glob_source = || {
+        MetadataFactory::dataset_source_root().fetch(FetchStep::FilesGlob(FetchStepFilesGlob {
+            path: "/data/*.csv".to_owned(),
+            event_time: None,
+            cache: None,
+            order: None,
+        }))
+    };
+
+    repo.borrow_mut()
+        .add_dataset(
+            MetadataFactory::dataset_snapshot()
+                .id("a")
+                .source(glob_source().build())
+                .build(),
+        )
+        .unwrap();
+    set_schedule(&repo, "a", interval("1h"));
+
+    // Not reported as it is not scheduled
+    repo.borrow_mut()
+        .add_dataset(
+            MetadataFactory::dataset_snapshot()
+                .id("b")
+                .source(glob_source().build())
+                .build(),
+        )
+        .unwrap();
+
+    let listener = Arc::new(Mutex::new(TestSchedulerListener::new()));
+
+    let next_run = scheduler
+        .tick(
+            Utc.ymd(2020, 1, 1).and_hms(12, 0, 0),
+            Some(listener.clone()),
+        )
+        .unwrap();
+
+    assert_eq!(next_run, None);
+    assert!(pull_svc.borrow().calls.is_empty());
+    assert_eq!(listener.lock().unwrap().invalid, vec![id("a")]);
+}
+
+struct TestSchedulerListener {
+    invalid: Vec<DatasetIDBuf>,
+}
+
+impl TestSchedulerListener {
+    fn new() -> Self {
+        Self {
+            invalid: Vec::new(),
+        }
+    }
+}
+
+impl PullSchedulerListener for TestSchedulerListener {
+    fn on_invalid_schedule(&mut self, dataset_id: &DatasetID, _error: &ScheduleError) {
+        self.invalid.push(dataset_id.to_owned());
+    }
+}
+
+pub struct TestPullService {
+    calls: Vec<Vec<DatasetIDBuf>>,
+}
+
+impl TestPullService {
+    pub fn new() -> Self {
+        Self { calls: Vec::new() }
+    }
+}
+
+impl PullService for TestPullService {
+    fn pull_multi(
+        &mut self,
+        dataset_ids: &mut dyn Iterator<Item = &DatasetID>,
+        _recursive: bool,
+        _all: bool,
+        _ingest_listener: Option<Arc<Mutex<dyn IngestMultiListener>>>,
+        _transform_listener: Option<Arc<Mutex<dyn TransformMultiListener>>>,
+    ) -> Vec<(DatasetIDBuf, Result<PullResult, PullError>)> {
+        let ids: Vec<_> = dataset_ids.map(|id| id.to_owned()).collect();
+        let results = ids
+            .iter()
+            .map(|id| {
+                (
+                    id.clone(),
+                    Ok(PullResult::Updated {
+                        block_hash: "".to_owned(),
+                        stats: UpdateStats::default(),
+                    }),
+                )
+            })
+            .collect();
+        self.calls.push(ids);
+        results
+    }
+
+    fn plan_pull(
+        &mut self,
+        _dataset_ids: &mut dyn Iterator<Item = &DatasetID>,
+        _recursive: bool,
+        _all: bool,
+    ) -> Result<Vec<PullPlanStep>, PullError> {
+        unimplemented!();
+    }
+}
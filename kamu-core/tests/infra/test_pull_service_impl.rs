@@ -2,6 +2,7 @@ use kamu::domain::*;
 use kamu::infra::*;
 use kamu_test::*;
 
+use chrono::{DateTime, Utc};
 use itertools::Itertools;
 use std::cell::RefCell;
 use std::convert::TryFrom;
@@ -34,7 +35,7 @@ fn create_graph(
 }
 
 #[test]
-fn test_pull_batching() {
+fn test_pull_ordering() {
     let tmp_dir = tempfile::tempdir().unwrap();
     let repo = Rc::new(RefCell::new(MetadataRepositoryImpl::new(
         &WorkspaceLayout::create(tmp_dir.path()).unwrap(),
@@ -76,7 +77,7 @@ fn test_pull_batching() {
 
     assert!(test_ingest_svc.borrow().calls.is_empty());
 
-    assert_eq!(test_transform_svc.borrow().calls, vec![vec![id("e")]]);
+    assert_eq!(test_transform_svc.borrow().calls, vec![id("e")]);
 
     test_transform_svc.borrow_mut().calls.clear();
 
@@ -88,21 +89,185 @@ fn test_pull_batching() {
         None,
     );
 
-    assert_eq!(test_ingest_svc.borrow().calls, vec![vec![id("a"), id("b")]]);
+    assert_eq!(test_ingest_svc.borrow().calls, vec![id("a"), id("b")]);
 
+    // E starts only when all of its inputs are done
     assert_eq!(
         test_transform_svc.borrow().calls,
-        vec![vec![id("c"), id("d")], vec![id("e")]]
+        vec![id("c"), id("d"), id("e")]
     );
 }
 
+#[test]
+fn test_pull_skips_dependents_of_failed() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let repo = Rc::new(RefCell::new(MetadataRepositoryImpl::new(
+        &WorkspaceLayout::create(tmp_dir.path()).unwrap(),
+    )));
+    let test_ingest_svc = Rc::new(RefCell::new(TestIngestService::new()));
+    let test_transform_svc = Rc::new(RefCell::new(TestTransformService::new()));
+    let mut pull_svc = PullServiceImpl::new(
+        repo.clone(),
+        test_ingest_svc.clone(),
+        test_transform_svc.clone(),
+        slog::Logger::root(slog::Discard, slog::o!()),
+    );
+
+    // A - C - E
+    // B - D
+    create_graph(
+        &mut repo.borrow_mut(),
+        vec![
+            (id("a"), None),
+            (id("b"), None),
+            (id("c"), Some(id("a"))),
+            (id("d"), Some(id("b"))),
+            (id("e"), Some(id("c"))),
+        ],
+    );
+
+    test_ingest_svc.borrow_mut().fail.push(id("a"));
+
+    let results = pull_svc.pull_multi(
+        &mut [id("d"), id("e")].iter().map(|id| id.as_ref()),
+        true,
+        false,
+        None,
+        None,
+    );
+
+    assert_eq!(test_ingest_svc.borrow().calls, vec![id("a"), id("b")]);
+    assert_eq!(test_transform_svc.borrow().calls, vec![id("d")]);
+
+    let ids: Vec<_> = results.iter().map(|(id, _)| id.clone()).collect();
+    assert_eq!(ids, vec![id("a"), id("b"), id("c"), id("d"), id("e")]);
+
+    let input_failed = SkipReason::InputFailed {
+        dataset_id: id("a"),
+    };
+    assert!(matches!(results[0].1, Err(PullError::IngestError(_))));
+    assert!(matches!(results[1].1, Ok(PullResult::UpToDate { .. })));
+    assert!(
+        matches!(results[2].1, Ok(PullResult::Skipped { ref reason }) if *reason == input_failed)
+    );
+    assert!(matches!(results[3].1, Ok(PullResult::UpToDate { .. })));
+    assert!(
+        matches!(results[4].1, Ok(PullResult::Skipped { ref reason }) if *reason == input_failed)
+    );
+}
+
+#[test]
+fn test_pull_cancelled() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let repo = Rc::new(RefCell::new(MetadataRepositoryImpl::new(
+        &WorkspaceLayout::create(tmp_dir.path()).unwrap(),
+    )));
+    let cancel = CancellationToken::new();
+    let test_ingest_svc = Rc::new(RefCell::new(TestIngestService::new()));
+    let test_transform_svc = Rc::new(RefCell::new(TestTransformService::new()));
+    let mut pull_svc = PullServiceImpl::new(
+        repo.clone(),
+        test_ingest_svc.clone(),
+        test_transform_svc.clone(),
+        slog::Logger::root(slog::Discard, slog::o!()),
+    )
+    .with_cancellation(cancel.clone());
+
+    create_graph(
+        &mut repo.borrow_mut(),
+        vec![(id("a"), None), (id("b"), None), (id("c"), Some(id("a")))],
+    );
+
+    // Cancellation arrives while root datasets are being ingested
+    test_ingest_svc.borrow_mut().cancel = Some(cancel.clone());
+
+    let results = pull_svc.pull_multi(
+        &mut [id("c")].iter().map(|id| id.as_ref()),
+        true,
+        false,
+        None,
+        None,
+    );
+
+    assert_eq!(test_ingest_svc.borrow().calls[0], id("a"));
+    assert!(test_transform_svc.borrow().calls.is_empty());
+
+    let ids: Vec<_> = results.iter().map(|(id, _)| id.clone()).collect();
+    assert_eq!(ids, vec![id("a"), id("b"), id("c")]);
+    assert!(results
+        .iter()
+        .all(|(_, res)| matches!(res, Err(PullError::Cancelled(_)))));
+}
+
+#[test]
+fn test_pull_plan() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let repo = Rc::new(RefCell::new(MetadataRepositoryImpl::new(
+        &WorkspaceLayout::create(tmp_dir.path()).unwrap(),
+    )));
+    let test_ingest_svc = Rc::new(RefCell::new(TestIngestService::new()));
+    let test_transform_svc = Rc::new(RefCell::new(TestTransformService::new()));
+    let mut pull_svc = PullServiceImpl::new(
+        repo.clone(),
+        test_ingest_svc.clone(),
+        test_transform_svc.clone(),
+        slog::Logger::root(slog::Discard, slog::o!()),
+    );
+
+    // A - C
+    //      > D
+    // B - -
+    create_graph(
+        &mut repo.borrow_mut(),
+        vec![
+            (id("a"), None),
+            (id("b"), None),
+            (id("c"), Some(id("a"))),
+            (id("d"), Some(id("c"))),
+            (id("d"), Some(id("b"))),
+        ],
+    );
+
+    let plan = pull_svc
+        .plan_pull(&mut [id("d")].iter().map(|id| id.as_ref()), true, false)
+        .unwrap();
+
+    let ids: Vec<_> = plan.iter().map(|s| s.dataset_id.clone()).collect();
+    assert_eq!(ids, vec![id("a"), id("b"), id("c"), id("d")]);
+    assert!(matches!(plan[0].action, PullAction::Ingest(_)));
+    assert!(matches!(plan[1].action, PullAction::Ingest(_)));
+    assert!(matches!(plan[2].action, PullAction::Transform(_)));
+    assert!(matches!(plan[3].action, PullAction::Transform(_)));
+    assert_eq!(plan[2].depends_on, vec![id("a")]);
+    assert_eq!(plan[3].depends_on, vec![id("c"), id("b")]);
+
+    // Dependencies outside of the plan are not listed
+    let plan = pull_svc
+        .plan_pull(&mut [id("d")].iter().map(|id| id.as_ref()), false, false)
+        .unwrap();
+    assert_eq!(plan.len(), 1);
+    assert!(plan[0].depends_on.is_empty());
+
+    // Nothing is executed
+    assert!(test_ingest_svc.borrow().calls.is_empty());
+    assert!(test_transform_svc.borrow().calls.is_empty());
+}
+
 pub struct TestIngestService {
-    calls: Vec<Vec<DatasetIDBuf>>,
+    calls: Vec<DatasetIDBuf>,
+    /// Token to cancel during ingestion
+    cancel: Option<CancellationToken>,
+    /// Datasets whose ingestion fails
+    fail: Vec<DatasetIDBuf>,
 }
 
 impl TestIngestService {
     pub fn new() -> Self {
-        Self { calls: Vec::new() }
+        Self {
+            calls: Vec::new(),
+            cancel: None,
+            fail: Vec::new(),
+        }
     }
 }
 
@@ -117,21 +282,73 @@ impl IngestService for TestIngestService {
 
     fn ingest_multi(
         &mut self,
-        dataset_ids: &mut dyn Iterator<Item = &DatasetID>,
+        _dataset_ids: &mut dyn Iterator<Item = &DatasetID>,
         _maybe_multi_listener: Option<Arc<Mutex<dyn IngestMultiListener>>>,
     ) -> Vec<(DatasetIDBuf, Result<IngestResult, IngestError>)> {
-        let ids: Vec<_> = dataset_ids.map(|id| id.to_owned()).collect();
-        let results = ids
-            .iter()
-            .map(|id| (id.clone(), Ok(IngestResult::UpToDate)))
-            .collect();
-        self.calls.push(ids);
-        results
+        unimplemented!();
+    }
+
+    fn prepare_ingest(
+        &mut self,
+        dataset_id: &DatasetID,
+        _maybe_multi_listener: Option<Arc<Mutex<dyn IngestMultiListener>>>,
+    ) -> Result<IngestJob, IngestError> {
+        self.calls.push(dataset_id.to_owned());
+        let cancel = self.cancel.clone();
+        let fail = self.fail.contains(&dataset_id.to_owned());
+        Ok(IngestJob {
+            engine: None,
+            run: Box::new(move || match cancel {
+                Some(cancel) => {
+                    cancel.cancel();
+                    Err(IngestError::Cancelled(CancelledError))
+                }
+                None if fail => Err(IngestError::unreachable("http://example.com/data", None)),
+                None => Ok(IngestResult::UpToDate {
+                    stats: UpdateStats::default(),
+                }),
+            }),
+        })
+    }
+
+    fn complete_ingest(
+        &mut self,
+        _dataset_id: &DatasetID,
+        result: Result<IngestResult, IngestError>,
+    ) -> Result<IngestResult, IngestError> {
+        result
+    }
+
+    fn plan_ingest(&mut self, _dataset_id: &DatasetID) -> Result<IngestPlan, IngestError> {
+        Ok(IngestPlan {
+            source: "http://example.com/data".to_owned(),
+            cache: FetchCacheState::Empty,
+            engine: None,
+        })
+    }
+
+    fn preview(
+        &mut self,
+        _dataset_id: &DatasetID,
+        _num_rows: usize,
+        _maybe_listener: Option<Arc<Mutex<dyn IngestListener>>>,
+    ) -> Result<IngestPreview, IngestError> {
+        unimplemented!();
+    }
+
+    fn ingest_from(
+        &mut self,
+        _dataset_id: &DatasetID,
+        _data: &mut dyn std::io::Read,
+        _event_time: Option<DateTime<Utc>>,
+        _maybe_listener: Option<Arc<Mutex<dyn IngestListener>>>,
+    ) -> Result<IngestResult, IngestError> {
+        unimplemented!();
     }
 }
 
 pub struct TestTransformService {
-    calls: Vec<Vec<DatasetIDBuf>>,
+    calls: Vec<DatasetIDBuf>,
 }
 
 impl TestTransformService {
@@ -151,15 +368,36 @@ impl TransformService for TestTransformService {
 
     fn transform_multi(
         &mut self,
-        dataset_ids: &mut dyn Iterator<Item = &DatasetID>,
+        _dataset_ids: &mut dyn Iterator<Item = &DatasetID>,
         _maybe_multi_listener: Option<Arc<Mutex<dyn TransformMultiListener>>>,
     ) -> Vec<(DatasetIDBuf, Result<TransformResult, TransformError>)> {
-        let ids: Vec<_> = dataset_ids.map(|id| id.to_owned()).collect();
-        let results = ids
-            .iter()
-            .map(|id| (id.clone(), Ok(TransformResult::UpToDate)))
-            .collect();
-        self.calls.push(ids);
-        results
+        unimplemented!();
+    }
+
+    fn plan_transform(&mut self, _dataset_id: &DatasetID) -> Result<TransformPlan, TransformError> {
+        Ok(TransformPlan {
+            engine: "sparkSQL".to_owned(),
+            batches: Vec::new(),
+        })
+    }
+
+    fn prepare_transform(
+        &mut self,
+        dataset_id: &DatasetID,
+        _maybe_multi_listener: Option<Arc<Mutex<dyn TransformMultiListener>>>,
+    ) -> Result<TransformJob, TransformError> {
+        self.calls.push(dataset_id.to_owned());
+        Ok(TransformJob {
+            engine: None,
+            run: Box::new(|| Ok(TransformResult::UpToDate)),
+        })
+    }
+
+    fn complete_transform(
+        &mut self,
+        _dataset_id: &DatasetID,
+        result: Result<TransformResult, TransformError>,
+    ) -> Result<TransformResult, TransformError> {
+        result
     }
 }
@@ -0,0 +1,95 @@
+use kamu::domain::*;
+use kamu::infra::serde::yaml::*;
+use kamu::infra::*;
+
+use std::convert::TryFrom;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use url::Url;
+
+fn id(s: &str) -> DatasetIDBuf {
+    DatasetIDBuf::try_from(s).unwrap()
+}
+
+fn fetch_url(path: &Path) -> FetchStep {
+    FetchStep::Url(FetchStepUrl {
+        url: Url::from_file_path(path).unwrap().as_str().to_owned(),
+        event_time: None,
+        cache: None,
+    })
+}
+
+fn fetch_glob(path: &str) -> FetchStep {
+    FetchStep::FilesGlob(FetchStepFilesGlob {
+        path: path.to_owned(),
+        event_time: None,
+        cache: None,
+        order: None,
+    })
+}
+
+#[test]
+fn test_watch_target() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let dir = tmp_dir.path().canonicalize().unwrap();
+
+    let target = get_watch_target(&fetch_url(&dir.join("data.csv"))).unwrap();
+    assert_eq!(target.dir, dir);
+    assert!(target.pattern.matches_path(&dir.join("data.csv")));
+    assert!(!target.pattern.matches_path(&dir.join("other.csv")));
+
+    // Globs can't be fetched yet
+    assert_eq!(
+        get_watch_target(&fetch_glob(&format!("{}/*.csv", dir.display()))),
+        None
+    );
+
+    assert_eq!(
+        get_watch_target(&FetchStep::Url(FetchStepUrl {
+            url: "http://example.com/data.csv".to_owned(),
+            event_time: None,
+            cache: None,
+        })),
+        None
+    );
+}
+
+#[test]
+fn test_watch_file_changes() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let dir = tmp_dir.path().canonicalize().unwrap();
+
+    let mut watcher = SourceWatcher::new(
+        Duration::from_millis(100),
+        slog::Logger::root(slog::Discard, slog::o!()),
+    )
+    .unwrap();
+
+    let errors = watcher.sync(&[
+        (id("a"), fetch_url(&dir.join("a.csv"))),
+        (id("b"), fetch_url(&dir.join("b.csv"))),
+        (id("c"), fetch_url(&dir.join("missing").join("c.csv"))),
+    ]);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].dataset_ids, vec![id("c")]);
+
+    let wait_for_changes = |watcher: &mut SourceWatcher| {
+        let start = Instant::now();
+        loop {
+            let changed = watcher.poll(Duration::from_millis(100)).unwrap();
+            if !changed.is_empty() || start.elapsed() > Duration::from_secs(5) {
+                return changed;
+            }
+        }
+    };
+
+    std::fs::write(dir.join("a.csv"), "1,2,3").unwrap();
+    assert_eq!(wait_for_changes(&mut watcher), vec![id("a")]);
+
+    std::fs::write(dir.join("b.csv"), "1,2,3").unwrap();
+    assert_eq!(wait_for_changes(&mut watcher), vec![id("b")]);
+
+    // Unrelated files are ignored
+    std::fs::write(dir.join("other.csv"), "1,2,3").unwrap();
+    assert!(wait_for_changes(&mut watcher).is_empty());
+}
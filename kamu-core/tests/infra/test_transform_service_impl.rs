@@ -6,7 +6,7 @@ use kamu_test::*;
 use chrono::{DateTime, TimeZone, Utc};
 use std::cell::RefCell;
 use std::rc::Rc;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 
 macro_rules! map(
     { } => { ::std::collections::BTreeMap::new() };
@@ -54,11 +54,21 @@ fn new_deriv(
 fn append_data_block(
     metadata_repo: &Rc<RefCell<MetadataRepositoryImpl>>,
     id: &DatasetID,
+) -> DateTime<Utc> {
+    let system_time = MetadataFactory::metadata_block().build().system_time;
+    append_data_block_at(metadata_repo, id, system_time)
+}
+
+fn append_data_block_at(
+    metadata_repo: &Rc<RefCell<MetadataRepositoryImpl>>,
+    id: &DatasetID,
+    system_time: DateTime<Utc>,
 ) -> DateTime<Utc> {
     let mut chain = metadata_repo.borrow_mut().get_metadata_chain(id).unwrap();
     chain.append(
         MetadataFactory::metadata_block()
             .prev(&chain.read_ref(&BlockRef::Head).unwrap())
+            .system_time(system_time)
             .output_slice(DataSlice {
                 hash: "12345".to_owned(),
                 num_records: 100,
@@ -81,7 +91,7 @@ fn test_get_next_operation() {
     let transform_svc = TransformServiceImpl::new(
         metadata_repo.clone(),
         // TODO: Use a mock
-        Arc::new(Mutex::new(EngineFactory::new(&workspace_layout))),
+        Arc::new(EngineFactory::new(&workspace_layout)),
         &volume_layout,
         slog::Logger::root(slog::Discard, slog::o!()),
     );
@@ -107,3 +117,166 @@ fn test_get_next_operation() {
         }}
     ));
 }
+
+#[test]
+fn test_get_operations_batching() {
+    let tempdir = tempfile::tempdir().unwrap();
+    let workspace_layout = WorkspaceLayout::create(tempdir.path()).unwrap();
+    let volume_layout = VolumeLayout::new(&workspace_layout.local_volume_dir);
+
+    let metadata_repo = Rc::new(RefCell::new(MetadataRepositoryImpl::new(&workspace_layout)));
+    let new_transform_svc = |batching: TransformBatchOptions| {
+        TransformServiceImpl::new(
+            metadata_repo.clone(),
+            // TODO: Use a mock
+            Arc::new(EngineFactory::new(&workspace_layout)),
+            &volume_layout,
+            slog::Logger::root(slog::Discard, slog::o!()),
+        )
+        .with_batching(batching)
+    };
+
+    let foo = new_root(&metadata_repo, "foo");
+    let (bar, _) = new_deriv(&metadata_repo, "bar", &[foo]);
+
+    let t0 = metadata_repo
+        .borrow()
+        .get_metadata_chain(foo)
+        .unwrap()
+        .iter_blocks()
+        .next()
+        .unwrap()
+        .system_time;
+    let t1 = append_data_block_at(&metadata_repo, foo, t0 + chrono::Duration::hours(1));
+    let t2 = append_data_block_at(&metadata_repo, foo, t0 + chrono::Duration::hours(2));
+    let t3 = append_data_block_at(&metadata_repo, foo, t0 + chrono::Duration::hours(3));
+
+    let watermark = |system_time: DateTime<Utc>| Watermark {
+        system_time: system_time,
+        event_time: Utc.ymd(2020, 1, 1).and_hms(10, 0, 0),
+    };
+
+    // No limits - everything in one batch
+    let ops = new_transform_svc(TransformBatchOptions::default())
+        .get_operations(bar)
+        .unwrap();
+    assert_eq!(ops.len(), 1);
+    assert_eq!(
+        ops[0].input_slices,
+        map! { foo.to_owned() => InputDataSlice {
+            interval: TimeInterval::unbounded_closed_right(t3),
+            explicit_watermarks: vec![watermark(t1), watermark(t2), watermark(t3)],
+        }}
+    );
+
+    // Limited by number of blocks
+    let ops = new_transform_svc(TransformBatchOptions {
+        max_input_blocks: Some(2),
+        max_interval: None,
+    })
+    .get_operations(bar)
+    .unwrap();
+    let slices: Vec<_> = ops.into_iter().map(|op| op.input_slices).collect();
+    assert_eq!(
+        slices,
+        vec![
+            map! { foo.to_owned() => InputDataSlice {
+                interval: TimeInterval::unbounded_closed_right(t1),
+                explicit_watermarks: vec![watermark(t1)],
+            }},
+            map! { foo.to_owned() => InputDataSlice {
+                interval: TimeInterval::left_half_open(t1, t3).unwrap(),
+                explicit_watermarks: vec![watermark(t2), watermark(t3)],
+            }},
+        ]
+    );
+
+    // Limited by system time span
+    let ops = new_transform_svc(TransformBatchOptions {
+        max_input_blocks: None,
+        max_interval: Some(chrono::Duration::minutes(90)),
+    })
+    .get_operations(bar)
+    .unwrap();
+    let intervals: Vec<_> = ops
+        .into_iter()
+        .map(|mut op| op.input_slices.remove(foo).unwrap().interval)
+        .collect();
+    assert_eq!(
+        intervals,
+        vec![
+            TimeInterval::unbounded_closed_right(t1),
+            TimeInterval::left_half_open(t1, t3).unwrap(),
+        ]
+    );
+}
+
+#[test]
+fn test_get_operations_batching_aligns_inputs() {
+    let tempdir = tempfile::tempdir().unwrap();
+    let workspace_layout = WorkspaceLayout::create(tempdir.path()).unwrap();
+    let volume_layout = VolumeLayout::new(&workspace_layout.local_volume_dir);
+
+    let metadata_repo = Rc::new(RefCell::new(MetadataRepositoryImpl::new(&workspace_layout)));
+    let transform_svc = TransformServiceImpl::new(
+        metadata_repo.clone(),
+        // TODO: Use a mock
+        Arc::new(EngineFactory::new(&workspace_layout)),
+        &volume_layout,
+        slog::Logger::root(slog::Discard, slog::o!()),
+    )
+    .with_batching(TransformBatchOptions {
+        max_input_blocks: None,
+        max_interval: Some(chrono::Duration::minutes(90)),
+    });
+
+    let foo = new_root(&metadata_repo, "foo");
+    let baz = new_root(&metadata_repo, "baz");
+    let (bar, _) = new_deriv(&metadata_repo, "bar", &[foo, baz]);
+
+    let t0 = metadata_repo
+        .borrow()
+        .get_metadata_chain(foo)
+        .unwrap()
+        .iter_blocks()
+        .next()
+        .unwrap()
+        .system_time;
+    append_data_block_at(&metadata_repo, foo, t0 + chrono::Duration::minutes(60));
+    let t2 = append_data_block_at(&metadata_repo, foo, t0 + chrono::Duration::minutes(120));
+    let t3 = append_data_block_at(&metadata_repo, foo, t0 + chrono::Duration::minutes(180));
+
+    let b1 = append_data_block_at(&metadata_repo, baz, t0 + chrono::Duration::minutes(30));
+    let b2 = append_data_block_at(&metadata_repo, baz, t0 + chrono::Duration::minutes(150));
+
+    let intervals: Vec<_> = transform_svc
+        .get_operations(bar)
+        .unwrap()
+        .into_iter()
+        .map(|mut op| {
+            (
+                op.input_slices.remove(foo).unwrap().interval,
+                op.input_slices.remove(baz).unwrap().interval,
+            )
+        })
+        .collect();
+
+    // Every batch is cut at the same system time across inputs
+    assert_eq!(
+        intervals,
+        vec![
+            (
+                TimeInterval::unbounded_closed_right(t0),
+                TimeInterval::unbounded_closed_right(b1),
+            ),
+            (
+                TimeInterval::left_half_open(t0, t2).unwrap(),
+                TimeInterval::empty(),
+            ),
+            (
+                TimeInterval::left_half_open(t2, t3).unwrap(),
+                TimeInterval::left_half_open(b1, b2).unwrap(),
+            ),
+        ]
+    );
+}
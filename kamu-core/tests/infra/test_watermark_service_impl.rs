@@ -0,0 +1,79 @@
+use kamu::domain::*;
+use kamu::infra::*;
+use kamu_test::*;
+
+use chrono::{TimeZone, Utc};
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::rc::Rc;
+
+fn id(s: &str) -> DatasetIDBuf {
+    DatasetIDBuf::try_from(s).unwrap()
+}
+
+#[test]
+fn test_set_watermark() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let repo = Rc::new(RefCell::new(MetadataRepositoryImpl::new(
+        &WorkspaceLayout::create(tmp_dir.path()).unwrap(),
+    )));
+    let mut watermark_svc =
+        WatermarkServiceImpl::new(repo.clone(), slog::Logger::root(slog::Discard, slog::o!()));
+
+    repo.borrow_mut().add_datasets(
+        &mut vec![
+            MetadataFactory::dataset_snapshot()
+                .id("a")
+                .source(MetadataFactory::dataset_source_root().build())
+                .build(),
+            MetadataFactory::dataset_snapshot()
+                .id("b")
+                .source(MetadataFactory::dataset_source_deriv(["a"].iter()).build())
+                .build(),
+        ]
+        .into_iter(),
+    );
+
+    let num_blocks = || {
+        repo.borrow()
+            .get_metadata_chain(&id("a"))
+            .unwrap()
+            .iter_blocks()
+            .count()
+    };
+    assert_eq!(num_blocks(), 1);
+
+    let t1 = Utc.ymd(2020, 1, 1).and_hms(12, 0, 0);
+    let t2 = Utc.ymd(2020, 1, 2).and_hms(12, 0, 0);
+
+    let res = watermark_svc.set_watermark(&id("a"), t2);
+    assert!(matches!(res, Ok(WatermarkResult::Updated { .. })));
+    assert_eq!(num_blocks(), 2);
+
+    let head = repo
+        .borrow()
+        .get_metadata_chain(&id("a"))
+        .unwrap()
+        .iter_blocks()
+        .next()
+        .unwrap();
+    assert_eq!(head.output_watermark, Some(t2));
+    assert_eq!(head.output_slice, None);
+
+    // Setting a watermark is not a pull
+    assert_eq!(
+        repo.borrow().get_summary(&id("a")).unwrap().last_pulled,
+        None
+    );
+
+    let res = watermark_svc.set_watermark(&id("a"), t2);
+    assert!(matches!(res, Ok(WatermarkResult::UpToDate)));
+    assert_eq!(num_blocks(), 2);
+
+    let res = watermark_svc.set_watermark(&id("a"), t1);
+    assert!(matches!(res, Err(WatermarkError::NotMonotonic { .. })));
+    assert_eq!(num_blocks(), 2);
+
+    let res = watermark_svc.set_watermark(&id("b"), t2);
+    assert!(matches!(res, Err(WatermarkError::NotARootDataset { .. })));
+}
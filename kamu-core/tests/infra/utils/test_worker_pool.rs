@@ -0,0 +1,224 @@
+use kamu::infra::utils::worker_pool::*;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Tracks the number of simultaneously running tasks per engine
+#[derive(Default)]
+struct Tracker {
+    running: Mutex<HashMap<Option<String>, usize>>,
+    max: Mutex<HashMap<Option<String>, usize>>,
+    total_max: AtomicUsize,
+}
+
+impl Tracker {
+    fn task(
+        tracker: &Arc<Self>,
+        engine: Option<&str>,
+        result: usize,
+    ) -> (Option<String>, Task<usize>) {
+        let engine = engine.map(|e| e.to_owned());
+        let tracker = tracker.clone();
+        let key = engine.clone();
+
+        let task: Task<usize> = Box::new(move || {
+            {
+                let mut running = tracker.running.lock().unwrap();
+                let n = running.entry(key.clone()).or_insert(0);
+                *n += 1;
+                let mut max = tracker.max.lock().unwrap();
+                let m = max.entry(key.clone()).or_insert(0);
+                *m = (*m).max(*n);
+                let total: usize = running.values().sum();
+                if total > tracker.total_max.load(Ordering::SeqCst) {
+                    tracker.total_max.store(total, Ordering::SeqCst);
+                }
+            }
+
+            std::thread::sleep(Duration::from_millis(20));
+
+            *tracker.running.lock().unwrap().get_mut(&key).unwrap() -= 1;
+            result
+        });
+
+        (engine, task)
+    }
+
+    fn max(&self, engine: Option<&str>) -> usize {
+        self.max
+            .lock()
+            .unwrap()
+            .get(&engine.map(|e| e.to_owned()))
+            .cloned()
+            .unwrap_or(0)
+    }
+}
+
+#[test]
+fn test_run_bounded_jobs() {
+    let tracker = Arc::new(Tracker::default());
+    let tasks = (0..10).map(|i| Tracker::task(&tracker, None, i)).collect();
+
+    let results = run_bounded(
+        tasks,
+        &ConcurrencyOptions {
+            jobs: 3,
+            engine_jobs: HashMap::new(),
+        },
+        "test",
+    );
+
+    assert_eq!(results, (0..10).collect::<Vec<_>>());
+    assert!(tracker.total_max.load(Ordering::SeqCst) <= 3);
+}
+
+#[test]
+fn test_run_bounded_engine_jobs() {
+    let tracker = Arc::new(Tracker::default());
+    let mut tasks = Vec::new();
+    for i in 0..6 {
+        tasks.push(Tracker::task(&tracker, Some("sparkSQL"), i));
+    }
+    for i in 6..12 {
+        tasks.push(Tracker::task(&tracker, None, i));
+    }
+
+    let mut engine_jobs = HashMap::new();
+    engine_jobs.insert("sparkSQL".to_owned(), 1);
+
+    let results = run_bounded(
+        tasks,
+        &ConcurrencyOptions {
+            jobs: 4,
+            engine_jobs: engine_jobs,
+        },
+        "test",
+    );
+
+    assert_eq!(results, (0..12).collect::<Vec<_>>());
+    assert_eq!(tracker.max(Some("sparkSQL")), 1);
+    assert!(tracker.total_max.load(Ordering::SeqCst) <= 4);
+    // Tasks blocked by the engine limit let the others run
+    assert!(tracker.max(None) > 1);
+}
+
+#[test]
+fn test_run_bounded_empty() {
+    let results: Vec<usize> = run_bounded(Vec::new(), &ConcurrencyOptions::default(), "test");
+    assert!(results.is_empty());
+}
+
+#[test]
+fn test_run_graph_starts_when_dependencies_finish() {
+    // slow
+    // fast - fast_child
+    let nodes = vec![
+        ("slow", vec![]),
+        ("fast", vec![]),
+        ("fast_child", vec!["fast"]),
+    ];
+
+    let results = run_graph(
+        nodes,
+        &ConcurrencyOptions {
+            jobs: 2,
+            engine_jobs: HashMap::new(),
+        },
+        "test",
+        |key| {
+            let delay = if *key == "slow" { 300 } else { 20 };
+            let task: Task<()> = Box::new(move || std::thread::sleep(Duration::from_millis(delay)));
+            (None, task)
+        },
+        |_, _| Ok::<_, ()>(()),
+    );
+
+    // Child doesn't wait for unrelated slow node
+    let finished: Vec<_> = results.finished.iter().map(|(k, _)| *k).collect();
+    assert_eq!(finished, vec!["fast", "fast_child", "slow"]);
+    assert!(results.blocked.is_empty());
+}
+
+#[test]
+fn test_run_graph_failure_blocks_dependents() {
+    // Only D and F don't depend on A
+    let nodes = vec![
+        ("a", vec![]),
+        ("b", vec!["a"]),
+        ("c", vec!["b", "e"]),
+        ("d", vec![]),
+        ("e", vec!["a", "d"]),
+        ("f", vec!["d"]),
+    ];
+
+    let mut started = Vec::new();
+
+    let results = run_graph(
+        nodes,
+        &ConcurrencyOptions::default(),
+        "test",
+        |key| {
+            started.push(*key);
+            let key = *key;
+            let task: Task<bool> = Box::new(move || key != "a");
+            (None, task)
+        },
+        |_, ok| if ok { Ok(()) } else { Err(()) },
+    );
+
+    started.sort();
+    assert_eq!(started, vec!["a", "d", "f"]);
+
+    let mut finished: Vec<_> = results
+        .finished
+        .iter()
+        .map(|(k, res)| (*k, res.is_ok()))
+        .collect();
+    finished.sort();
+    assert_eq!(finished, vec![("a", false), ("d", true), ("f", true)]);
+
+    assert_eq!(results.blocked, vec![("b", "a"), ("c", "a"), ("e", "a")]);
+}
+
+#[test]
+fn test_run_graph_failure_does_not_stop_others() {
+    let nodes = vec![
+        ("fail", vec![]),
+        ("slow", vec![]),
+        ("slow_child", vec!["slow"]),
+    ];
+
+    let results = run_graph(
+        nodes,
+        &ConcurrencyOptions {
+            jobs: 2,
+            engine_jobs: HashMap::new(),
+        },
+        "test",
+        |key| {
+            let key = *key;
+            let task: Task<bool> = Box::new(move || {
+                if key == "slow" {
+                    std::thread::sleep(Duration::from_millis(100));
+                }
+                key != "fail"
+            });
+            (None, task)
+        },
+        |_, ok| if ok { Ok(()) } else { Err(()) },
+    );
+
+    // Nodes that don't depend on the failed one still start after the failure
+    let finished: Vec<_> = results
+        .finished
+        .iter()
+        .map(|(k, res)| (*k, res.is_ok()))
+        .collect();
+    assert_eq!(
+        finished,
+        vec![("fail", false), ("slow", true), ("slow_child", true)]
+    );
+    assert!(results.blocked.is_empty());
+}